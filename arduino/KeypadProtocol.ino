@@ -1,15 +1,63 @@
 // https://www.arduino.cc/reference/en/libraries/multitapkeypad/
 #include <MultitapKeypad.h>
+
+// Protocol (see src/sdl2_interaction/keypad.rs), the host sends:
+// - HELLO VERSION: answered with HELLO VERSION ID_HIGH ID_LOW CAPABILITIES, VERSION being the
+//   version both speak; turns the buzzer and the LEDs off
+// - POLL: answered with STATUS HIGH LOW, bit n of HIGH LOW being key n (row by row) and
+//   STATUS_AMBIGUOUS being set if keys of several rows and columns are pressed
+// - BUZZER ON: turns the buzzer on (1) or off (0)
+// - LEDS HIGH LOW: turns on the LEDs of the set bits (in the order of the keys)
+// Version 0 (without HELLO): LEGACY_HANDSHAKE is answered with itself and POLL with HIGH LOW,
+// 0xFFFF meaning ambiguous.
+const byte PROTOCOL_VERSION = 1;
+const byte HELLO = 0xFC;
+const byte LEGACY_HANDSHAKE = 0xFB;
+const byte POLL = 0x01;
+const byte BUZZER = 0x02;
+const byte LEDS = 0x03;
+const byte CAPABILITY_BUZZER = 1 << 0;
+const byte CAPABILITY_LEDS = 1 << 1;
+const byte STATUS_AMBIGUOUS = 1 << 0;
+
+// change it to tell several keypads apart
+const unsigned int DEVICE_ID = 0x0001;
+// set to false if the parts aren't connected
+const bool HAS_BUZZER = true;
+const bool HAS_LEDS = true;
+// a passive buzzer
+const int BUZZER_PIN = 10;
+const int BUZZER_FREQUENCY = 440;
+// two daisy chained 74HC595 shift registers, the first key's LED at the last output
+const int LED_DATA_PIN = 11;
+const int LED_LATCH_PIN = 12;
+const int LED_CLOCK_PIN = 13;
+
 // from right to left (top view), the outputs of the keypads are connected to 2..=9
 MultitapKeypad keypad(5, 4, 3, 2, 9, 8, 7, 6);
 // creates key as Key object
 Key key;
 
-int down = 0;
+unsigned int down = 0;
+bool ambiguous = false;
+// 0 until the host sends HELLO
+byte version = 0;
+// the command waiting for its arguments
+byte command = 0;
+byte arguments[2];
+int received_arguments = 0;
+int missing_arguments = 0;
 
 void setup()
 {
   Serial.begin(9601);
+  if (HAS_LEDS)
+  {
+    pinMode(LED_DATA_PIN, OUTPUT);
+    pinMode(LED_LATCH_PIN, OUTPUT);
+    pinMode(LED_CLOCK_PIN, OUTPUT);
+    setLeds(0);
+  }
   keypad.attachFunction(serial);
 }
 
@@ -17,6 +65,7 @@ void loop()
 {
   key = keypad.getKey();
   down = 0;
+  ambiguous = false;
   if (key.state < 4)
   {
     int high = (~key.code >> 4) & 0xF;
@@ -53,23 +102,94 @@ void loop()
     }
     else
     {
-      down = 0xFFFF;
+      ambiguous = true;
     }
   }
 }
 
-void serial()
+void setBuzzer(bool on)
 {
-  int m;
-  while (Serial.available())
+  if (on)
+    tone(BUZZER_PIN, BUZZER_FREQUENCY);
+  else
+    noTone(BUZZER_PIN);
+}
+
+void setLeds(unsigned int leds)
+{
+  digitalWrite(LED_LATCH_PIN, LOW);
+  shiftOut(LED_DATA_PIN, LED_CLOCK_PIN, MSBFIRST, (leds >> 8) & 0xFF);
+  shiftOut(LED_DATA_PIN, LED_CLOCK_PIN, MSBFIRST, leds & 0xFF);
+  digitalWrite(LED_LATCH_PIN, HIGH);
+}
+
+void execute()
+{
+  if (command == LEGACY_HANDSHAKE)
+  {
+    version = 0;
+    Serial.write(LEGACY_HANDSHAKE);
+  }
+  else if (command == HELLO)
+  {
+    version = min(arguments[0], PROTOCOL_VERSION);
+    byte capabilities = 0;
+    if (HAS_BUZZER)
+    {
+      capabilities |= CAPABILITY_BUZZER;
+      setBuzzer(false);
+    }
+    if (HAS_LEDS)
+    {
+      capabilities |= CAPABILITY_LEDS;
+      setLeds(0);
+    }
+    Serial.write(HELLO);
+    Serial.write(version);
+    Serial.write((DEVICE_ID >> 8) & 0xFF);
+    Serial.write(DEVICE_ID & 0xFF);
+    Serial.write(capabilities);
+  }
+  else if (command == POLL)
+  {
+    unsigned int pressed = down;
+    if (version == 0 && ambiguous)
+      pressed = 0xFFFF;
+    else if (version > 0)
+      Serial.write(ambiguous ? STATUS_AMBIGUOUS : 0);
+    Serial.write((pressed >> 8) & 0xFF);
+    Serial.write(pressed & 0xFF);
+  }
+  else if (command == BUZZER && HAS_BUZZER)
   {
-    m = Serial.read();
-    if (m == 0xFB)
-      Serial.write(0xFB);
+    setBuzzer(arguments[0] != 0);
   }
-  if (m == 0x01)
+  else if (command == LEDS && HAS_LEDS)
+  {
+    setLeds((arguments[0] << 8) | arguments[1]);
+  }
+}
+
+void serial()
+{
+  while (Serial.available())
   {
-    Serial.write((down >> 8) & 0xFF);
-    Serial.write(down & 0xFF);
+    byte b = Serial.read();
+    if (missing_arguments > 0)
+    {
+      arguments[received_arguments++] = b;
+      missing_arguments--;
+    }
+    else
+    {
+      command = b;
+      received_arguments = 0;
+      if (b == HELLO || b == BUZZER)
+        missing_arguments = 1;
+      else if (b == LEDS)
+        missing_arguments = 2;
+    }
+    if (missing_arguments == 0)
+      execute();
   }
-}
\ No newline at end of file
+}
@@ -0,0 +1,93 @@
+// https://github.com/adafruit/RGB-matrix-Panel (needs an Arduino Mega for 64x32 panels)
+#include <RGBmatrixPanel.h>
+
+// Protocol (see src/sdl2_interaction/led_matrix.rs), the host sends every frame as
+// FRAME_START WIDTH HEIGHT DATA CHECKSUM: DATA are HEIGHT rows of WIDTH / 8 bytes from top to
+// bottom, the most significant bit being the leftmost LED, and CHECKSUM is the xor of DATA.
+// Frames of another size or with a wrong checksum are dropped and the next FRAME_START is awaited.
+const byte FRAME_START = 0xC8;
+const byte MATRIX_WIDTH = 64;
+const byte MATRIX_HEIGHT = 32;
+const int ROW_BYTES = MATRIX_WIDTH / 8;
+const int DATA_LENGTH = ROW_BYTES * MATRIX_HEIGHT;
+
+// the wiring of a HUB75 panel to a Mega
+const int CLK = 11;
+const int OE = 9;
+const int LAT = 10;
+RGBmatrixPanel matrix(A0, A1, A2, A3, CLK, LAT, OE, false, MATRIX_WIDTH);
+
+// the colors of the pixels that are on and off
+const uint16_t ON_COLOR = matrix.Color333(7, 7, 7);
+const uint16_t OFF_COLOR = matrix.Color333(0, 0, 0);
+
+enum State
+{
+  WAIT_FOR_START,
+  WIDTH,
+  HEIGHT,
+  DATA,
+  CHECKSUM,
+};
+
+State state = WAIT_FOR_START;
+byte data[DATA_LENGTH];
+int received = 0;
+byte checksum = 0;
+
+void setup()
+{
+  Serial.begin(250000);
+  matrix.begin();
+  matrix.fillScreen(OFF_COLOR);
+}
+
+void loop()
+{
+  while (Serial.available())
+  {
+    receive(Serial.read());
+  }
+}
+
+void receive(byte b)
+{
+  switch (state)
+  {
+  case WAIT_FOR_START:
+    if (b == FRAME_START)
+      state = WIDTH;
+    break;
+  case WIDTH:
+    state = b == MATRIX_WIDTH ? HEIGHT : WAIT_FOR_START;
+    break;
+  case HEIGHT:
+    state = b == MATRIX_HEIGHT ? DATA : WAIT_FOR_START;
+    received = 0;
+    checksum = 0;
+    break;
+  case DATA:
+    data[received++] = b;
+    checksum ^= b;
+    if (received == DATA_LENGTH)
+      state = CHECKSUM;
+    break;
+  case CHECKSUM:
+    if (b == checksum)
+      draw();
+    state = WAIT_FOR_START;
+    break;
+  }
+}
+
+void draw()
+{
+  for (int y = 0; y < MATRIX_HEIGHT; y++)
+  {
+    for (int x = 0; x < MATRIX_WIDTH; x++)
+    {
+      bool on = data[y * ROW_BYTES + x / 8] & (0x80 >> (x % 8));
+      matrix.drawPixel(x, y, on ? ON_COLOR : OFF_COLOR);
+    }
+  }
+}
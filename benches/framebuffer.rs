@@ -0,0 +1,135 @@
+use std::collections::HashMap;
+use std::hint::black_box;
+use std::time::Instant;
+
+use chip8_emulator::emulator::Emulator;
+use chip8_emulator::sdl2_interaction::screen::Screen;
+use sdl2::pixels::Color;
+
+/// clears the screen and covers it with 8x15 sprites, forever
+static DRAWING_ROM: [u8; 26] = [
+    0x00, 0xE0, 0x60, 0x00, 0x61, 0x00, 0xA0, 0x00, 0xD0, 0x1F, 0x70, 0x08, 0x30, 0x40, 0x12, 0x08,
+    0x60, 0x00, 0x71, 0x0F, 0x31, 0x2D, 0x12, 0x08, 0x12, 0x00,
+];
+
+/// the screen before the framebuffer (at a scale of 1), as the baseline
+#[derive(Default)]
+struct HashMapScreen {
+    pixels: HashMap<usize, HashMap<usize, Color>>,
+}
+impl HashMapScreen {
+    fn get(&self, x: usize, y: usize) -> Color {
+        self.pixels
+            .get(&y)
+            .and_then(|r| r.get(&x))
+            .copied()
+            .unwrap_or(Color::BLACK)
+    }
+
+    fn set(&mut self, x: usize, y: usize, c: Color) -> bool {
+        let pix = self
+            .pixels
+            .entry(y)
+            .or_default()
+            .entry(x)
+            .or_insert(Color::BLACK);
+        if *pix == c {
+            false
+        } else {
+            *pix = c;
+            true
+        }
+    }
+
+    /// the copy sent with every `RedrawAll`
+    fn get_pixels(&self) -> HashMap<usize, HashMap<usize, Color>> {
+        self.pixels.clone()
+    }
+}
+
+fn bench<F: FnMut()>(name: &str, iterations: u32, mut f: F) {
+    let start = Instant::now();
+    for _ in 0..iterations {
+        f();
+    }
+    let elapsed = start.elapsed();
+    println!(
+        "{:<32} {:>12?} per iteration",
+        name,
+        elapsed / iterations.max(1)
+    );
+}
+
+fn main() {
+    let mut emulator = Emulator::new_headless_chip8(60.0, 1000);
+    emulator.load_memory(DRAWING_ROM.to_vec());
+    bench("60 frames, 1000 opcodes each", 10, || {
+        black_box(emulator.run_headless(60, None, &[]));
+    });
+
+    println!("nested HashMap (before)");
+    let mut screen = HashMapScreen::default();
+    bench("set every pixel", 1000, || {
+        for y in 0..64 {
+            for x in 0..128 {
+                let c = if (x + y) % 2 == 0 {
+                    Color::WHITE
+                } else {
+                    Color::BLACK
+                };
+                black_box(screen.set(x, y, c));
+            }
+        }
+    });
+    bench("get every pixel", 1000, || {
+        for y in 0..64 {
+            for x in 0..128 {
+                black_box(screen.get(x, y));
+            }
+        }
+    });
+    bench("draw (xor every pixel)", 1000, || {
+        for y in 0..64 {
+            for x in 0..128 {
+                let c = if screen.get(x, y) == Color::WHITE {
+                    Color::BLACK
+                } else {
+                    Color::WHITE
+                };
+                black_box(screen.set(x, y, c));
+            }
+        }
+    });
+    bench("snapshot", 1000, || {
+        black_box(screen.get_pixels());
+    });
+
+    println!("framebuffer");
+    let mut screen = Screen::new();
+    screen.set_scale(1);
+    bench("set every pixel", 1000, || {
+        for y in 0..64 {
+            for x in 0..128 {
+                black_box(screen.set(x, y, (x + y) % 2 == 0));
+            }
+        }
+    });
+    bench("get every pixel", 1000, || {
+        for y in 0..64 {
+            for x in 0..128 {
+                black_box(screen.get(x, y));
+            }
+        }
+    });
+    bench("draw (xor every pixel)", 1000, || {
+        for y in 0..64 {
+            for x in 0..128 {
+                let on = screen.get(x, y);
+                black_box(screen.set(x, y, !on));
+            }
+        }
+    });
+    bench("snapshot", 1000, || {
+        black_box(screen.get_framebuffer().clone());
+    });
+}
@@ -0,0 +1,11 @@
+#![no_main]
+
+use chip8_emulator::emulator::Emulator;
+use chip8_emulator::{FPS, OPCODES_PER_FRAME};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|rom: &[u8]| {
+    let mut emulator = Emulator::new_headless_chip8(FPS, OPCODES_PER_FRAME);
+    emulator.load_memory(rom.to_vec());
+    emulator.run_headless(100, Some(10_000), &[]);
+});
@@ -0,0 +1,12 @@
+#![no_main]
+
+use chip8_emulator::emulator::Emulator;
+use chip8_emulator::{FPS, OPCODES_PER_FRAME};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|save: &[u8]| {
+    let mut emulator = Emulator::new_headless_chip8(FPS, OPCODES_PER_FRAME);
+    if emulator.load_state(save.to_vec()).is_ok() {
+        emulator.run_headless(10, Some(1_000), &[]);
+    }
+});
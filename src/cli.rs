@@ -0,0 +1,411 @@
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::str::FromStr;
+
+use crate::config::{rom_hash, Config, Settings};
+use crate::emulator::conformance::ConformanceOptions;
+use crate::emulator::disassembler::{disassemble_rom, is_super_chip};
+use crate::emulator::headless::{HeadlessOptions, EXIT_ERROR, EXIT_SUCCESS};
+use crate::emulator::interpreter::Chip8Interpreter;
+use crate::emulator::quirks::Platform;
+use crate::emulator::{Emulator, EmulatorBuilder};
+use crate::sdl2_interaction::palette::Palette;
+use crate::sdl2_interaction::pressed_key::Keymap;
+
+pub static HELP: &str = "\
+(SUPER) CHIP-8 emulator
+
+USAGE:
+    chip8-emulator [run] [ROM] [OPTIONS]      run a rom in a window (a file dialog opens without ROM)
+    chip8-emulator [run] --headless ROM [OPTIONS]
+                                              run a rom without a window and print the screen
+    chip8-emulator disasm ROM [--load-address ADDRESS]
+                                              print the instructions of a rom
+    chip8-emulator info ROM                   print the size, hash and config overrides of a rom
+    chip8-emulator test DIRECTORY [OPTIONS]   compare the screens of test roms with captures
+    chip8-emulator help                       print this help
+
+EMULATION OPTIONS (run, headless and test):
+    --platform NAME         quirk profile: default, chip8, schip or xochip
+    --ipf N                 instructions per frame
+    --seed N                seed of the random numbers
+    --load-address ADDRESS  where the rom is loaded and started (e.g. 0x200)
+    --state FILE            save state to load after the rom
+    --trace FILE            write every executed instruction to FILE
+    --keymap FILE           hex keys to host keys, lines of `HEX = KEY[, KEY...]` (e.g. `5 = W, Up`)
+
+WINDOW OPTIONS (run):
+    --speed N               speed in steps of the speed hotkeys, 0 being normal
+    --scale N               size of a pixel of the 128x64 screen
+    --fullscreen            start in fullscreen
+    --palette PALETTE       classic, amber, green, lcd, high-contrast, colorblind or
+                            comma separated RRGGBB colors, e.g. 000000,FFFFFF
+
+HEADLESS OPTIONS:
+    --frames N              frames to run (600)
+    --instructions N        stop after N instructions
+    --press FRAME:KEY[:N]   hold hex KEY at FRAME for N frames (5), repeatable
+    --output FILE           write the screen to FILE instead of stdout
+    --format text|pbm       format of the screen (by the extension of --output)
+    --expect FILE           exit with 1 if the screen differs from FILE
+
+TEST OPTIONS:
+    --frames N              maximum frames per rom (3600)
+    --stable N              frames without a change until the screen is compared (60)
+";
+
+pub fn parse_number<T: FromStr>(arg: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("`{}` expects a number, got `{}`", arg, value))
+}
+
+/// a hex (`0x` prefix) or decimal address inside of the memory
+pub fn parse_address(value: &str) -> Result<u16, String> {
+    match value.strip_prefix("0x") {
+        Some(hex) => u16::from_str_radix(hex, 16).ok(),
+        None => value.parse().ok(),
+    }
+    .filter(|a| *a < 0x1000)
+    .ok_or(format!("invalid address `{}`", value))
+}
+
+/// options shared by every way of running a rom
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EmulationOptions {
+    pub platform: Option<Platform>,
+    pub opcodes_per_frame: Option<u32>,
+    pub seed: Option<u64>,
+    pub load_address: Option<u16>,
+    pub state: Option<String>,
+    pub trace: Option<String>,
+    pub keymap: Option<Keymap>,
+}
+impl EmulationOptions {
+    /// returns false if `arg` isn't an emulation option, `value` returns the next argument
+    pub fn parse<F: FnMut() -> Result<String, String>>(
+        &mut self,
+        arg: &str,
+        mut value: F,
+    ) -> Result<bool, String> {
+        match arg {
+            "--platform" => {
+                let name = value()?;
+                self.platform = Some(Platform::parse(&name).ok_or(format!(
+                    "unknown platform `{}`, use one of {}",
+                    name,
+                    Platform::get_names().join(", ")
+                ))?);
+            }
+            "--ipf" => self.opcodes_per_frame = Some(parse_number(arg, &value()?)?),
+            "--seed" => self.seed = Some(parse_number(arg, &value()?)?),
+            "--load-address" => self.load_address = Some(parse_address(&value()?)?),
+            "--state" => self.state = Some(value()?),
+            "--trace" => self.trace = Some(value()?),
+            "--keymap" => {
+                let path = value()?;
+                self.keymap = Some(
+                    fs::read_to_string(&path)
+                        .map_err(|e| e.to_string())
+                        .and_then(|s| Keymap::parse(&s))
+                        .map_err(|e| format!("invalid keymap \"{}\": {}", path, e))?,
+                );
+            }
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    /// fails if the trace can't be created
+    pub fn apply(&self, mut builder: EmulatorBuilder) -> Result<EmulatorBuilder, String> {
+        if let Some(platform) = self.platform {
+            builder = builder.quirks(platform.get_quirks());
+        }
+        if let Some(opcodes_per_frame) = self.opcodes_per_frame {
+            builder = builder.opcodes_per_frame(opcodes_per_frame);
+        }
+        if let Some(seed) = self.seed {
+            builder = builder.seed(seed);
+        }
+        if let Some(load_address) = self.load_address {
+            builder = builder.load_address(load_address);
+        }
+        if let Some(keymap) = &self.keymap {
+            builder = builder.keymap(keymap.clone());
+        }
+        if let Some(path) = &self.trace {
+            builder = builder.trace(BufWriter::new(
+                File::create(path).map_err(|e| format!("can't create \"{}\": {}", path, e))?,
+            ));
+        }
+        Ok(builder)
+    }
+
+    /// loads the save state, if there is one
+    pub fn load_state(&self, emulator: &mut Emulator<Chip8Interpreter>) -> Result<(), String> {
+        match &self.state {
+            Some(path) => fs::read(path)
+                .map_err(|e| e.to_string())
+                .and_then(|data| emulator.load_state(data))
+                .map_err(|e| format!("can't load state \"{}\": {}", path, e)),
+            None => Ok(()),
+        }
+    }
+}
+
+/// options of `chip8-emulator [run] [ROM] [OPTIONS]`
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RunOptions {
+    pub rom: Option<String>,
+    pub emulation: EmulationOptions,
+    pub speed: Option<i8>,
+    pub scale: Option<u32>,
+    pub fullscreen: bool,
+    pub palette: Option<String>,
+}
+impl RunOptions {
+    pub fn from_args<I: Iterator<Item = String>>(mut args: I) -> Result<Self, String> {
+        let mut options = Self::default();
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or(format!("missing value for `{}`", arg));
+            match arg.as_str() {
+                "--speed" => options.speed = Some(parse_number(&arg, &value()?)?),
+                "--scale" => {
+                    options.scale = Some(
+                        Some(parse_number(&arg, &value()?)?)
+                            .filter(|s| *s > 0)
+                            .ok_or("`--scale` must be at least 1")?,
+                    )
+                }
+                "--fullscreen" => options.fullscreen = true,
+                "--palette" => {
+                    let palette = value()?;
+                    Palette::parse(&palette)?;
+                    options.palette = Some(palette);
+                }
+                _ if options.emulation.parse(&arg, &mut value)? => {}
+                _ if arg.starts_with("--") => return Err(format!("unknown option `{}`", arg)),
+                _ if options.rom.is_none() => options.rom = Some(arg),
+                _ => return Err(format!("unexpected argument `{}`", arg)),
+            }
+        }
+        Ok(options)
+    }
+
+    /// overrides the settings given on the command line
+    pub fn apply_settings(&self, settings: &mut Settings) {
+        if let Some(opcodes_per_frame) = self.emulation.opcodes_per_frame {
+            settings.opcodes_per_frame = opcodes_per_frame;
+        }
+        if let Some(speed) = self.speed {
+            settings.speed = speed;
+        }
+        if let Some(scale) = self.scale {
+            settings.scale = scale;
+        }
+        settings.fullscreen |= self.fullscreen;
+        if let Some(palette) = &self.palette {
+            settings.palette = palette.clone();
+        }
+        if let Some(keymap) = &self.emulation.keymap {
+            settings.keymap = keymap.clone();
+        }
+    }
+}
+
+/// options of `chip8-emulator disasm ROM [OPTIONS]`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DisassembleOptions {
+    pub rom: String,
+    pub load_address: u16,
+}
+impl DisassembleOptions {
+    pub fn from_args<I: Iterator<Item = String>>(mut args: I) -> Result<Self, String> {
+        let mut rom = None;
+        let mut load_address = 0x200;
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--load-address" => {
+                    load_address = parse_address(
+                        &args
+                            .next()
+                            .ok_or("missing value for `--load-address`".to_owned())?,
+                    )?
+                }
+                _ if arg.starts_with("--") => return Err(format!("unknown option `{}`", arg)),
+                _ if rom.is_none() => rom = Some(arg),
+                _ => return Err(format!("unexpected argument `{}`", arg)),
+            }
+        }
+        Ok(Self {
+            rom: rom.ok_or("missing rom path")?,
+            load_address,
+        })
+    }
+}
+
+pub enum Command {
+    Run(RunOptions),
+    Headless(HeadlessOptions),
+    Disassemble(DisassembleOptions),
+    Info(String),
+    Test(ConformanceOptions),
+    Help,
+}
+impl Command {
+    /// parses the arguments without the name of the binary, `run` is the default subcommand
+    pub fn from_args<I: Iterator<Item = String>>(args: I) -> Result<Self, String> {
+        let mut args = args.collect::<Vec<String>>();
+        if args.iter().any(|a| a == "--help" || a == "-h") {
+            return Ok(Command::Help);
+        }
+        let subcommand = match args.first().map(String::as_str) {
+            Some("run" | "disasm" | "info" | "test" | "help") => args.remove(0),
+            _ => "run".to_owned(),
+        };
+        let args = args.into_iter();
+        Ok(match subcommand.as_str() {
+            "help" => Command::Help,
+            "disasm" => Command::Disassemble(DisassembleOptions::from_args(args)?),
+            "info" => {
+                let mut args = args;
+                let rom = args.next().ok_or("missing rom path")?;
+                if let Some(arg) = args.next() {
+                    return Err(format!("unexpected argument `{}`", arg));
+                }
+                Command::Info(rom)
+            }
+            "test" => Command::Test(ConformanceOptions::from_args(args)?),
+            _ if args.as_slice().iter().any(|a| a == "--headless") => {
+                Command::Headless(HeadlessOptions::from_args(args)?)
+            }
+            _ => Command::Run(RunOptions::from_args(args)?),
+        })
+    }
+}
+
+fn read_rom(path: &str) -> Option<Vec<u8>> {
+    fs::read(path)
+        .map_err(|e| eprintln!("Failed to read file \"{}\" - error: \"{}\".", path, e))
+        .ok()
+}
+
+/// prints the disassembly and returns the exit code of the process
+pub fn disassemble(options: &DisassembleOptions) -> i32 {
+    match read_rom(&options.rom) {
+        Some(rom) => {
+            print!("{}", disassemble_rom(&rom, options.load_address));
+            EXIT_SUCCESS
+        }
+        None => EXIT_ERROR,
+    }
+}
+
+/// prints information about a rom and returns the exit code of the process
+pub fn info(path: &str) -> i32 {
+    let rom = match read_rom(path) {
+        Some(rom) => rom,
+        None => return EXIT_ERROR,
+    };
+    let hash = rom_hash(&rom);
+    println!("File: {}", path);
+    println!("Size: {} bytes", rom.len());
+    println!("Hash: {}", hash);
+    println!(
+        "SUPER-CHIP instructions: {}",
+        if rom
+            .chunks_exact(2)
+            .any(|w| is_super_chip(u16::from_be_bytes([w[0], w[1]])))
+        {
+            "yes"
+        } else {
+            "no"
+        }
+    );
+    match Config::load().roms.get(&hash) {
+        Some(overrides) => println!(
+            "Config overrides: {}",
+            toml::to_string(overrides)
+                .unwrap_or_default()
+                .trim()
+                .replace('\n', ", ")
+        ),
+        None => println!("Config overrides: none"),
+    }
+    EXIT_SUCCESS
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cli::{parse_address, Command, EmulationOptions, RunOptions};
+    use crate::config::Settings;
+    use crate::emulator::quirks::Platform;
+
+    fn args(s: &str) -> impl Iterator<Item = String> + '_ {
+        s.split_whitespace().map(str::to_owned)
+    }
+
+    #[test]
+    fn subcommands() {
+        assert!(matches!(Command::from_args(args("")), Ok(Command::Run(_))));
+        assert!(matches!(
+            Command::from_args(args("rom.ch8 --help")),
+            Ok(Command::Help)
+        ));
+        assert!(matches!(
+            Command::from_args(args("run --headless rom.ch8 --seed 1")),
+            Ok(Command::Headless(o)) if o.emulation.seed == Some(1)
+        ));
+        assert!(matches!(
+            Command::from_args(args("disasm rom.ch8 --load-address 0x300")),
+            Ok(Command::Disassemble(o)) if o.load_address == 0x300
+        ));
+        assert!(matches!(
+            Command::from_args(args("info rom.ch8")),
+            Ok(Command::Info(r)) if r == "rom.ch8"
+        ));
+        assert!(matches!(
+            Command::from_args(args("test roms --platform schip")),
+            Ok(Command::Test(o)) if o.emulation.platform == Some(Platform::SuperChip)
+        ));
+        assert!(Command::from_args(args("info")).is_err());
+        assert!(Command::from_args(args("--unknown")).is_err());
+    }
+
+    #[test]
+    fn run_options() {
+        let cut = RunOptions::from_args(args(
+            "game.ch8 --platform chip8 --ipf 30 --speed -2 --scale 4 --fullscreen --palette 000000,00FF00",
+        ))
+        .unwrap();
+        assert_eq!(cut.rom.as_deref(), Some("game.ch8"));
+        assert_eq!(
+            cut.emulation,
+            EmulationOptions {
+                platform: Some(Platform::Chip8),
+                opcodes_per_frame: Some(30),
+                ..Default::default()
+            }
+        );
+        let mut settings = Settings::default();
+        cut.apply_settings(&mut settings);
+        assert_eq!(
+            (settings.opcodes_per_frame, settings.speed, settings.scale),
+            (30, -2, 4)
+        );
+        assert!(settings.fullscreen);
+        assert_eq!(settings.palette, "000000,00FF00");
+        assert!(RunOptions::from_args(args("--palette red,blue")).is_err());
+        assert!(RunOptions::from_args(args("--platform cosmac")).is_err());
+        assert!(RunOptions::from_args(args("--ipf")).is_err());
+        assert!(RunOptions::from_args(args("--keymap does/not/exist")).is_err());
+    }
+
+    #[test]
+    fn addresses() {
+        assert_eq!(parse_address("0x200"), Ok(0x200));
+        assert_eq!(parse_address("1536"), Ok(0x600));
+        assert!(parse_address("0x1000").is_err());
+        assert!(parse_address("x").is_err());
+    }
+}
@@ -0,0 +1,551 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+
+use sdl2::pixels::Color;
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::events::app::AppEvent;
+use crate::events::hotkeys::Hotkeys;
+use crate::events::plugin::{Plugin, PluginContext};
+use crate::sdl2_interaction::controller::ControllerMap;
+use crate::sdl2_interaction::crt::CrtFilter;
+use crate::sdl2_interaction::event_manager::{EventKind, IncomingEvent};
+use crate::sdl2_interaction::input_sources::InputAssignment;
+use crate::sdl2_interaction::keypad::KeypadSettings;
+use crate::sdl2_interaction::led_matrix::LedMatrixSettings;
+use crate::sdl2_interaction::output::ScreenEvent;
+use crate::sdl2_interaction::palette::Palette;
+use crate::sdl2_interaction::pressed_key::Keymap;
+use crate::sdl2_interaction::upscaler::Upscaler;
+use crate::{
+    LogError, LogWarning, FPS, OPCODES_PER_FRAME, PAUSE_TRANSPARENT_COLOR, SCREEN_HEIGHT,
+    SCREEN_WIDTH, SPEED_CHANGE_PER_KEYPRESS, STANDARD_BUZZ_FREQUENCY, STARTING_SCALE,
+};
+
+static SETTINGS: OnceLock<Settings> = OnceLock::new();
+
+/// the recent roms saved in the config
+static MAX_RECENT: usize = 10;
+
+/// the global settings of the config file, missing values are the defaults of `lib.rs`
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub fps: f32,
+    pub opcodes_per_frame: u32,
+    pub speed_change_per_keypress: f32,
+    /// the size of a pixel of the 128x64 screen in window pixels
+    pub scale: u32,
+    pub buzz_frequency: f32,
+    /// rgba
+    pub pause_color: [u8; 4],
+    /// the name of a theme or comma separated `RRGGBB` colors, see `Palette::parse`
+    pub palette: String,
+    /// how much of its glow a pixel keeps every frame after it turned off (0 to 0.99), against
+    /// the flickering of sprites that are erased and redrawn, 0 turns pixels off at once
+    pub persistence: f32,
+    /// `none`, `scanlines`, `pixel_grid`, `bloom`, `vignette` or `crt` (all but the grid)
+    pub crt_filter: CrtFilter,
+    /// `none`, `scale2x`, `scale3x`, `epx` or `xbr`, applied before the CRT filter
+    pub upscaler: Upscaler,
+    /// in steps of `speed_change_per_keypress`, 0 being the normal speed
+    pub speed: i8,
+    pub fullscreen: bool,
+    /// only scales the screen by whole numbers, the window is filled up with bars
+    pub integer_scaling: bool,
+    /// shows the frames and instructions per second and the speed
+    pub show_stats: bool,
+    /// the directory the rom browser starts in, `roms` in the working directory (or the working
+    /// directory itself) if missing
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rom_directory: Option<PathBuf>,
+    /// offers the file dialog of the system in the rom browser (needs an XDG portal on Linux)
+    pub file_dialog: bool,
+    /// `HEX = ["KEY", ...]`, missing hex keys keep their default key
+    pub keymap: Keymap,
+    /// the controller map of every player (`[[controllers]]` sections of `HEX = ["INPUT", ...]`),
+    /// players without a map use the last one
+    pub controllers: Vec<ControllerMap>,
+    /// `action = ["[Ctrl+][Shift+][Alt+]KEY", ...]`, missing actions keep their default hotkeys
+    pub hotkeys: Hotkeys,
+    /// the hex keys every input source may press (`source = ["HEX", ...]`, e.g. `player2`),
+    /// sources that aren't listed may press all of them
+    pub inputs: InputAssignment,
+    /// the serial keypads (`[[keypads]]` sections, a single `[keypad]` section of older
+    /// configs is read as well), a keypad is only used if its port or USB vendor id is configured
+    #[serde(alias = "keypad", deserialize_with = "one_or_many")]
+    pub keypads: Vec<KeypadSettings>,
+    /// the serial LED matrix mirroring the screen (`[led_matrix]`), only used if its port is
+    /// configured
+    pub led_matrix: LedMatrixSettings,
+}
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            fps: FPS,
+            opcodes_per_frame: OPCODES_PER_FRAME,
+            speed_change_per_keypress: SPEED_CHANGE_PER_KEYPRESS,
+            scale: STARTING_SCALE,
+            buzz_frequency: STANDARD_BUZZ_FREQUENCY,
+            pause_color: PAUSE_TRANSPARENT_COLOR.rgba().into(),
+            palette: "classic".to_owned(),
+            persistence: 0.0,
+            crt_filter: CrtFilter::None,
+            upscaler: Upscaler::None,
+            speed: 0,
+            fullscreen: false,
+            integer_scaling: false,
+            show_stats: false,
+            rom_directory: None,
+            file_dialog: true,
+            keymap: Keymap::default(),
+            controllers: vec![ControllerMap::default()],
+            hotkeys: Hotkeys::default(),
+            inputs: InputAssignment::default(),
+            keypads: Vec::new(),
+            led_matrix: LedMatrixSettings::default(),
+        }
+    }
+}
+impl Settings {
+    pub fn get_pause_color(&self) -> Color {
+        let [r, g, b, a] = self.pause_color;
+        Color::RGBA(r, g, b, a)
+    }
+
+    /// the default palette if the configured one is invalid
+    pub fn get_palette(&self) -> Palette {
+        Palette::parse(&self.palette).unwrap_or_else(|e| {
+            format!("Invalid palette: {}.", e).as_str().wlog();
+            Palette::default()
+        })
+    }
+
+    /// multiplier of the emulation speed for a speed step
+    pub fn get_speed_factor(&self, speed: i8) -> f32 {
+        self.speed_change_per_keypress.powi(speed as i32)
+    }
+
+    /// the configured rom directory, else `roms` or the working directory
+    pub fn get_rom_directory(&self) -> PathBuf {
+        self.rom_directory.clone().unwrap_or_else(|| {
+            let current = std::env::current_dir().unwrap_or_default();
+            let roms = current.join("roms");
+            if roms.is_dir() {
+                roms
+            } else {
+                current
+            }
+        })
+    }
+}
+
+/// sets the settings returned by `settings`, can only be done once (before the emulator starts)
+pub fn init_settings(settings: Settings) {
+    if SETTINGS.set(settings).is_err() {
+        "Settings were already initialized.".wlog();
+    }
+}
+
+/// the global settings, the defaults if `init_settings` wasn't called
+pub fn settings() -> &'static Settings {
+    SETTINGS.get_or_init(Settings::default)
+}
+
+/// a rom section, overriding the global settings
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct RomSettings {
+    /// shown by the rom browser instead of the file name
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fps: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub opcodes_per_frame: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub speed: Option<i8>,
+    /// replaces the global keymap, missing hex keys keep their default key (not the global one)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keymap: Option<Keymap>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub controllers: Option<Vec<ControllerMap>>,
+    /// replaces the global assignment, e.g. for two player games
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub inputs: Option<InputAssignment>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub palette: Option<String>,
+}
+
+/// the config file, `config.toml` in the XDG config directory (e.g. `~/.config/chip8-emulator`)
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    #[serde(flatten)]
+    pub settings: Settings,
+    /// the roms played last, the newest first
+    pub recent: Vec<PathBuf>,
+    /// keyed by `rom_hash`
+    pub roms: BTreeMap<String, RomSettings>,
+    /// set if the file couldn't be parsed, it isn't overwritten then
+    #[serde(skip)]
+    invalid: bool,
+}
+impl Config {
+    pub fn get_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|d| d.join("chip8-emulator").join("config.toml"))
+    }
+
+    /// the defaults if there is no config file or it is invalid
+    pub fn load() -> Self {
+        match Self::get_path() {
+            Some(path) => Self::load_from(&path),
+            None => Self::default(),
+        }
+    }
+
+    fn load_from(path: &Path) -> Self {
+        if !path.exists() {
+            return Self::default();
+        }
+        match fs::read_to_string(path)
+            .map_err(|e| e.to_string())
+            .and_then(|s| Self::parse(&s))
+        {
+            Ok(config) => config,
+            Err(e) => {
+                format!("Couldn't load config {:?}: {}.", path, e)
+                    .as_str()
+                    .wlog();
+                Self {
+                    invalid: true,
+                    ..Self::default()
+                }
+            }
+        }
+    }
+
+    pub fn parse(s: &str) -> Result<Self, String> {
+        toml::from_str(s).map_err(|e| e.message().to_owned())
+    }
+
+    pub fn save(&self) {
+        match Self::get_path() {
+            Some(path) => self.save_to(&path),
+            None => "No config directory found.".wlog(),
+        }
+    }
+
+    /// an invalid config file is kept so that it can be fixed by hand
+    fn save_to(&self, path: &Path) {
+        if self.invalid {
+            return format!("Not saving the config, {:?} is invalid.", path)
+                .as_str()
+                .wlog();
+        }
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).elog("creating config directory");
+        }
+        match toml::to_string(self) {
+            Ok(s) => fs::write(path, s).elog(format!("saving config to {:?}", path).as_str()),
+            Err(e) => Err::<(), _>(e).elog("serializing config"),
+        }
+    }
+
+    /// the global settings with the overrides of the rom's section
+    pub fn get_rom_settings(&self, rom: &[u8]) -> Settings {
+        let mut settings = self.settings.clone();
+        if let Some(overrides) = self.roms.get(&rom_hash(rom)) {
+            settings.fps = overrides.fps.unwrap_or(settings.fps);
+            settings.opcodes_per_frame = overrides
+                .opcodes_per_frame
+                .unwrap_or(settings.opcodes_per_frame);
+            settings.speed = overrides.speed.unwrap_or(settings.speed);
+            if let Some(keymap) = &overrides.keymap {
+                settings.keymap = keymap.clone();
+            }
+            if let Some(controllers) = &overrides.controllers {
+                settings.controllers = controllers.clone();
+            }
+            if let Some(inputs) = &overrides.inputs {
+                settings.inputs = inputs.clone();
+            }
+            if let Some(palette) = &overrides.palette {
+                settings.palette = palette.clone();
+            }
+        }
+        settings
+    }
+
+    pub fn get_rom_mut(&mut self, rom: &[u8]) -> &mut RomSettings {
+        self.roms.entry(rom_hash(rom)).or_default()
+    }
+
+    /// the title of the rom's section
+    pub fn get_title(&self, rom: &[u8]) -> Option<&str> {
+        self.roms.get(&rom_hash(rom))?.title.as_deref()
+    }
+
+    /// moves the rom to the front of the recent roms, only the newest `MAX_RECENT` are kept
+    pub fn add_recent(&mut self, path: &Path) {
+        let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_owned());
+        self.recent.retain(|p| *p != path);
+        self.recent.insert(0, path);
+        self.recent.truncate(MAX_RECENT);
+    }
+}
+
+/// a list or a single value, e.g. a table where an array of tables was used before
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum OneOrMany<T> {
+    Many(Vec<T>),
+    One(T),
+}
+
+fn one_or_many<'de, D: Deserializer<'de>, T: Deserialize<'de>>(
+    deserializer: D,
+) -> Result<Vec<T>, D::Error> {
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::Many(values) => values,
+        OneOrMany::One(value) => vec![value],
+    })
+}
+
+/// 64 bit FNV-1a of the rom as hex
+pub fn rom_hash(rom: &[u8]) -> String {
+    let hash = rom.iter().fold(0xcbf29ce484222325_u64, |h, b| {
+        (h ^ *b as u64).wrapping_mul(0x100000001b3)
+    });
+    format!("{:016x}", hash)
+}
+
+/// the window settings changed at runtime
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WindowChanges {
+    pub scale: u32,
+    pub fullscreen: bool,
+    pub crt_filter: CrtFilter,
+    pub upscaler: Upscaler,
+}
+
+/// a plugin recording `WindowChanges` to save them on exit
+#[derive(Clone)]
+pub struct WindowRecorder {
+    changes: Arc<Mutex<WindowChanges>>,
+}
+impl WindowRecorder {
+    pub fn new(settings: &Settings) -> Self {
+        Self {
+            changes: Arc::new(Mutex::new(WindowChanges {
+                scale: settings.scale,
+                fullscreen: settings.fullscreen,
+                crt_filter: settings.crt_filter,
+                upscaler: settings.upscaler,
+            })),
+        }
+    }
+
+    pub fn get_changes(&self) -> WindowChanges {
+        *self.changes.lock().unwrap()
+    }
+}
+impl Plugin for WindowRecorder {
+    fn get_subscriptions(&self) -> &[EventKind] {
+        &[EventKind::App, EventKind::Screen]
+    }
+
+    fn on_event(&mut self, event: &IncomingEvent, _: &PluginContext) {
+        let mut changes = self.changes.lock().unwrap();
+        match event {
+            IncomingEvent::Screen(ScreenEvent::ToggleFullscreen) => {
+                changes.fullscreen = !changes.fullscreen
+            }
+            IncomingEvent::Screen(ScreenEvent::NextCrtFilter) => {
+                changes.crt_filter = changes.crt_filter.next()
+            }
+            IncomingEvent::Screen(ScreenEvent::NextUpscaler) => {
+                changes.upscaler = changes.upscaler.next()
+            }
+            // the size of a fullscreen window isn't a scale the user chose
+            IncomingEvent::App(AppEvent::WindowSizeChange(w, h)) if !changes.fullscreen => {
+                changes.scale = (*w as u32 / SCREEN_WIDTH)
+                    .min(*h as u32 / SCREEN_HEIGHT)
+                    .max(1)
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+    use std::sync::mpsc;
+
+    use sdl2::keyboard::Scancode;
+
+    use crate::config::{rom_hash, Config, RomSettings, Settings, WindowRecorder};
+    use crate::events::app::AppEvent;
+    use crate::events::plugin::{Plugin, PluginContext};
+    use crate::sdl2_interaction::crt::CrtFilter;
+    use crate::sdl2_interaction::event_manager::IncomingEvent;
+    use crate::sdl2_interaction::input_sources::InputSource;
+    use crate::sdl2_interaction::output::ScreenEvent;
+    use crate::sdl2_interaction::palette::Palette;
+    use crate::sdl2_interaction::upscaler::Upscaler;
+
+    #[test]
+    fn parse() {
+        let rom = [0x12, 0x00];
+        let cut = Config::parse(&format!(
+            "opcodes_per_frame = 20\nfullscreen = true\n[roms.{}]\nspeed = -2\nfps = 30.0\n\
+            palette = \"amber\"\n",
+            rom_hash(&rom)
+        ))
+        .unwrap();
+        assert_eq!(cut.settings.opcodes_per_frame, 20);
+        assert!(cut.settings.fullscreen);
+        assert_eq!(cut.settings.scale, Settings::default().scale);
+        let rom_settings = cut.get_rom_settings(&rom);
+        assert_eq!(
+            (
+                rom_settings.fps,
+                rom_settings.opcodes_per_frame,
+                rom_settings.speed
+            ),
+            (30.0, 20, -2)
+        );
+        assert_eq!(rom_settings.get_palette(), Palette::parse("amber").unwrap());
+        assert_eq!(cut.get_rom_settings(&[0x00]).fps, Settings::default().fps);
+        assert!(Config::parse("fps = \"fast\"").is_err());
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut cut = Config::default();
+        cut.settings.pause_color = [1, 2, 3, 4];
+        *cut.get_rom_mut(&[0xA0]) = RomSettings {
+            speed: Some(3),
+            ..Default::default()
+        };
+        let saved = toml::to_string(&cut).unwrap();
+        assert_eq!(Config::parse(&saved), Ok(cut));
+    }
+
+    #[test]
+    fn keymaps() {
+        let rom = [0x12, 0x00];
+        let cut = Config::parse(&format!(
+            "[keymap]\n5 = [\"W\", \"Up\"]\n[roms.{}.keymap]\n8 = [\"Down\"]\n",
+            rom_hash(&rom)
+        ))
+        .unwrap();
+        assert_eq!(
+            cut.settings.keymap.get_scancodes(0x5),
+            [Scancode::W, Scancode::Up]
+        );
+        let rom_keymap = cut.get_rom_settings(&rom).keymap;
+        assert_eq!(rom_keymap.get_scancodes(0x5), [Scancode::W]);
+        assert_eq!(rom_keymap.get_scancodes(0x8), [Scancode::Down]);
+        assert_eq!(Config::parse(&toml::to_string(&cut).unwrap()), Ok(cut));
+        assert!(Config::parse("[keymap]\n5 = [\"NoSuchKey\"]").is_err());
+    }
+
+    #[test]
+    fn inputs() {
+        let rom = [0x12, 0x00];
+        let cut = Config::parse(&format!(
+            "[inputs]\nkeyboard = [\"1\", \"4\"]\n[roms.{}.inputs]\nplayer2 = [\"C\"]\n\
+            [[keypads]]\nport = \"/dev/ttyACM0\"\n[[keypads]]\nvid = 0x2341\nbaud_rate = 115200\n\
+            [led_matrix]\nport = \"/dev/ttyACM1\"\nheight = 16\n",
+            rom_hash(&rom)
+        ))
+        .unwrap();
+        assert_eq!(
+            cut.settings.inputs.get_keys(&InputSource::Keyboard),
+            1 << 0x1 | 1 << 0x4
+        );
+        let rom_inputs = cut.get_rom_settings(&rom).inputs;
+        assert_eq!(rom_inputs.get_keys(&InputSource::Keyboard), u16::MAX);
+        assert_eq!(rom_inputs.get_keys(&InputSource::Controller(1)), 1 << 0xC);
+        assert_eq!(cut.settings.keypads.len(), 2);
+        assert_eq!(cut.settings.keypads[1].vid, Some(0x2341));
+        assert_eq!(cut.settings.keypads[1].baud_rate, 115200);
+        assert!(cut.settings.led_matrix.is_enabled());
+        assert_eq!(cut.settings.led_matrix.width, 64);
+        assert_eq!(cut.settings.led_matrix.height, 16);
+        assert_eq!(Config::parse(&toml::to_string(&cut).unwrap()), Ok(cut));
+    }
+
+    #[test]
+    fn old_keypad_section() {
+        let cut = Config::parse("[keypad]\nbaud_rate = 115200\n").unwrap();
+        assert_eq!(cut.settings.keypads.len(), 1);
+        assert_eq!(cut.settings.keypads[0].baud_rate, 115200);
+        let cut = Config::parse("keypads = []").unwrap();
+        assert!(cut.settings.keypads.is_empty());
+    }
+
+    #[test]
+    fn recent() {
+        let mut cut = Config::default();
+        for i in 0..12 {
+            cut.add_recent(Path::new(&format!("missing-{}.ch8", i)));
+        }
+        cut.add_recent(Path::new("missing-5.ch8"));
+        assert_eq!(cut.recent.len(), 10);
+        assert_eq!(cut.recent[0], Path::new("missing-5.ch8"));
+        assert_eq!(cut.recent[1], Path::new("missing-11.ch8"));
+        assert_eq!(Config::parse(&toml::to_string(&cut).unwrap()), Ok(cut));
+    }
+
+    #[test]
+    fn invalid_file_is_kept() {
+        let path =
+            std::env::temp_dir().join(format!("chip8-invalid-config-{}.toml", std::process::id()));
+        fs::write(&path, "fps = \"fast\"").unwrap();
+        let cut = Config::load_from(&path);
+        assert_eq!(cut.settings, Settings::default());
+        cut.save_to(&path);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "fps = \"fast\"");
+
+        let cut = Config::default();
+        cut.save_to(&path);
+        assert_eq!(Config::load_from(&path), cut);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn hash() {
+        assert_eq!(rom_hash(&[]), "cbf29ce484222325");
+        assert_ne!(rom_hash(&[0x00]), rom_hash(&[0x01]));
+    }
+
+    #[test]
+    fn window_changes() {
+        let mut cut = WindowRecorder::new(&Settings::default());
+        let context = PluginContext::new(mpsc::channel().0);
+        cut.on_event(
+            &IncomingEvent::App(AppEvent::WindowSizeChange(640, 640)),
+            &context,
+        );
+        assert_eq!(cut.get_changes().scale, 5);
+        cut.on_event(
+            &IncomingEvent::Screen(ScreenEvent::ToggleFullscreen),
+            &context,
+        );
+        cut.on_event(
+            &IncomingEvent::App(AppEvent::WindowSizeChange(1920, 1080)),
+            &context,
+        );
+        assert_eq!(cut.get_changes().scale, 5);
+        assert!(cut.get_changes().fullscreen);
+        cut.on_event(&IncomingEvent::Screen(ScreenEvent::NextCrtFilter), &context);
+        assert_eq!(cut.get_changes().crt_filter, CrtFilter::Scanlines);
+        cut.on_event(&IncomingEvent::Screen(ScreenEvent::NextUpscaler), &context);
+        assert_eq!(cut.get_changes().upscaler, Upscaler::Scale2x);
+    }
+}
@@ -0,0 +1,160 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::cli::{parse_number, EmulationOptions};
+use crate::emulator::headless::{Capture, EXIT_ERROR, EXIT_MISMATCH, EXIT_SUCCESS};
+use crate::emulator::interpreter::Chip8Interpreter;
+use crate::emulator::Emulator;
+use crate::{has_rom_extension, FPS, OPCODES_PER_FRAME};
+
+static CAPTURE_EXTENSIONS: [&str; 2] = ["pbm", "txt"];
+static STANDARD_MAX_FRAMES: u32 = 3600;
+static STANDARD_STABLE_FRAMES: u32 = 60;
+
+/// options of `chip8-emulator test DIRECTORY [OPTIONS]`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConformanceOptions {
+    pub directory: PathBuf,
+    pub max_frames: u32,
+    pub stable_frames: u32,
+    pub emulation: EmulationOptions,
+}
+impl ConformanceOptions {
+    pub fn from_args<I: Iterator<Item = String>>(mut args: I) -> Result<Self, String> {
+        let mut directory = None;
+        let mut max_frames = STANDARD_MAX_FRAMES;
+        let mut stable_frames = STANDARD_STABLE_FRAMES;
+        let mut emulation = EmulationOptions::default();
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or(format!("missing value for `{}`", arg));
+            match arg.as_str() {
+                "--frames" => max_frames = parse_number(&arg, &value()?)?,
+                "--stable" => stable_frames = parse_number(&arg, &value()?)?,
+                _ if emulation.parse(&arg, &mut value)? => {}
+                _ if arg.starts_with("--") => return Err(format!("unknown option `{}`", arg)),
+                _ if directory.is_none() => directory = Some(PathBuf::from(arg)),
+                _ => return Err(format!("unexpected argument `{}`", arg)),
+            }
+        }
+        Ok(Self {
+            directory: directory.ok_or("missing test rom directory")?,
+            max_frames,
+            stable_frames,
+            emulation,
+        })
+    }
+}
+
+/// the result of comparing a rom's screen with its expected capture
+pub enum Verdict {
+    Pass,
+    Fail(Capture, Capture),
+    MissingCapture,
+}
+
+/// runs until the screen didn't change for `stable_frames` frames or `max_frames` are reached
+pub fn run_until_stable(
+    mut emulator: Emulator<Chip8Interpreter>,
+    rom: Vec<u8>,
+    max_frames: u32,
+    stable_frames: u32,
+) -> Capture {
+    emulator.load_memory(rom);
+    let mut capture = emulator.run_headless(1, None, &[]);
+    let mut unchanged = 0;
+    for _ in 1..max_frames {
+        if unchanged >= stable_frames {
+            break;
+        }
+        let next = emulator.run_headless(1, None, &[]);
+        if next == capture {
+            unchanged += 1;
+        } else {
+            unchanged = 0;
+            capture = next;
+        }
+    }
+    capture
+}
+
+fn find_expected(rom: &Path) -> Option<Capture> {
+    CAPTURE_EXTENSIONS
+        .iter()
+        .map(|e| rom.with_extension(e))
+        .find(|p| p.exists())
+        .and_then(|p| fs::read_to_string(p).ok())
+        .and_then(|s| Capture::parse(&s))
+}
+
+pub fn check(rom_path: &Path, options: &ConformanceOptions) -> Result<Verdict, String> {
+    let rom = fs::read(rom_path).map_err(|e| e.to_string())?;
+    let expected = match find_expected(rom_path) {
+        Some(expected) => expected,
+        None => return Ok(Verdict::MissingCapture),
+    };
+    let emulator = options
+        .emulation
+        .apply(
+            Emulator::builder()
+                .fps(FPS)
+                .opcodes_per_frame(OPCODES_PER_FRAME),
+        )?
+        .build_headless_chip8();
+    let actual = run_until_stable(emulator, rom, options.max_frames, options.stable_frames);
+    Ok(if actual == expected {
+        Verdict::Pass
+    } else {
+        Verdict::Fail(expected, actual)
+    })
+}
+
+pub fn find_roms(directory: &Path) -> Result<Vec<PathBuf>, String> {
+    let mut roms = fs::read_dir(directory)
+        .map_err(|e| e.to_string())?
+        .filter_map(|f| f.ok())
+        .map(|f| f.path())
+        .filter(|p| has_rom_extension(p))
+        .collect::<Vec<PathBuf>>();
+    roms.sort();
+    Ok(roms)
+}
+
+/// checks every rom of the directory and returns the exit code of the process
+pub fn run(options: &ConformanceOptions) -> i32 {
+    let roms = match find_roms(&options.directory) {
+        Ok(roms) if !roms.is_empty() => roms,
+        Ok(_) => {
+            eprintln!("No test roms found in {:?}.", options.directory);
+            return EXIT_ERROR;
+        }
+        Err(e) => {
+            eprintln!(
+                "Failed to read directory {:?} - error: \"{}\".",
+                options.directory, e
+            );
+            return EXIT_ERROR;
+        }
+    };
+    let mut passed = 0;
+    for rom in roms.iter() {
+        let name = rom.file_name().unwrap_or_default().to_string_lossy();
+        match check(rom, options) {
+            Ok(Verdict::Pass) => {
+                println!("PASS {}", name);
+                passed += 1;
+            }
+            Ok(Verdict::Fail(expected, actual)) => {
+                println!("FAIL {}", name);
+                print!("{}", expected.diff(&actual));
+            }
+            Ok(Verdict::MissingCapture) => println!("FAIL {} (no expected screen)", name),
+            Err(e) => println!("FAIL {} ({})", name, e),
+        }
+    }
+    println!("{}/{} passed", passed, roms.len());
+    if passed == roms.len() {
+        EXIT_SUCCESS
+    } else {
+        EXIT_MISMATCH
+    }
+}
@@ -0,0 +1,119 @@
+/// the mnemonic of an opcode (in the style of Cowgod's reference), `DW` for unknown opcodes
+pub fn disassemble(opcode: u16) -> String {
+    let x = (opcode >> 8) & 0xF;
+    let y = (opcode >> 4) & 0xF;
+    let n = opcode & 0xF;
+    let nn = opcode & 0xFF;
+    let nnn = opcode & 0xFFF;
+    match (opcode >> 12, x, y, n) {
+        (0x0, 0x0, 0xC, _) => format!("SCD {}", n),
+        (0x0, 0x0, 0xE, 0x0) => "CLS".to_owned(),
+        (0x0, 0x0, 0xE, 0xE) => "RET".to_owned(),
+        (0x0, 0x0, 0xF, 0xB) => "SCR".to_owned(),
+        (0x0, 0x0, 0xF, 0xC) => "SCL".to_owned(),
+        (0x0, 0x0, 0xF, 0xD) => "EXIT".to_owned(),
+        (0x0, 0x0, 0xF, 0xE) => "LOW".to_owned(),
+        (0x0, 0x0, 0xF, 0xF) => "HIGH".to_owned(),
+        (0x1, ..) => format!("JP {:#05X}", nnn),
+        (0x2, ..) => format!("CALL {:#05X}", nnn),
+        (0x3, ..) => format!("SE V{:X}, {:#04X}", x, nn),
+        (0x4, ..) => format!("SNE V{:X}, {:#04X}", x, nn),
+        (0x5, _, _, 0x0) => format!("SE V{:X}, V{:X}", x, y),
+        (0x6, ..) => format!("LD V{:X}, {:#04X}", x, nn),
+        (0x7, ..) => format!("ADD V{:X}, {:#04X}", x, nn),
+        (0x8, _, _, 0x0) => format!("LD V{:X}, V{:X}", x, y),
+        (0x8, _, _, 0x1) => format!("OR V{:X}, V{:X}", x, y),
+        (0x8, _, _, 0x2) => format!("AND V{:X}, V{:X}", x, y),
+        (0x8, _, _, 0x3) => format!("XOR V{:X}, V{:X}", x, y),
+        (0x8, _, _, 0x4) => format!("ADD V{:X}, V{:X}", x, y),
+        (0x8, _, _, 0x5) => format!("SUB V{:X}, V{:X}", x, y),
+        (0x8, _, _, 0x6) => format!("SHR V{:X}, V{:X}", x, y),
+        (0x8, _, _, 0x7) => format!("SUBN V{:X}, V{:X}", x, y),
+        (0x8, _, _, 0xE) => format!("SHL V{:X}, V{:X}", x, y),
+        (0x9, _, _, 0x0) => format!("SNE V{:X}, V{:X}", x, y),
+        (0xA, ..) => format!("LD I, {:#05X}", nnn),
+        (0xB, ..) => format!("JP V0, {:#05X}", nnn),
+        (0xC, ..) => format!("RND V{:X}, {:#04X}", x, nn),
+        (0xD, ..) => format!("DRW V{:X}, V{:X}, {}", x, y, n),
+        (0xE, _, 0x9, 0xE) => format!("SKP V{:X}", x),
+        (0xE, _, 0xA, 0x1) => format!("SKNP V{:X}", x),
+        (0xF, _, 0x0, 0x7) => format!("LD V{:X}, DT", x),
+        (0xF, _, 0x0, 0xA) => format!("LD V{:X}, K", x),
+        (0xF, _, 0x1, 0x5) => format!("LD DT, V{:X}", x),
+        (0xF, _, 0x1, 0x8) => format!("LD ST, V{:X}", x),
+        (0xF, _, 0x1, 0xE) => format!("ADD I, V{:X}", x),
+        (0xF, _, 0x2, 0x9) => format!("LD F, V{:X}", x),
+        (0xF, _, 0x3, 0x0) => format!("LD HF, V{:X}", x),
+        (0xF, _, 0x3, 0x3) => format!("LD B, V{:X}", x),
+        (0xF, _, 0x5, 0x5) => format!("LD [I], V{:X}", x),
+        (0xF, _, 0x6, 0x5) => format!("LD V{:X}, [I]", x),
+        _ => format!("DW {:#06X}", opcode),
+    }
+}
+
+/// instructions added by SUPER-CHIP (scrolling, high resolution, large font and flags)
+pub fn is_super_chip(opcode: u16) -> bool {
+    matches!(opcode & 0xFFF0, 0x00C0)
+        || (0x00FB..=0x00FF).contains(&opcode)
+        || matches!(opcode & 0xF00F, 0xD000)
+        || matches!(opcode & 0xF0FF, 0xF030 | 0xF075 | 0xF085)
+}
+
+/// one line per instruction: `ADDRESS: OPCODE  MNEMONIC`
+pub fn format_instruction(address: u16, opcode: u16) -> String {
+    format!("{:04X}: {:04X}  {}", address, opcode, disassemble(opcode))
+}
+
+/// disassembles every 2 bytes of a rom loaded at `load_address`, a trailing byte is shown as data
+pub fn disassemble_rom(rom: &[u8], load_address: u16) -> String {
+    rom.chunks(2)
+        .enumerate()
+        .map(|(i, word)| {
+            let address = load_address.wrapping_add(i as u16 * 2);
+            match word {
+                [high, low] => format_instruction(address, u16::from_be_bytes([*high, *low])),
+                _ => format!("{:04X}: {:02X}    DB {:#04X}", address, word[0], word[0]),
+            }
+        })
+        .map(|l| l + "\n")
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::emulator::disassembler::{disassemble, disassemble_rom, is_super_chip};
+
+    #[test]
+    fn mnemonics() {
+        for (opcode, mnemonic) in [
+            (0x00E0, "CLS"),
+            (0x00C4, "SCD 4"),
+            (0x1234, "JP 0x234"),
+            (0x3A0F, "SE VA, 0x0F"),
+            (0x8AB6, "SHR VA, VB"),
+            (0xD125, "DRW V1, V2, 5"),
+            (0xE19E, "SKP V1"),
+            (0xF265, "LD V2, [I]"),
+            (0x5121, "DW 0x5121"),
+            (0xFFFF, "DW 0xFFFF"),
+        ] {
+            assert_eq!(disassemble(opcode), mnemonic);
+        }
+    }
+
+    #[test]
+    fn super_chip() {
+        assert!([0x00C1, 0x00FF, 0xD120, 0xF130, 0xFF85]
+            .iter()
+            .all(|o| is_super_chip(*o)));
+        assert!(![0x00E0, 0xD121, 0xF129].iter().any(|o| is_super_chip(*o)));
+    }
+
+    #[test]
+    fn rom() {
+        assert_eq!(
+            disassemble_rom(&[0x00, 0xE0, 0x12, 0x00, 0xAB], 0x200),
+            "0200: 00E0  CLS\n0202: 1200  JP 0x200\n0204: AB    DB 0xAB\n"
+        );
+    }
+}
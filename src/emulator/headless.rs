@@ -0,0 +1,432 @@
+use std::fs;
+
+use crate::cli::EmulationOptions;
+use crate::emulator::Emulator;
+use crate::sdl2_interaction::screen::Screen;
+use crate::{FPS, OPCODES_PER_FRAME};
+
+pub static EXIT_SUCCESS: i32 = 0;
+pub static EXIT_MISMATCH: i32 = 1;
+pub static EXIT_ERROR: i32 = 2;
+
+static STANDARD_FRAMES: u32 = 600;
+static STANDARD_PRESS_FRAMES: u32 = 5;
+
+/// a hex key held down from `frame` for `frames` frames
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KeyPress {
+    pub frame: u32,
+    pub key: u8,
+    pub frames: u32,
+}
+impl KeyPress {
+    /// parses `FRAME:KEY[:FRAMES]`, the key being a single hex digit
+    pub fn parse(s: &str) -> Option<Self> {
+        let mut parts = s.split(':');
+        let frame = parts.next()?.parse().ok()?;
+        let key = u8::from_str_radix(parts.next()?, 16)
+            .ok()
+            .filter(|k| *k < 16)?;
+        let frames = match parts.next() {
+            Some(f) => f.parse().ok().filter(|f| *f > 0)?,
+            None => STANDARD_PRESS_FRAMES,
+        };
+        parts
+            .next()
+            .is_none()
+            .then_some(Self { frame, key, frames })
+    }
+
+    pub fn is_held(&self, frame: u32) -> bool {
+        frame >= self.frame && frame - self.frame < self.frames
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CaptureFormat {
+    Text,
+    Pbm,
+}
+
+/// the visible (logical) pixels of the screen, `true` being `on`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Capture {
+    width: usize,
+    height: usize,
+    pixels: Vec<bool>,
+}
+impl Capture {
+    pub fn from_screen(screen: &Screen) -> Self {
+        let width = screen.get_width();
+        let height = screen.get_height();
+        Self {
+            width,
+            height,
+            pixels: (0..height)
+                .flat_map(|y| (0..width).map(move |x| screen.get(x, y)))
+                .collect(),
+        }
+    }
+
+    pub fn format(&self, format: CaptureFormat) -> String {
+        match format {
+            CaptureFormat::Text => self.to_text(),
+            CaptureFormat::Pbm => self.to_pbm(),
+        }
+    }
+
+    /// one line per row, `#` is `on` and `.` is `off`
+    pub fn to_text(&self) -> String {
+        self.pixels
+            .chunks(self.width)
+            .map(|r| {
+                r.iter()
+                    .map(|p| if *p { '#' } else { '.' })
+                    .collect::<String>()
+                    + "\n"
+            })
+            .collect()
+    }
+
+    /// plain (ascii) portable bitmap
+    pub fn to_pbm(&self) -> String {
+        format!("P1\n{} {}\n", self.width, self.height)
+            + &self
+                .pixels
+                .chunks(self.width)
+                .map(|r| {
+                    r.iter()
+                        .map(|p| if *p { "1" } else { "0" })
+                        .collect::<Vec<&str>>()
+                        .join(" ")
+                        + "\n"
+                })
+                .collect::<String>()
+    }
+
+    /// a text grid of `self` (expected) with `+` marking pixels only `on` in `other` and `-`
+    /// marking pixels only `on` in `self`
+    pub fn diff(&self, other: &Self) -> String {
+        if (self.width, self.height) != (other.width, other.height) {
+            return format!(
+                "expected a {}x{} screen, got {}x{}\n",
+                self.width, self.height, other.width, other.height
+            );
+        }
+        let differing = self
+            .pixels
+            .iter()
+            .zip(other.pixels.iter())
+            .filter(|(a, b)| a != b)
+            .count();
+        format!("{} pixels differ:\n", differing)
+            + &self
+                .pixels
+                .iter()
+                .zip(other.pixels.iter())
+                .map(|(a, b)| match (a, b) {
+                    (true, true) => '#',
+                    (false, false) => '.',
+                    (false, true) => '+',
+                    (true, false) => '-',
+                })
+                .collect::<Vec<char>>()
+                .chunks(self.width)
+                .map(|r| r.iter().collect::<String>() + "\n")
+                .collect::<String>()
+    }
+
+    /// parses either a plain portable bitmap or a text grid
+    pub fn parse(s: &str) -> Option<Self> {
+        if s.trim_start().starts_with("P1") {
+            Self::parse_pbm(s)
+        } else {
+            Self::parse_text(s)
+        }
+    }
+
+    fn parse_text(s: &str) -> Option<Self> {
+        let rows = s
+            .lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty())
+            .map(|l| {
+                l.chars()
+                    .map(|c| match c {
+                        '#' => Some(true),
+                        '.' => Some(false),
+                        _ => None,
+                    })
+                    .collect::<Option<Vec<bool>>>()
+            })
+            .collect::<Option<Vec<Vec<bool>>>>()?;
+        let width = rows.first()?.len();
+        rows.iter().all(|r| r.len() == width).then(|| Self {
+            width,
+            height: rows.len(),
+            pixels: rows.concat(),
+        })
+    }
+
+    fn parse_pbm(s: &str) -> Option<Self> {
+        let mut tokens = s
+            .lines()
+            .map(|l| l.split('#').next().unwrap_or_default())
+            .flat_map(str::split_whitespace)
+            .skip(1);
+        let width = tokens.next()?.parse().ok()?;
+        let height = tokens.next()?.parse().ok()?;
+        let pixels = tokens
+            .flat_map(str::chars)
+            .map(|c| match c {
+                '1' => Some(true),
+                '0' => Some(false),
+                _ => None,
+            })
+            .collect::<Option<Vec<bool>>>()?;
+        (pixels.len() == width * height).then_some(Self {
+            width,
+            height,
+            pixels,
+        })
+    }
+}
+
+/// options of `chip8-emulator --headless ROM [OPTIONS]`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HeadlessOptions {
+    pub rom: String,
+    pub frames: u32,
+    pub max_instructions: Option<u64>,
+    pub key_presses: Vec<KeyPress>,
+    pub output: Option<String>,
+    pub format: CaptureFormat,
+    pub expected: Option<String>,
+    pub emulation: EmulationOptions,
+}
+impl HeadlessOptions {
+    pub fn from_args<I: Iterator<Item = String>>(mut args: I) -> Result<Self, String> {
+        let mut rom = None;
+        let mut frames = STANDARD_FRAMES;
+        let mut max_instructions = None;
+        let mut key_presses = Vec::new();
+        let mut output = None;
+        let mut format = None;
+        let mut expected = None;
+        let mut emulation = EmulationOptions::default();
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or(format!("missing value for `{}`", arg));
+            match arg.as_str() {
+                "--headless" => {}
+                "--frames" => {
+                    frames = value()?
+                        .parse()
+                        .map_err(|_| "`--frames` expects a number".to_owned())?
+                }
+                "--instructions" => {
+                    max_instructions = Some(
+                        value()?
+                            .parse()
+                            .map_err(|_| "`--instructions` expects a number".to_owned())?,
+                    )
+                }
+                "--press" => {
+                    let press = value()?;
+                    key_presses.push(KeyPress::parse(&press).ok_or(format!(
+                        "invalid key press `{}`, expected `FRAME:KEY[:FRAMES]`",
+                        press
+                    ))?)
+                }
+                "--output" => output = Some(value()?),
+                "--format" => {
+                    format = Some(match value()?.as_str() {
+                        "text" => CaptureFormat::Text,
+                        "pbm" => CaptureFormat::Pbm,
+                        f => return Err(format!("unknown format `{}`, use `text` or `pbm`", f)),
+                    })
+                }
+                "--expect" => expected = Some(value()?),
+                _ if emulation.parse(&arg, &mut value)? => {}
+                _ if arg.starts_with("--") => return Err(format!("unknown option `{}`", arg)),
+                _ if rom.is_none() => rom = Some(arg),
+                _ => return Err(format!("unexpected argument `{}`", arg)),
+            }
+        }
+        let format = format.unwrap_or(match &output {
+            Some(o) if o.ends_with(".pbm") => CaptureFormat::Pbm,
+            _ => CaptureFormat::Text,
+        });
+        Ok(Self {
+            rom: rom.ok_or("missing rom path")?,
+            frames,
+            max_instructions,
+            key_presses,
+            output,
+            format,
+            expected,
+            emulation,
+        })
+    }
+}
+
+/// runs a rom without a window and returns the exit code of the process
+pub fn run(options: &HeadlessOptions) -> i32 {
+    let rom = match fs::read(&options.rom) {
+        Ok(rom) => rom,
+        Err(e) => {
+            eprintln!(
+                "Failed to read file \"{}\" - error: \"{}\".",
+                options.rom, e
+            );
+            return EXIT_ERROR;
+        }
+    };
+    let mut emulator = match options.emulation.apply(
+        Emulator::builder()
+            .fps(FPS)
+            .opcodes_per_frame(OPCODES_PER_FRAME),
+    ) {
+        Ok(builder) => builder.build_headless_chip8(),
+        Err(e) => {
+            eprintln!("{}.", e);
+            return EXIT_ERROR;
+        }
+    };
+    emulator.load_memory(rom);
+    if let Err(e) = options.emulation.load_state(&mut emulator) {
+        eprintln!("{}.", e);
+        return EXIT_ERROR;
+    }
+    let capture = emulator.run_headless(
+        options.frames,
+        options.max_instructions,
+        &options.key_presses,
+    );
+
+    let formatted = capture.format(options.format);
+    if let Some(path) = &options.output {
+        if let Err(e) = fs::write(path, formatted) {
+            eprintln!("Failed to write file \"{}\" - error: \"{}\".", path, e);
+            return EXIT_ERROR;
+        }
+    } else {
+        print!("{}", formatted);
+    }
+
+    if let Some(path) = &options.expected {
+        match fs::read_to_string(path)
+            .ok()
+            .and_then(|s| Capture::parse(&s))
+        {
+            Some(expected) if expected == capture => {}
+            Some(_) => {
+                eprintln!("Screen doesn't match \"{}\".", path);
+                return EXIT_MISMATCH;
+            }
+            None => {
+                eprintln!("Failed to read capture \"{}\".", path);
+                return EXIT_ERROR;
+            }
+        }
+    }
+    EXIT_SUCCESS
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::emulator::headless::{Capture, CaptureFormat, HeadlessOptions, KeyPress};
+
+    #[test]
+    fn parse_key_press() {
+        assert_eq!(
+            KeyPress::parse("10:a"),
+            Some(KeyPress {
+                frame: 10,
+                key: 0xA,
+                frames: 5
+            })
+        );
+        assert_eq!(
+            KeyPress::parse("0:F:1"),
+            Some(KeyPress {
+                frame: 0,
+                key: 0xF,
+                frames: 1
+            })
+        );
+        assert_eq!(KeyPress::parse("0:10"), None);
+        assert_eq!(KeyPress::parse("0:1:0"), None);
+        assert_eq!(KeyPress::parse("0:1:1:1"), None);
+        assert_eq!(KeyPress::parse("a:1"), None);
+    }
+
+    #[test]
+    fn key_press_held() {
+        let cut = KeyPress {
+            frame: 2,
+            key: 0,
+            frames: 3,
+        };
+        assert!(!cut.is_held(1));
+        assert!(cut.is_held(2));
+        assert!(cut.is_held(4));
+        assert!(!cut.is_held(5));
+
+        let cut = KeyPress {
+            frame: u32::MAX - 1,
+            key: 0,
+            frames: u32::MAX,
+        };
+        assert!(cut.is_held(u32::MAX));
+    }
+
+    #[test]
+    fn capture_round_trip() {
+        let cut = Capture::parse("#..\n.#.\n").unwrap();
+        assert_eq!(cut.to_text(), "#..\n.#.\n");
+        assert_eq!(cut.to_pbm(), "P1\n3 2\n1 0 0\n0 1 0\n");
+        assert_eq!(Capture::parse(&cut.to_pbm()), Some(cut.clone()));
+        assert_eq!(
+            Capture::parse("P1\n# comment\n3 2\n100010"),
+            Some(cut.clone())
+        );
+        assert_eq!(Capture::parse("#..\n.#"), None);
+        assert_eq!(
+            cut.diff(&Capture::parse("...\n.##").unwrap()),
+            "2 pixels differ:\n-..\n.#+\n"
+        );
+        assert_eq!(
+            cut.diff(&Capture::parse("..\n..").unwrap()),
+            "expected a 3x2 screen, got 2x2\n"
+        );
+        assert_eq!(Capture::parse("P1\n3 2\n1 0"), None);
+    }
+
+    #[test]
+    fn options() {
+        let cut = HeadlessOptions::from_args(
+            [
+                "--headless",
+                "rom.ch8",
+                "--frames",
+                "20",
+                "--press",
+                "3:5",
+                "--output",
+                "out.pbm",
+            ]
+            .into_iter()
+            .map(str::to_owned),
+        )
+        .unwrap();
+        assert_eq!(cut.rom, "rom.ch8");
+        assert_eq!(cut.frames, 20);
+        assert_eq!(cut.key_presses.len(), 1);
+        assert_eq!(cut.format, CaptureFormat::Pbm);
+        assert!(HeadlessOptions::from_args(["--headless".to_owned()].into_iter()).is_err());
+        assert!(
+            HeadlessOptions::from_args(["--frames".to_owned(), "x".to_owned()].into_iter())
+                .is_err()
+        );
+    }
+}
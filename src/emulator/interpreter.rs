@@ -1,32 +1,31 @@
 use std::collections::{HashSet, LinkedList};
+use std::path::PathBuf;
 
 use sdl2::keyboard::Scancode;
-use sdl2::pixels::Color;
 
 use crate::emulator::fixed_bit_numbers::{FixedBitNumber, IntoEmpty};
-use crate::sdl2_interaction::event_manager::Event;
+use crate::emulator::quirks::Quirks;
 use crate::sdl2_interaction::output::Output;
-use crate::sdl2_interaction::pressed_key::{HexToScancode, ScancodeToHex};
-use crate::sdl2_interaction::screen::{Chip8BoolToColor, Chip8ColorToBool};
-use crate::LogInfo;
+use crate::sdl2_interaction::pressed_key::Keymap;
+use crate::sdl2_interaction::screen::Screen;
+use crate::{LogInfo, LogWarning, SCREEN_HEIGHT, SCREEN_WIDTH};
 
 pub type Byte = FixedBitNumber<8>;
 pub type Address = FixedBitNumber<16>;
 
+static MEMORY_SIZE: usize = 4096;
+
 #[derive(Clone, Debug)]
 pub enum InterpreterEvent {
-    SetPixel(usize, usize, Color),
+    /// turns the pixel at the unscaled coordinates on or off (cheat mode)
+    SetPixel(usize, usize, bool),
     RedrawAll,
     QuickSave,
     QuickLoad,
     Save,
     Load,
-    Any,
-}
-impl Event for InterpreterEvent {
-    fn is_any(&self) -> bool {
-        matches!(&self, &InterpreterEvent::Any)
-    }
+    /// loads the save, e.g. one dropped onto the window
+    LoadFile(PathBuf),
 }
 
 pub trait Interpreter {
@@ -51,6 +50,16 @@ pub struct Chip8Interpreter {
     random_numbers: LinkedList<Byte>,
     awaiting_key: Option<usize>,
     finished: bool,
+    quirks: Quirks,
+    keymap: Keymap,
+    /// bit `n` is set if hex key `n` is held on a device other than the keyboard
+    hex_keys: u16,
+    /// the hex keys the keyboard may press
+    keyboard_keys: u16,
+    /// the hex keys the game read since the last frame
+    read_keys: u16,
+    /// the hex keys lit on the keypad
+    lit_keys: u16,
 }
 impl Chip8Interpreter {
     fn get_next_random(&mut self) -> Byte {
@@ -73,21 +82,13 @@ impl Chip8Interpreter {
         let mut screen = self
             .get_output()
             .get_screen()
-            .get_pixels()
-            .into_iter()
-            .flat_map(|(y, r)| {
-                r.into_iter()
-                    .filter(|&(_, c)| c.into_bool())
-                    .fold(Vec::new(), |mut a, (x, _)| {
-                        a.push(x as u8);
-                        a.push(y as u8);
-                        a
-                    })
-            })
+            .get_framebuffer()
+            .iter_on()
+            .flat_map(|(x, y)| [x as u8, y as u8])
             .collect::<Vec<u8>>();
         save.push(self.get_output().get_screen().get_scale() as u8);
-        save.push(self.get_output().get_screen().get_scroll_down() as u8);
-        save.push(self.get_output().get_screen().get_scroll_side() as u8);
+        // scrolling moves the pixels themselves, the old scroll offsets are always 0
+        save.append(&mut vec![0, 0]);
         save.push(((screen.len() & 0xFF00) >> 8) as u8);
         save.push((screen.len() & 0xFF) as u8);
         save.append(&mut screen);
@@ -126,86 +127,215 @@ impl Chip8Interpreter {
         save
     }
 
-    pub fn reset(&mut self) {
+    /// continues at `start` with a clear screen
+    pub fn reset(&mut self, start: u16) {
         self.finished = false;
-        self.pc = Address::from(0x200);
+        self.pc = Address::from(start as u32);
         self.output.clear();
     }
 
-    pub fn load(&mut self, data: Vec<u8>) {
+    /// loads a save created by `save`, nothing is changed if the save is invalid
+    pub fn load(&mut self, data: Vec<u8>) -> Result<(), String> {
         "Loading ...".log();
+        let mut save = SaveReader {
+            data: &data,
+            position: 0,
+        };
+        let random_numbers = save.take(256)?;
+        let memory = save.take(MEMORY_SIZE)?;
+        let scale = save.byte()? as usize;
+        if scale != 1 && scale != 2 {
+            return Err(format!("invalid scale {}", scale));
+        }
+        save.take(2)?;
+        let screen_length = save.word()? as usize;
+        let screen = save.take(screen_length)?;
+        if !screen.len().is_multiple_of(2)
+            || screen
+                .chunks(2)
+                .any(|p| p[0] as u32 >= SCREEN_WIDTH || p[1] as u32 >= SCREEN_HEIGHT)
+        {
+            return Err("invalid screen".to_owned());
+        }
+        let address_register = save.word()?;
+        let data_registers = save.take(16)?;
+        let sound_timer = save.byte()?;
+        let delay_timer = save.byte()?;
+        let pc = save.word()?;
+        let awaiting_key = save.byte()?;
+        let stack = save.rest();
+        if !stack.len().is_multiple_of(2) {
+            return Err("invalid stack".to_owned());
+        }
+
         self.finished = false;
-        self.random_numbers = LinkedList::from_iter(data[..256].iter().map(|b| Byte::from_u8(*b)));
-        self.memory = data[256..(4096 + 256)]
-            .iter()
-            .map(|b| Byte::from_u8(*b))
-            .collect::<Vec<Byte>>();
-        self.get_output_mut()
-            .get_screen_mut()
-            .set_scale(data[4096 + 256] as usize);
-        self.get_output_mut()
-            .get_screen_mut()
-            .set_scroll_down(data[4096 + 256 + 1] as usize);
-        self.get_output_mut()
-            .get_screen_mut()
-            .set_scroll_side(data[4096 + 256 + 2] as usize);
-        let screen_end =
-            (4096 + 256 + 5 + ((data[4096 + 256 + 3] as u16) << 8) + data[4096 + 256 + 4] as u16)
-                as usize;
-        let screen = &data[(4096 + 256 + 5)..screen_end];
-        self.output.get_screen_mut().clear();
-        let mut x = None;
-        for b in screen {
-            if let Some(cur_x) = x {
-                x = None;
-                self.get_output_mut().get_screen_mut().set(
-                    cur_x as usize,
-                    *b as usize,
-                    true.into_color(),
-                );
-            } else {
-                x = Some(*b);
-            }
+        self.random_numbers = random_numbers.iter().map(|b| Byte::from_u8(*b)).collect();
+        self.memory = memory.iter().map(|b| Byte::from_u8(*b)).collect();
+        let screen_mut = self.output.get_screen_mut();
+        screen_mut.set_scale(scale);
+        screen_mut.clear();
+        for p in screen.chunks(2) {
+            screen_mut.set(p[0] as usize, p[1] as usize, true);
         }
-        self.address_register = Address::from_combined(
-            &Byte::from_u8(data[screen_end]),
-            &Byte::from_u8(data[screen_end + 1]),
-        );
-        self.data_registers = data[(screen_end + 2)..(screen_end + 2 + 16)]
-            .iter()
-            .map(|b| Byte::from_u8(*b))
-            .collect::<Vec<Byte>>();
-        self.sound_timer = Byte::from_u8(data[(screen_end + 2 + 16)]);
-        self.delay_timer = Byte::from_u8(data[(screen_end + 2 + 16 + 1)]);
-        self.pc = Address::from_combined(
-            &Byte::from_u8(data[(screen_end + 2 + 16 + 2)]),
-            &Byte::from_u8(data[(screen_end + 2 + 16 + 3)]),
-        );
-        if data[(screen_end + 2 + 16 + 3 + 1)] == 0 {
-            self.awaiting_key = None;
-        } else {
-            self.awaiting_key = Some((0xF & data[(screen_end + 2 + 16 + 3 + 1)]) as usize);
+        self.address_register = Address::from(address_register as u32);
+        self.data_registers = data_registers.iter().map(|b| Byte::from_u8(*b)).collect();
+        self.sound_timer = Byte::from_u8(sound_timer);
+        self.delay_timer = Byte::from_u8(delay_timer);
+        self.pc = Address::from(pc as u32);
+        self.awaiting_key = (awaiting_key != 0).then_some((0xF & awaiting_key) as usize);
+        self.stack = stack
+            .chunks(2)
+            .map(|a| Address::from(u16::from_be_bytes([a[0], a[1]]) as u32))
+            .collect();
+        Ok(())
+    }
+
+    pub fn set_quirks(&mut self, quirks: Quirks) {
+        self.quirks = quirks;
+    }
+
+    pub fn set_keymap(&mut self, keymap: Keymap) {
+        self.keymap = keymap;
+    }
+
+    pub fn get_keymap(&self) -> &Keymap {
+        &self.keymap
+    }
+
+    /// the hex keys held on devices other than the keyboard, bit `n` being hex key `n`
+    pub fn set_hex_keys(&mut self, hex_keys: u16) {
+        self.hex_keys = hex_keys;
+    }
+
+    /// the hex keys the keyboard may press, bit `n` being hex key `n`
+    pub fn set_keyboard_keys(&mut self, keyboard_keys: u16) {
+        self.keyboard_keys = keyboard_keys;
+    }
+
+    fn is_key_pressed(&mut self, hex: u8, pressed_keys: &HashSet<Scancode>) -> bool {
+        if hex >= 16 {
+            return false;
         }
-        self.stack = LinkedList::new();
-        let mut a = Byte::new();
-        let mut in_mid_bit = false;
-        for b in data[(screen_end + 2 + 16 + 3 + 1)..].iter() {
-            if !in_mid_bit {
-                a = Byte::from_u8(*b);
-                in_mid_bit = true;
-            } else {
-                self.stack
-                    .push_back(Address::from_combined(&a, &Byte::from_u8(*b)));
-                a = Byte::new();
-                in_mid_bit = false;
-            }
+        self.read_keys |= 1 << hex;
+        self.hex_keys & 1 << hex != 0
+            || (self.keyboard_keys & 1 << hex != 0 && self.keymap.is_pressed(hex, pressed_keys))
+    }
+
+    /// replaces the random numbers with a permutation depending only on the seed
+    pub fn seed_random(&mut self, seed: u64) {
+        // splitmix64
+        let mut state = seed;
+        let mut next = || {
+            state = state.wrapping_add(0x9E3779B97F4A7C15);
+            let mut z = state;
+            z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+            z ^ (z >> 31)
+        };
+        let mut numbers = (0..=255).collect::<Vec<u8>>();
+        for i in (1..numbers.len()).rev() {
+            numbers.swap(i, (next() % (i as u64 + 1)) as usize);
         }
+        self.random_numbers = numbers.into_iter().map(Byte::from_u8).collect();
+    }
+
+    pub fn get_state(&self) -> InterpreterState<'_> {
+        InterpreterState { interpreter: self }
+    }
+
+    /// stops the emulation of a rom that can't be interpreted any further
+    fn stop(&mut self, reason: String) {
+        format!("{} - program stopped!", reason).as_str().wlog();
+        self.finished = true;
+    }
+}
+
+struct SaveReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+impl<'a> SaveReader<'a> {
+    fn take(&mut self, length: usize) -> Result<&'a [u8], String> {
+        let taken = self
+            .data
+            .get(self.position..self.position + length)
+            .ok_or(format!("save ends after {} bytes", self.data.len()))?;
+        self.position += length;
+        Ok(taken)
+    }
+
+    fn byte(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn word(&mut self) -> Result<u16, String> {
+        let word = self.take(2)?;
+        Ok(u16::from_be_bytes([word[0], word[1]]))
+    }
+
+    fn rest(self) -> &'a [u8] {
+        &self.data[self.position..]
     }
 }
+
+/// a read-only view of the interpreter, e.g. for plugins
+pub struct InterpreterState<'a> {
+    interpreter: &'a Chip8Interpreter,
+}
+impl InterpreterState<'_> {
+    pub fn get_pc(&self) -> u16 {
+        self.interpreter.pc.into_u32() as u16
+    }
+
+    pub fn get_address_register(&self) -> u16 {
+        self.interpreter.address_register.into_u32() as u16
+    }
+
+    /// the value of `Vx`
+    pub fn get_data_register(&self, x: usize) -> u8 {
+        self.interpreter.data_registers[x % 16].into_u8()
+    }
+
+    pub fn get_delay_timer(&self) -> u8 {
+        self.interpreter.delay_timer.into_u8()
+    }
+
+    pub fn get_sound_timer(&self) -> u8 {
+        self.interpreter.sound_timer.into_u8()
+    }
+
+    /// the return addresses, the most recent one last
+    pub fn get_stack(&self) -> Vec<u16> {
+        self.interpreter
+            .stack
+            .iter()
+            .map(|a| a.into_u32() as u16)
+            .collect()
+    }
+
+    /// addresses outside of the memory wrap around
+    pub fn get_memory(&self, address: u16) -> u8 {
+        self.interpreter.memory[address as usize % MEMORY_SIZE].into_u8()
+    }
+
+    pub fn get_screen(&self) -> &Screen {
+        self.interpreter.output.get_screen()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.interpreter.finished
+    }
+}
+
+/// addresses outside of the memory wrap around
+fn memory_index(address: Address) -> usize {
+    address.into_usize() % MEMORY_SIZE
+}
+
 impl Interpreter for Chip8Interpreter {
     fn new(output: Output) -> Self {
         Self {
-            memory: [Byte::new(); 4096].to_vec(),
+            memory: [Byte::new(); MEMORY_SIZE].to_vec(),
             data_registers: [Byte::new(); 16].to_vec(),
             address_register: Address::new(),
             stack: LinkedList::new(),
@@ -224,10 +354,21 @@ impl Interpreter for Chip8Interpreter {
                 LinkedList::from_iter(a.drain().map(Byte::from_u8))
             },
             awaiting_key: None,
+            quirks: Quirks::default(),
+            keymap: Keymap::default(),
+            hex_keys: 0,
+            keyboard_keys: u16::MAX,
+            read_keys: 0,
+            lit_keys: 0,
         }
     }
 
     fn next_frame(&mut self) {
+        if self.read_keys != self.lit_keys {
+            self.output.light_keys(self.read_keys);
+            self.lit_keys = self.read_keys;
+        }
+        self.read_keys = 0;
         if self.delay_timer.into_u32() > 0 {
             self.delay_timer.decrease_by_u32(1);
         }
@@ -244,8 +385,23 @@ impl Interpreter for Chip8Interpreter {
     fn shutdown(&mut self) {}
 
     fn load_memory(&mut self, bytes: Vec<u8>, starting_address: u16) {
-        for (i, b) in bytes.into_iter().enumerate() {
-            self.memory[i + starting_address as usize] = Byte::from(b as u32);
+        let fitting = self.memory.len().saturating_sub(starting_address as usize);
+        if bytes.len() > fitting {
+            format!(
+                "Only {} of {} bytes fit into the memory, the rest is ignored.",
+                fitting,
+                bytes.len()
+            )
+            .as_str()
+            .wlog();
+        }
+        for (m, b) in self
+            .memory
+            .iter_mut()
+            .skip(starting_address as usize)
+            .zip(bytes)
+        {
+            *m = Byte::from_u8(b);
         }
     }
 
@@ -254,13 +410,16 @@ impl Interpreter for Chip8Interpreter {
             return;
         }
         if let Some(x) = self.awaiting_key {
-            if let Some(c) = pressed_keys.iter().find_map(|k| k.try_into_hex()) {
+            if let Some(c) = (0..16).find(|h| self.is_key_pressed(*h, pressed_keys)) {
                 self.data_registers[x].set_by_u32(c as u32);
                 self.awaiting_key = None;
             }
             return;
         }
-        let current = Address::from_combined(&self.memory[&self.pc], &self.memory[&self.pc + 1]);
+        let current = Address::from_combined(
+            &self.memory[memory_index(self.pc)],
+            &self.memory[memory_index(&self.pc + 1)],
+        );
         let x = &current.get_bitrange(8, 4);
         let vx = self.data_registers[x];
         let vy = self.data_registers[&current.get_bitrange(4, 4)];
@@ -283,7 +442,12 @@ impl Interpreter for Chip8Interpreter {
                             self.output.clear();
                             self.pc.increase_by_u32(2);
                         }
-                        0x0EE => self.pc = self.stack.pop_back().unwrap(),
+                        0x0EE => match self.stack.pop_back() {
+                            Some(address) => self.pc = address,
+                            None => {
+                                self.stop(format!("Returning with an empty stack at {}", self.pc))
+                            }
+                        },
                         0x0FD => self.finished = true,
                         0x0FB => {
                             self.output.scroll_side(4);
@@ -301,10 +465,10 @@ impl Interpreter for Chip8Interpreter {
                             self.output.get_screen_mut().set_scale(1);
                             self.pc.increase_by_u32(2);
                         }
-                        _ => panic!(
+                        _ => self.stop(format!(
                             "No rom interaction possible (opcode: {}, pc: {})",
                             current, self.pc
-                        ),
+                        )),
                     }
                 }
             }
@@ -329,9 +493,16 @@ impl Interpreter for Chip8Interpreter {
             0x7 => self.data_registers[x].increase(&l2_const).into_empty(),
             0x8 => match l1_const.into_u32() {
                 0x0 => self.data_registers[x].set(&vy),
-                0x1 => self.data_registers[x].or(&vy),
-                0x2 => self.data_registers[x].and(&vy),
-                0x3 => self.data_registers[x].xor(&vy),
+                0x1..=0x3 => {
+                    match l1_const.into_u32() {
+                        0x1 => self.data_registers[x].or(&vy),
+                        0x2 => self.data_registers[x].and(&vy),
+                        _ => self.data_registers[x].xor(&vy),
+                    }
+                    if self.quirks.reset_vf {
+                        self.data_registers[0xF].set_bool(false);
+                    }
+                }
                 0x4 => {
                     let f = self.data_registers[x].increase(&vy);
                     self.data_registers[0xF].set_bool(f);
@@ -341,6 +512,9 @@ impl Interpreter for Chip8Interpreter {
                     self.data_registers[0xF].set_bool(f);
                 }
                 0x6 => {
+                    if self.quirks.shift_vy {
+                        self.data_registers[x].set(&vy);
+                    }
                     let f = self.data_registers[x].shift_right();
                     self.data_registers[0xF].set_bool(f);
                 }
@@ -349,16 +523,24 @@ impl Interpreter for Chip8Interpreter {
                     self.data_registers[0xF].set_bool(f);
                 }
                 0xE => {
+                    if self.quirks.shift_vy {
+                        self.data_registers[x].set(&vy);
+                    }
                     let f = self.data_registers[x].shift_left();
                     self.data_registers[0xF].set_bool(f);
                 }
-                _ => panic!("Unknown variable opcode {}.", current),
+                _ => self.stop(format!("Unknown variable opcode {}", current)),
             },
             0x9 => vx.execute_if_not_equals(&vy, || self.pc.increase_by_u32(2).into_empty()),
             0xA => self.address_register.set(&l3_const),
-            0xB => self
-                .pc
-                .set_take_ownership(&l3_const + &self.data_registers[0]),
+            0xB => {
+                let offset = if self.quirks.jump_vx {
+                    vx
+                } else {
+                    self.data_registers[0]
+                };
+                self.pc.set_take_ownership(&l3_const + &offset)
+            }
             0xC => {
                 let nr = &self.get_next_random();
                 self.data_registers[x].set(nr);
@@ -366,54 +548,46 @@ impl Interpreter for Chip8Interpreter {
             }
             0xD => {
                 self.data_registers[0xF].set_bool(false);
-                if l1_const.into_usize() == 0 && self.output.get_screen().get_scale() == 1 {
-                    for row in 0..16 {
-                        for offset in 0..=1u32 {
-                            for pix in 0..8 {
-                                if self.memory[&self.address_register + (row + offset)]
-                                    .get_bit(7 - pix)
-                                {
-                                    let collision = self.output.swap(
-                                        (&vx + pix as u32).into_usize(),
-                                        (&vy + row).into_usize(),
-                                    );
-                                    self.data_registers[0xF].set_bool(collision);
-                                }
-                            }
+                let width = self.output.get_screen().get_width();
+                let height = self.output.get_screen().get_height();
+                let (start_x, start_y) = (vx.into_usize() % width, vy.into_usize() % height);
+                // DXY0 draws 16x16 sprites in high resolution
+                let (rows, bytes_per_row) =
+                    if l1_const.into_usize() == 0 && self.output.get_screen().get_scale() == 1 {
+                        (16, 2)
+                    } else {
+                        (l1_const.into_u32(), 1)
+                    };
+                for row in 0..rows {
+                    for column in 0..bytes_per_row * 8 {
+                        let byte = self.memory[memory_index(
+                            &self.address_register + (row * bytes_per_row + column / 8),
+                        )];
+                        if !byte.get_bit(7 - (column % 8) as u8) {
+                            continue;
                         }
-                    }
-                } else {
-                    for row in 0..l1_const.into_u32() {
-                        for bit in 0..8 {
-                            if self.memory[&self.address_register + row].get_bit(7 - bit) {
-                                let collision = self.output.swap(
-                                    (&vx + bit as u32).into_usize(),
-                                    (&vy + row).into_usize(),
-                                );
-                                self.data_registers[0xF].set_bool(collision);
-                            }
+                        let (x, y) = (start_x + column as usize, start_y + row as usize);
+                        if self.quirks.clip_sprites && (x >= width || y >= height) {
+                            continue;
+                        }
+                        if self.output.swap(x, y) {
+                            self.data_registers[0xF].set_bool(true);
                         }
                     }
                 }
             }
             0xE => match l2_const.into_u32() {
                 0x9E => {
-                    if let Some(k) = vx.into_u8().try_into_scancode() {
-                        if pressed_keys.contains(&k) {
-                            self.pc.increase_by_u32(2);
-                        }
+                    if vx.into_u32() < 16 && self.is_key_pressed(vx.into_u8(), pressed_keys) {
+                        self.pc.increase_by_u32(2);
                     }
                 }
                 0xA1 => {
-                    if let Some(k) = vx.into_u8().try_into_scancode() {
-                        if !pressed_keys.contains(&k) {
-                            self.pc.increase_by_u32(2);
-                        }
-                    } else {
+                    if vx.into_u32() >= 16 || !self.is_key_pressed(vx.into_u8(), pressed_keys) {
                         self.pc.increase_by_u32(2);
                     }
                 }
-                _ => panic!("Unknown opcode {}.", current),
+                _ => self.stop(format!("Unknown opcode {}", current)),
             },
             0xF => {
                 let second_hex = current.get_bitrange(8, 4);
@@ -441,27 +615,39 @@ impl Interpreter for Chip8Interpreter {
                         .address_register
                         .set_by_u32(self.data_registers[x].into_u32() * 10 + 80),
                     0x33 => {
-                        self.memory[&self.address_register].set_by_u32(vx.into_u32() / 100);
-                        self.memory[&self.address_register + 1]
+                        self.memory[memory_index(self.address_register)]
+                            .set_by_u32(vx.into_u32() / 100);
+                        self.memory[memory_index(&self.address_register + 1)]
                             .set_by_u32(vx.into_u32() % 100 / 10);
-                        self.memory[&self.address_register + 2].set_by_u32(vx.into_u32() % 10);
+                        self.memory[memory_index(&self.address_register + 2)]
+                            .set_by_u32(vx.into_u32() % 10);
                     }
                     0x55 => {
                         for i in 0..=second_hex.into_u32() {
-                            self.memory[&self.address_register + i]
+                            self.memory[memory_index(&self.address_register + i)]
                                 .set(&self.data_registers[i as usize]);
                         }
+                        if self.quirks.increment_i {
+                            self.address_register
+                                .increase_by_u32(second_hex.into_u32() + 1)
+                                .into_empty();
+                        }
                     }
                     0x65 => {
                         for i in 0..=second_hex.into_u32() {
                             self.data_registers[i as usize]
-                                .set(&self.memory[&self.address_register + i]);
+                                .set(&self.memory[memory_index(&self.address_register + i)]);
+                        }
+                        if self.quirks.increment_i {
+                            self.address_register
+                                .increase_by_u32(second_hex.into_u32() + 1)
+                                .into_empty();
                         }
                     }
-                    _ => panic!("Unknown memory opcode {}.", current),
+                    _ => self.stop(format!("Unknown memory opcode {}", current)),
                 }
             }
-            _ => panic!("Unknown opcode {} at {}.", current, self.pc),
+            _ => self.stop(format!("Unknown opcode {} at {}", current, self.pc)),
         }
     }
 
@@ -473,3 +659,471 @@ impl Interpreter for Chip8Interpreter {
         &self.output
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::sync::mpsc;
+
+    use sdl2::keyboard::Scancode;
+
+    use crate::emulator::interpreter::{Chip8Interpreter, Interpreter};
+    use crate::emulator::{DOUBLE_SIZE_FONT, FONT};
+    use crate::sdl2_interaction::event_manager::{AppEventReceiver, IncomingEvent};
+    use crate::sdl2_interaction::keypad::KeypadEvent;
+    use crate::sdl2_interaction::output::Output;
+    use crate::sdl2_interaction::pressed_key::HexToScancode;
+
+    /// the receiver has to be kept alive, otherwise sending screen events fails
+    fn new_cut(program: &[u16]) -> (Chip8Interpreter, AppEventReceiver) {
+        let (sender, receiver) = mpsc::channel();
+        let mut cut = Chip8Interpreter::new(Output::new_headless(sender));
+        cut.load_memory(FONT.to_vec(), 0);
+        cut.load_memory(DOUBLE_SIZE_FONT.to_vec(), 80);
+        cut.load_memory(
+            program.iter().flat_map(|o| o.to_be_bytes()).collect(),
+            0x200,
+        );
+        (cut, receiver)
+    }
+
+    fn run(cut: &mut Chip8Interpreter, opcodes: usize) {
+        run_with_keys(cut, opcodes, &HashSet::new());
+    }
+
+    fn run_with_keys(cut: &mut Chip8Interpreter, opcodes: usize, keys: &HashSet<Scancode>) {
+        for _ in 0..opcodes {
+            cut.interpret_next(keys);
+        }
+    }
+
+    fn v(cut: &Chip8Interpreter, register: usize) -> u32 {
+        cut.data_registers[register].into_u32()
+    }
+
+    fn pixel(cut: &Chip8Interpreter, x: usize, y: usize) -> bool {
+        cut.output.get_screen().get(x, y)
+    }
+
+    #[test]
+    fn jump() {
+        let (mut cut, _r) = new_cut(&[0x1206, 0x0000, 0x0000, 0x1206]);
+        run(&mut cut, 1);
+        assert_eq!(cut.pc.into_u32(), 0x206);
+        run(&mut cut, 1);
+        assert!(cut.finished);
+        assert_eq!(cut.pc.into_u32(), 0x206);
+    }
+
+    #[test]
+    fn jump_with_offset() {
+        let (mut cut, _r) = new_cut(&[0x6004, 0xB300]);
+        run(&mut cut, 2);
+        assert_eq!(cut.pc.into_u32(), 0x304);
+    }
+
+    #[test]
+    fn nested_subroutines() {
+        let (mut cut, _r) = new_cut(&[
+            0x2206, // 0x200: call 0x206
+            0x6001, // 0x202
+            0x0000, // 0x204
+            0x220C, // 0x206: call 0x20C
+            0x00EE, // 0x208: return
+            0x0000, // 0x20A
+            0x6102, // 0x20C
+            0x00EE, // 0x20E: return
+        ]);
+        run(&mut cut, 2);
+        assert_eq!(cut.pc.into_u32(), 0x20C);
+        assert_eq!(cut.stack.len(), 2);
+        run(&mut cut, 2);
+        assert_eq!(cut.pc.into_u32(), 0x208);
+        assert_eq!(v(&cut, 1), 2);
+        run(&mut cut, 1);
+        assert_eq!(cut.pc.into_u32(), 0x202);
+        assert!(cut.stack.is_empty());
+        run(&mut cut, 1);
+        assert_eq!(v(&cut, 0), 1);
+    }
+
+    #[test]
+    fn skips() {
+        let (mut cut, _r) = new_cut(&[0x6005, 0x6105, 0x3005]);
+        run(&mut cut, 3);
+        assert_eq!(cut.pc.into_u32(), 0x208);
+
+        for (opcode, skipped) in [
+            (0x3005, true),
+            (0x3006, false),
+            (0x4005, false),
+            (0x4006, true),
+            (0x5010, true),
+            (0x5020, false),
+            (0x9010, false),
+            (0x9020, true),
+        ] {
+            let (mut cut, _r) = new_cut(&[0x6005, 0x6105, 0x6206, opcode]);
+            run(&mut cut, 4);
+            assert_eq!(
+                cut.pc.into_u32(),
+                if skipped { 0x20A } else { 0x208 },
+                "{:x}",
+                opcode
+            );
+        }
+    }
+
+    #[test]
+    fn set_and_add() {
+        let (mut cut, _r) = new_cut(&[0x6AFE, 0x7A03, 0x6F42, 0x7F01]);
+        run(&mut cut, 2);
+        assert_eq!(v(&cut, 0xA), 0x01);
+        assert_eq!(v(&cut, 0xF), 0);
+        run(&mut cut, 2);
+        assert_eq!(v(&cut, 0xF), 0x43);
+    }
+
+    #[test]
+    fn logical_operations() {
+        for (opcode, result) in [
+            (0x8010, 0b0101),
+            (0x8011, 0b0111),
+            (0x8012, 0b0100),
+            (0x8013, 0b0011),
+        ] {
+            let (mut cut, _r) = new_cut(&[0x6006, 0x6105, opcode]);
+            run(&mut cut, 3);
+            assert_eq!(v(&cut, 0), result, "{:x}", opcode);
+        }
+    }
+
+    #[test]
+    fn add_with_carry() {
+        for (a, b, result, carry) in [(1, 1, 2, 0), (0xFF, 1, 0, 1), (0xFF, 0xFF, 0xFE, 1)] {
+            let (mut cut, _r) = new_cut(&[0x6000 + a, 0x6100 + b, 0x8014]);
+            run(&mut cut, 3);
+            assert_eq!(v(&cut, 0), result as u32);
+            assert_eq!(v(&cut, 0xF), carry);
+        }
+        let (mut cut, _r) = new_cut(&[0x6FFF, 0x6101, 0x8F14]);
+        run(&mut cut, 3);
+        assert_eq!(v(&cut, 0xF), 1);
+    }
+
+    #[test]
+    fn subtract_with_borrow() {
+        for (opcode, a, b, result, no_borrow) in [
+            (0x8015, 5, 3, 2, 1),
+            (0x8015, 3, 5, 0xFE, 0),
+            (0x8015, 5, 5, 0, 1),
+            (0x8017, 3, 5, 2, 1),
+            (0x8017, 5, 3, 0xFE, 0),
+            (0x8017, 5, 5, 0, 1),
+        ] {
+            let (mut cut, _r) = new_cut(&[0x6000 + a, 0x6100 + b, opcode]);
+            run(&mut cut, 3);
+            assert_eq!(v(&cut, 0), result, "{:x} {} {}", opcode, a, b);
+            assert_eq!(v(&cut, 0xF), no_borrow, "{:x} {} {}", opcode, a, b);
+        }
+    }
+
+    #[test]
+    fn shifts() {
+        for (opcode, a, result, shifted_out) in [
+            (0x8016, 0x01, 0x00, 1),
+            (0x8016, 0x02, 0x01, 0),
+            (0x8016, 0x00, 0x00, 0),
+            (0x801E, 0x80, 0x00, 1),
+            (0x801E, 0x7F, 0xFE, 0),
+            (0x801E, 0xFF, 0xFE, 1),
+        ] {
+            let (mut cut, _r) = new_cut(&[0x6000 + a, 0x61FF, opcode]);
+            run(&mut cut, 3);
+            assert_eq!(v(&cut, 0), result, "{:x} {:x}", opcode, a);
+            assert_eq!(v(&cut, 0xF), shifted_out, "{:x} {:x}", opcode, a);
+            assert_eq!(v(&cut, 1), 0xFF);
+        }
+    }
+
+    #[test]
+    fn random_is_masked() {
+        let (mut cut, _r) = new_cut(&[0xC000, 0xC10F]);
+        run(&mut cut, 2);
+        assert_eq!(v(&cut, 0), 0);
+        assert!(v(&cut, 1) <= 0x0F);
+    }
+
+    #[test]
+    fn binary_coded_decimal() {
+        for (value, digits) in [(0, [0, 0, 0]), (255, [2, 5, 5]), (137, [1, 3, 7])] {
+            let (mut cut, _r) = new_cut(&[0x6000 + value, 0xA300, 0xF033]);
+            run(&mut cut, 3);
+            assert_eq!(
+                (0..3)
+                    .map(|i| cut.memory[0x300 + i].into_u32())
+                    .collect::<Vec<u32>>(),
+                digits,
+                "{}",
+                value
+            );
+        }
+    }
+
+    #[test]
+    fn add_to_address_register() {
+        let (mut cut, _r) = new_cut(&[0xAFFF, 0x6001, 0x6F12, 0xF01E]);
+        run(&mut cut, 4);
+        assert_eq!(cut.address_register.into_u32(), 0x1000);
+        assert_eq!(v(&cut, 0xF), 0x12);
+    }
+
+    #[test]
+    fn font_addresses() {
+        let (mut cut, _r) = new_cut(&[0x600A, 0xF029, 0xF030]);
+        run(&mut cut, 2);
+        assert_eq!(cut.address_register.into_u32(), 0xA * 5);
+        run(&mut cut, 1);
+        assert_eq!(cut.address_register.into_u32(), 0xA * 10 + 80);
+    }
+
+    #[test]
+    fn store_and_load_registers() {
+        let (mut cut, _r) = new_cut(&[0x6001, 0x6102, 0x6203, 0x6304, 0xA300, 0xF255]);
+        run(&mut cut, 6);
+        assert_eq!(
+            (0..4)
+                .map(|i| cut.memory[0x300 + i].into_u32())
+                .collect::<Vec<u32>>(),
+            [1, 2, 3, 0]
+        );
+        assert_eq!(cut.address_register.into_u32(), 0x300);
+
+        let (mut cut, _r) = new_cut(&[0xA200, 0x63FF, 0xF265]);
+        run(&mut cut, 3);
+        assert_eq!([v(&cut, 0), v(&cut, 1), v(&cut, 2)], [0xA2, 0x00, 0x63]);
+        assert_eq!(v(&cut, 3), 0xFF);
+        assert_eq!(cut.address_register.into_u32(), 0x200);
+
+        let (mut cut, _r) = new_cut(&[0xA300, 0x6F0F, 0xFF55, 0xA300, 0xF065]);
+        run(&mut cut, 5);
+        assert_eq!(cut.memory[0x30F].into_u32(), 0x0F);
+        assert_eq!(v(&cut, 0), 0);
+    }
+
+    #[test]
+    fn timers() {
+        let (mut cut, _r) = new_cut(&[0x6003, 0xF015, 0xF018, 0xF107]);
+        run(&mut cut, 3);
+        cut.next_frame();
+        run(&mut cut, 1);
+        assert_eq!(v(&cut, 1), 2);
+        assert_eq!(cut.sound_timer.into_u32(), 2);
+        cut.next_frame();
+        cut.next_frame();
+        cut.next_frame();
+        assert_eq!(cut.delay_timer.into_u32(), 0);
+        assert_eq!(cut.sound_timer.into_u32(), 0);
+    }
+
+    #[test]
+    fn key_skips() {
+        let pressed = HashSet::from([0x5u8.try_into_scancode().unwrap()]);
+        for (opcode, value, skipped) in [
+            (0xE09E, 0x05, true),
+            (0xE09E, 0x06, false),
+            (0xE09E, 0x10, false),
+            (0xE0A1, 0x05, false),
+            (0xE0A1, 0x06, true),
+            (0xE0A1, 0x10, true),
+        ] {
+            let (mut cut, _r) = new_cut(&[0x6000 + value, opcode]);
+            run_with_keys(&mut cut, 2, &pressed);
+            assert_eq!(
+                cut.pc.into_u32(),
+                if skipped { 0x206 } else { 0x204 },
+                "{:x} {:x}",
+                opcode,
+                value
+            );
+        }
+    }
+
+    #[test]
+    fn hex_keys() {
+        let (mut cut, _r) = new_cut(&[0x6006, 0xE09E, 0x0000, 0xF30A]);
+        cut.set_hex_keys(1 << 0x6);
+        run(&mut cut, 2);
+        assert_eq!(cut.pc.into_u32(), 0x206);
+        run(&mut cut, 2);
+        assert_eq!(cut.awaiting_key, None);
+        assert_eq!(v(&cut, 3), 0x6);
+    }
+
+    #[test]
+    fn keyboard_keys() {
+        let pressed = HashSet::from([0x5u8.try_into_scancode().unwrap()]);
+        let (mut cut, _r) = new_cut(&[0x6005, 0xE09E, 0x0000, 0xE09E]);
+        run_with_keys(&mut cut, 2, &pressed);
+        assert_eq!(cut.pc.into_u32(), 0x206);
+        cut.set_keyboard_keys(!(1 << 0x5));
+        run_with_keys(&mut cut, 1, &pressed);
+        assert_eq!(cut.pc.into_u32(), 0x208);
+    }
+
+    #[test]
+    fn lit_keys() {
+        let (mut cut, r) = new_cut(&[0x6006, 0xE09E, 0x1202]);
+        run(&mut cut, 3);
+        cut.next_frame();
+        run(&mut cut, 2);
+        cut.next_frame();
+        cut.next_frame();
+        let lit = r
+            .try_iter()
+            .filter_map(|e| match e {
+                IncomingEvent::Keypad(KeypadEvent::SetLeds(l)) => Some(l),
+                _ => None,
+            })
+            .collect::<Vec<u16>>();
+        assert_eq!(lit, [1 << 0x6, 0]);
+    }
+
+    #[test]
+    fn await_key() {
+        let (mut cut, _r) = new_cut(&[0xF30A, 0x6001]);
+        run(&mut cut, 3);
+        assert_eq!(cut.awaiting_key, Some(3));
+        assert_eq!(cut.pc.into_u32(), 0x202);
+        run_with_keys(&mut cut, 1, &HashSet::from([Scancode::V]));
+        assert_eq!(cut.awaiting_key, None);
+        assert_eq!(v(&cut, 3), 0xF);
+        run(&mut cut, 1);
+        assert_eq!(v(&cut, 0), 1);
+    }
+
+    #[test]
+    fn clear_screen() {
+        let (mut cut, _r) = new_cut(&[0xD005, 0x00E0]);
+        run(&mut cut, 1);
+        assert!(pixel(&cut, 0, 0));
+        run(&mut cut, 1);
+        assert!(!pixel(&cut, 0, 0));
+        assert_eq!(cut.pc.into_u32(), 0x204);
+    }
+
+    #[test]
+    fn draw_and_collide() {
+        // font `0` at 0x0, a single pixel `1` (0x20 .. 0x70) at 0x5
+        let (mut cut, _r) = new_cut(&[0x6002, 0xD115, 0xD115, 0xA005, 0xD001, 0xA000, 0xD001]);
+        run(&mut cut, 2);
+        assert_eq!(v(&cut, 0xF), 0);
+        assert!(pixel(&cut, 0, 0) && pixel(&cut, 3, 0) && !pixel(&cut, 4, 0));
+        assert!(pixel(&cut, 0, 1) && !pixel(&cut, 1, 1));
+        run(&mut cut, 1);
+        assert_eq!(v(&cut, 0xF), 1);
+        assert!((0..8).all(|x| (0..5).all(|y| !pixel(&cut, x, y))));
+
+        // 0x20 at x = 2 sets pixel 4, then 0xF0 at x = 2 collides with its last pixel
+        run(&mut cut, 2);
+        assert!(pixel(&cut, 4, 2));
+        assert_eq!(v(&cut, 0xF), 0);
+        run(&mut cut, 2);
+        assert_eq!(v(&cut, 0xF), 1);
+        assert!(!pixel(&cut, 4, 2) && pixel(&cut, 2, 2));
+    }
+
+    #[test]
+    fn draw_wraps_around() {
+        let (mut cut, _r) = new_cut(&[0x603E, 0x611F, 0xD015]);
+        run(&mut cut, 3);
+        assert!(pixel(&cut, 62, 31) && pixel(&cut, 63, 31));
+        assert!(pixel(&cut, 0, 31) && pixel(&cut, 1, 31) && !pixel(&cut, 2, 31));
+        assert!(pixel(&cut, 62, 0) && !pixel(&cut, 63, 0) && pixel(&cut, 1, 0));
+        assert!(pixel(&cut, 62, 3) && pixel(&cut, 1, 3));
+
+        let (mut cut, _r) = new_cut(&[0x00FF, 0x607E, 0x613F, 0xD011]);
+        run(&mut cut, 4);
+        assert!(pixel(&cut, 126, 63) && pixel(&cut, 1, 63) && !pixel(&cut, 2, 63));
+    }
+
+    #[test]
+    fn draw_large_sprite() {
+        let (mut cut, _r) = new_cut(&[0x00FF, 0xA300, 0x6004, 0x6102, 0xD010]);
+        cut.load_memory([0xFF, 0x01].repeat(16), 0x300);
+        run(&mut cut, 5);
+        for y in 2..18 {
+            assert!((4..12).all(|x| pixel(&cut, x, y)));
+            assert!((12..19).all(|x| !pixel(&cut, x, y)));
+            assert!(pixel(&cut, 19, y));
+        }
+        assert!(!pixel(&cut, 4, 18));
+        assert_eq!(v(&cut, 0xF), 0);
+    }
+
+    #[test]
+    fn invalid_opcodes_stop() {
+        for program in [[0x0123], [0x8008], [0xE000], [0xF0FF], [0x00EE]] {
+            let (mut cut, _r) = new_cut(&program);
+            run(&mut cut, 2);
+            assert!(cut.finished, "{:x}", program[0]);
+        }
+    }
+
+    #[test]
+    fn memory_wraps_around() {
+        let (mut cut, _r) = new_cut(&[0xAFFF, 0x6001, 0x616A, 0xF155, 0xBFFF]);
+        run(&mut cut, 4);
+        assert_eq!(cut.memory[0xFFF].into_u32(), 0x01);
+        assert_eq!(cut.memory[0x000].into_u32(), 0x6A);
+        run(&mut cut, 1);
+        assert_eq!(cut.pc.into_u32(), 0x1000);
+        run(&mut cut, 1);
+        assert_eq!(v(&cut, 0xA), 0x90);
+    }
+
+    #[test]
+    fn oversized_rom_is_cut() {
+        let (mut cut, _r) = new_cut(&[]);
+        cut.load_memory(vec![0x12; 4000], 0x200);
+        assert_eq!(cut.memory[0xFFF].into_u32(), 0x12);
+    }
+
+    #[test]
+    fn save_and_load() {
+        let (mut cut, _r) = new_cut(&[0x6105, 0xD115, 0xA123, 0x220A, 0x0000, 0xF215, 0x120C]);
+        run(&mut cut, 5);
+        let save = cut.save();
+
+        let (mut loaded, _r) = new_cut(&[]);
+        assert_eq!(loaded.load(save.clone()), Ok(()));
+        assert_eq!(loaded.save(), save);
+        assert_eq!(v(&loaded, 1), 5);
+        assert_eq!(loaded.address_register.into_u32(), 0x123);
+        assert_eq!(loaded.pc.into_u32(), 0x20C);
+        assert_eq!(loaded.stack.len(), 1);
+        assert_eq!(loaded.stack.back().unwrap().into_u32(), 0x208);
+        assert!(pixel(&loaded, 5, 5));
+        run(&mut loaded, 1);
+        assert!(loaded.finished);
+    }
+
+    #[test]
+    fn invalid_saves_are_rejected() {
+        let (mut cut, _r) = new_cut(&[0x6105]);
+        run(&mut cut, 1);
+        let save = cut.save();
+        let (mut loaded, _r) = new_cut(&[]);
+        for invalid in [
+            Vec::new(),
+            save[..save.len() - 1].to_vec(),
+            [save.as_slice(), &[0x02]].concat(),
+        ] {
+            assert!(loaded.load(invalid).is_err());
+        }
+        let mut invalid_scale = save.clone();
+        invalid_scale[256 + 4096] = 0;
+        assert!(loaded.load(invalid_scale).is_err());
+        assert_eq!(v(&loaded, 1), 0);
+    }
+}
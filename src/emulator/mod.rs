@@ -1,28 +1,51 @@
-use std::collections::HashSet;
-use std::fs::{read, write};
-use std::path::Path;
-use std::sync::mpsc;
-use std::time::SystemTime;
+use std::collections::{HashMap, HashSet};
+use std::fs::{read, write, File};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::RecvTimeoutError;
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::{Duration, SystemTime};
 
 use fixed_bit_numbers::IntoEmpty;
+use headless::{Capture, KeyPress};
+use sdl2::keyboard::Scancode;
 
+use crate::config::{rom_hash, settings, Settings};
+use crate::emulator::disassembler::format_instruction;
 use crate::emulator::interpreter::{Chip8Interpreter, Interpreter, InterpreterEvent};
-use crate::events::app::AppEvent;
+use crate::emulator::quirks::Quirks;
 use crate::events::input::InputEvent;
-use crate::events::EventRedirectManager;
-use crate::sdl2_interaction::audio_manager::AudioEvent;
-use crate::sdl2_interaction::event_manager::{AppEventReceiver, AppEventSender, IncomingEvent};
-use crate::sdl2_interaction::output::{Output, ScreenEvent};
-use crate::sdl2_interaction::screen::Chip8ColorToBool;
-use crate::{
-    get_fd, LogError, LogWarning, SCREEN_HEIGHT, SCREEN_WIDTH, SPEED_CHANGE_PER_KEYPRESS,
-    STARTING_SCALE,
+use crate::events::plugin::{Plugin, PluginContext, PluginEventManager, SharedPlugin};
+use crate::events::{EventBus, EventManager};
+use crate::sdl2_interaction::controller::{ControllerInput, ControllerMap};
+use crate::sdl2_interaction::event_manager::{
+    AppEventReceiver, AppEventSender, EventKind, IncomingEvent,
 };
+use crate::sdl2_interaction::input_sources::{InputAssignment, InputSource, PressedHexKeys};
+use crate::sdl2_interaction::led_matrix;
+use crate::sdl2_interaction::osd::Stats;
+use crate::sdl2_interaction::output::{Output, ScreenEvent};
+use crate::sdl2_interaction::pressed_key::Keymap;
+use crate::sdl2_interaction::rom_browser::{Choice, Navigation, RomBrowser};
+use crate::{get_fd, LogError, LogWarning, ROM_EXTENSIONS, SCREEN_HEIGHT, SCREEN_WIDTH};
 
+pub mod conformance;
+pub mod disassembler;
 mod fixed_bit_numbers;
+pub mod headless;
 pub mod interpreter;
+pub mod quirks;
+
+/// the start of saves, followed by the hash of the rom (older saves are only the state of the
+/// interpreter)
+static SAVE_MAGIC: &[u8; 8] = b"CH8SAVE1";
+/// the length of `rom_hash`
+static HASH_LENGTH: usize = 16;
 
-static FONT: [u8; 80] = [
+/// how often the stats are sent to the screen
+static STATS_INTERVAL: Duration = Duration::from_secs(1);
+
+pub(crate) static FONT: [u8; 80] = [
     0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
     0x20, 0x60, 0x20, 0x20, 0x70, // 1
     0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
@@ -60,85 +83,326 @@ static DOUBLE_SIZE_FONT: [u8; 160] = [
     0x00, 0x00, 0x00, 0x00, 0x00, 0xF0, 0x80, 0xF0, 0x80, 0x80, // F
 ];
 
-pub struct Emulator<T: Interpreter> {
+/// configures an emulator before it is created, e.g. to register plugins
+pub struct EmulatorBuilder {
     fps: f32,
     opcodes_per_frame: u32,
-    interpreter: T,
-    last_opcode: SystemTime,
-    opcode_counter: u32,
-    interpreter_receiver: AppEventReceiver,
-    app_state_event_sender: AppEventSender,
+    speed: i8,
+    plugins: Vec<SharedPlugin>,
+    quirks: Quirks,
+    seed: Option<u64>,
+    load_address: u16,
+    keymap: Keymap,
+    controllers: Vec<ControllerMap>,
+    inputs: InputAssignment,
+    trace: Option<Box<dyn Write>>,
 }
-impl Emulator<Chip8Interpreter> {
-    pub fn new_chip8(fps: f32, opcodes_per_frame: u32) -> Self {
-        let (audio_sender, audio_receiver) = mpsc::channel();
-        let (output_sender, output_receiver) = mpsc::channel();
-        let (interpreter_sender, interpreter_receiver) = mpsc::channel();
-
-        let event_manager = EventRedirectManager::new(vec![
-            (
-                audio_sender,
-                [
-                    IncomingEvent::SetSpeed(0),
-                    IncomingEvent::Audio(AudioEvent::Any),
-                ]
-                .to_vec(),
-            ),
-            (
-                output_sender,
-                [
-                    IncomingEvent::Screen(ScreenEvent::Any),
-                    IncomingEvent::App(AppEvent::Any),
-                    IncomingEvent::Pause(true),
-                ]
-                .to_vec(),
-            ),
-            (
-                interpreter_sender,
-                [
-                    IncomingEvent::Pause(false),
-                    IncomingEvent::Input(InputEvent::Any),
-                    IncomingEvent::RequestTermination,
-                    IncomingEvent::SetSpeed(0),
-                    IncomingEvent::Interpreter(InterpreterEvent::Any),
-                    IncomingEvent::Restart,
-                    IncomingEvent::NewGame,
-                ]
-                .to_vec(),
-            ),
+impl EmulatorBuilder {
+    pub fn fps(mut self, fps: f32) -> Self {
+        self.fps = fps;
+        self
+    }
+
+    pub fn opcodes_per_frame(mut self, opcodes_per_frame: u32) -> Self {
+        self.opcodes_per_frame = opcodes_per_frame;
+        self
+    }
+
+    /// uses the emulation settings (fps, opcodes per frame, speed, keymap, controller maps and
+    /// input assignment)
+    pub fn settings(self, settings: &Settings) -> Self {
+        Self {
+            fps: settings.fps,
+            opcodes_per_frame: settings.opcodes_per_frame,
+            speed: settings.speed,
+            keymap: settings.keymap.clone(),
+            controllers: settings.controllers.clone(),
+            inputs: settings.inputs.clone(),
+            ..self
+        }
+    }
+
+    pub fn quirks(mut self, quirks: Quirks) -> Self {
+        self.quirks = quirks;
+        self
+    }
+
+    /// makes the random numbers reproducible
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// where roms are loaded and started, 0x200 by default
+    pub fn load_address(mut self, load_address: u16) -> Self {
+        self.load_address = load_address;
+        self
+    }
+
+    pub fn keymap(mut self, keymap: Keymap) -> Self {
+        self.keymap = keymap;
+        self
+    }
+
+    /// writes every executed instruction to the writer
+    pub fn trace<W: Write + 'static>(mut self, writer: W) -> Self {
+        self.trace = Some(Box::new(writer));
+        self
+    }
+
+    /// plugins are called in the order they are registered
+    pub fn plugin<P: Plugin + 'static>(mut self, plugin: P) -> Self {
+        self.plugins.push(Arc::new(Mutex::new(Box::new(plugin))));
+        self
+    }
+
+    pub fn build_chip8(self) -> Emulator<Chip8Interpreter> {
+        let event_bus = EventBus::new();
+        let audio_receiver = event_bus.subscribe(&[EventKind::SetSpeed, EventKind::Audio]);
+        let keypad_receiver = event_bus.subscribe(&[EventKind::Audio, EventKind::Keypad]);
+        let led_matrix = settings().led_matrix.clone();
+        if led_matrix.is_enabled() {
+            led_matrix::spawn(led_matrix, event_bus.subscribe(&[EventKind::Screen]));
+        }
+        let output_receiver = event_bus.subscribe(&[
+            EventKind::Screen,
+            EventKind::App,
+            EventKind::Pause,
+            EventKind::KeymapDialog,
+            EventKind::Keypad,
+            EventKind::HexKeys,
+            EventKind::SetSpeed,
+            EventKind::SetCheatMode,
+        ]);
+        let interpreter_receiver = event_bus.subscribe(&[
+            EventKind::Pause,
+            EventKind::Input,
+            EventKind::RequestTermination,
+            EventKind::SetSpeed,
+            EventKind::Interpreter,
+            EventKind::Restart,
+            EventKind::NewGame,
+            EventKind::OpenRom,
+            EventKind::KeymapDialog,
         ]);
-        let app_state_event_sender = event_manager.get_event_sender();
+        let app_state_event_sender = event_bus.get_event_sender();
+        for plugin in self.plugins.iter() {
+            event_bus.register(Box::new(PluginEventManager::new(
+                Arc::clone(plugin),
+                PluginContext::new(app_state_event_sender.clone()),
+            )));
+        }
 
-        let mut interpreter = Chip8Interpreter::new(Output::new(
+        let output = Output::new(
             SCREEN_WIDTH,
             SCREEN_HEIGHT,
-            STARTING_SCALE,
             output_receiver,
             audio_receiver,
+            keypad_receiver,
             app_state_event_sender.clone(),
-        ));
+            self.plugins.clone(),
+        );
+        self.build(
+            output,
+            interpreter_receiver,
+            app_state_event_sender,
+            Some(event_bus),
+        )
+    }
+
+    /// creates an emulator without a window, audio or keypad thread, all events sent by the
+    /// interpreter (or plugins) end up in its own receiver and are discarded by `run_headless`
+    pub fn build_headless_chip8(self) -> Emulator<Chip8Interpreter> {
+        let (app_state_event_sender, interpreter_receiver) = mpsc::channel();
+        let output = Output::new_headless(app_state_event_sender.clone());
+        self.build(output, interpreter_receiver, app_state_event_sender, None)
+    }
+
+    fn build(
+        self,
+        output: Output,
+        interpreter_receiver: AppEventReceiver,
+        app_state_event_sender: AppEventSender,
+        event_bus: Option<EventBus>,
+    ) -> Emulator<Chip8Interpreter> {
+        let mut interpreter = Chip8Interpreter::new(output);
         interpreter.load_memory(FONT.to_vec(), 0);
         interpreter.load_memory(DOUBLE_SIZE_FONT.to_vec(), 80);
-        Self {
+        interpreter.set_quirks(self.quirks);
+        interpreter.set_keymap(self.keymap);
+        interpreter.set_keyboard_keys(self.inputs.get_keys(&InputSource::Keyboard));
+        if let Some(seed) = self.seed {
+            interpreter.seed_random(seed);
+        }
+        Emulator {
             interpreter,
-            fps,
-            opcodes_per_frame,
+            fps: self.fps,
+            opcodes_per_frame: self.opcodes_per_frame,
+            speed: self.speed,
             last_opcode: SystemTime::now(),
             opcode_counter: 0,
             interpreter_receiver,
+            plugins: self.plugins,
+            load_address: self.load_address,
+            controllers: self.controllers,
+            inputs: self.inputs,
+            trace: self.trace,
+            rom_hash: None,
+            plugin_context: PluginContext::new(app_state_event_sender.clone()),
             app_state_event_sender,
+            event_bus,
+        }
+    }
+}
+
+pub struct Emulator<T: Interpreter> {
+    fps: f32,
+    opcodes_per_frame: u32,
+    speed: i8,
+    interpreter: T,
+    last_opcode: SystemTime,
+    opcode_counter: u32,
+    interpreter_receiver: AppEventReceiver,
+    app_state_event_sender: AppEventSender,
+    /// `None` without a window, the events then go straight to the interpreter
+    event_bus: Option<EventBus>,
+    plugins: Vec<SharedPlugin>,
+    plugin_context: PluginContext,
+    load_address: u16,
+    /// the controller map of every player, players without a map use the last one
+    controllers: Vec<ControllerMap>,
+    /// the hex keys every input source may press
+    inputs: InputAssignment,
+    trace: Option<Box<dyn Write>>,
+    /// the `rom_hash` of the loaded rom, saved with the state
+    rom_hash: Option<String>,
+}
+impl Emulator<Chip8Interpreter> {
+    /// a builder using the global settings
+    pub fn builder() -> EmulatorBuilder {
+        let settings = settings();
+        EmulatorBuilder {
+            fps: settings.fps,
+            opcodes_per_frame: settings.opcodes_per_frame,
+            speed: settings.speed,
+            plugins: Vec::new(),
+            quirks: Quirks::default(),
+            seed: None,
+            load_address: 0x200,
+            keymap: settings.keymap.clone(),
+            controllers: settings.controllers.clone(),
+            inputs: settings.inputs.clone(),
+            trace: None,
+        }
+    }
+
+    /// changes the emulation settings, e.g. to the ones of a new rom
+    pub fn apply_settings(&mut self, settings: &Settings) {
+        self.fps = settings.fps;
+        self.opcodes_per_frame = settings.opcodes_per_frame;
+        self.speed = settings.speed;
+        self.interpreter.set_keymap(settings.keymap.clone());
+        self.controllers = settings.controllers.clone();
+        self.inputs = settings.inputs.clone();
+        self.interpreter
+            .set_keyboard_keys(self.inputs.get_keys(&InputSource::Keyboard));
+        self.interpreter
+            .get_output()
+            .send_to_app_state(ScreenEvent::SetPalette(settings.get_palette()));
+    }
+
+    /// the speed in steps, changed by the speed hotkeys
+    pub fn get_speed(&self) -> i8 {
+        self.speed
+    }
+
+    /// the keymap, changed by the keymap dialog
+    pub fn get_keymap(&self) -> &Keymap {
+        self.interpreter.get_keymap()
+    }
+
+    /// adds a manager to the event bus while the emulator is running, after the built-in ones and
+    /// the plugins
+    pub fn register(&self, manager: Box<dyn EventManager + Send>) {
+        match self.event_bus.as_ref() {
+            Some(event_bus) => event_bus.register(manager),
+            None => "Headless emulators have no event bus to register managers with.".wlog(),
+        }
+    }
+
+    pub fn new_chip8(fps: f32, opcodes_per_frame: u32) -> Self {
+        Self::builder()
+            .fps(fps)
+            .opcodes_per_frame(opcodes_per_frame)
+            .build_chip8()
+    }
+
+    /// creates an emulator without a window, audio or keypad thread, all events sent by the
+    /// interpreter end up in its own receiver and are discarded by `run_headless`
+    pub fn new_headless_chip8(fps: f32, opcodes_per_frame: u32) -> Self {
+        Self::builder()
+            .fps(fps)
+            .opcodes_per_frame(opcodes_per_frame)
+            .build_headless_chip8()
+    }
+
+    /// executes one instruction, tracing it if enabled
+    fn step(&mut self, pressed_keys: &HashSet<Scancode>) {
+        if let Some(trace) = self.trace.as_mut() {
+            let state = self.interpreter.get_state();
+            let pc = state.get_pc();
+            let opcode =
+                u16::from_be_bytes([state.get_memory(pc), state.get_memory(pc.wrapping_add(1))]);
+            writeln!(trace, "{}", format_instruction(pc, opcode)).elog("writing trace");
+        }
+        self.interpreter.interpret_next(pressed_keys);
+    }
+
+    fn next_frame(&mut self) {
+        self.interpreter.next_frame();
+        let state = self.interpreter.get_state();
+        for plugin in self.plugins.iter() {
+            plugin
+                .lock()
+                .unwrap()
+                .on_frame(&state, &self.plugin_context);
         }
     }
 
     pub fn load_memory(&mut self, opcodes: Vec<u8>) {
-        self.interpreter.reset();
-        self.interpreter.load_memory(opcodes, 0x200);
-        self.interpreter.get_output().redraw_all();
+        self.rom_hash = Some(rom_hash(&opcodes));
+        self.interpreter.reset(self.load_address);
+        self.interpreter.load_memory(opcodes, self.load_address);
+        self.interpreter.get_output().publish_frame();
     }
 
     pub fn save<P: AsRef<Path>>(&self, path: P) {
-        write(&path, self.interpreter.save())
-            .elog(format!("saving to {:?}", path.as_ref()).as_str());
+        match write(&path, self.get_save()) {
+            Ok(()) => self.toast("SAVED", path.as_ref()),
+            Err(e) => Err::<(), _>(e).elog(format!("saving to {:?}", path.as_ref()).as_str()),
+        }
+    }
+
+    /// the state of the interpreter after `SAVE_MAGIC` and the hash of the rom
+    pub fn get_save(&self) -> Vec<u8> {
+        let hash = self
+            .rom_hash
+            .clone()
+            .unwrap_or_else(|| "0".repeat(HASH_LENGTH));
+        [
+            SAVE_MAGIC.as_slice(),
+            hash.as_bytes(),
+            &self.interpreter.save(),
+        ]
+        .concat()
+    }
+
+    /// shows the action and the file name on the screen
+    fn toast(&self, action: &str, path: &Path) {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        self.interpreter
+            .get_output()
+            .send_to_app_state(ScreenEvent::Toast(format!("{} {}", action, name)));
     }
 
     pub fn load<P: AsRef<Path>>(&mut self, path: P) {
@@ -146,60 +410,309 @@ impl Emulator<Chip8Interpreter> {
             "Couldn't find quicksaves.".wlog();
             return;
         }
-        self.interpreter.load(read(path).unwrap());
-        self.interpreter.get_output().redraw_all();
+        match read(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|data| self.load_state(data))
+        {
+            Ok(()) => {
+                self.toast("LOADED", path.as_ref());
+                self.app_state_event_sender
+                    .send(IncomingEvent::Pause(true))
+                    .elog("sending pause after load")
+            }
+            Err(e) => format!("Couldn't load {:?}: {}.", path.as_ref(), e)
+                .as_str()
+                .wlog(),
+        }
+    }
+
+    /// loads a save, the emulation state is left untouched if it is invalid, warns if the save
+    /// belongs to another rom
+    pub fn load_state(&mut self, data: Vec<u8>) -> Result<(), String> {
+        let (hash, state) = match data.strip_prefix(SAVE_MAGIC.as_slice()) {
+            Some(rest) => {
+                let (hash, state) = rest
+                    .split_at_checked(HASH_LENGTH)
+                    .ok_or("missing rom hash")?;
+                (Some(hash), state.to_vec())
+            }
+            None => (None, data),
+        };
+        self.interpreter.load(state)?;
+        if hash
+            .zip(self.rom_hash.as_ref())
+            .is_some_and(|(s, r)| s != r.as_bytes())
+        {
+            "The save belongs to another rom.".wlog();
+            self.interpreter
+                .get_output()
+                .send_to_app_state(ScreenEvent::Toast("SAVE OF ANOTHER ROM".to_owned()));
+        }
+        self.interpreter.get_output().publish_frame();
+        Ok(())
+    }
+
+    /// runs `frames` frames as fast as possible (at most `max_instructions` opcodes) without
+    /// waiting between opcodes, pressing the scripted keys at their frames
+    pub fn run_headless(
+        &mut self,
+        frames: u32,
+        max_instructions: Option<u64>,
+        key_presses: &[KeyPress],
+    ) -> Capture {
+        let mut executed = 0;
+        for frame in 0..frames {
+            // computed from every press so overlapping presses of a key don't release each other
+            let pressed_keys = key_presses
+                .iter()
+                .filter(|p| p.is_held(frame))
+                .filter_map(|p| {
+                    self.interpreter
+                        .get_keymap()
+                        .get_scancodes(p.key)
+                        .first()
+                        .copied()
+                })
+                .collect::<HashSet<Scancode>>();
+            for _ in 0..self.opcodes_per_frame {
+                if max_instructions.is_some_and(|max| executed >= max) {
+                    break;
+                }
+                self.step(&pressed_keys);
+                executed += 1;
+            }
+            self.next_frame();
+            while self.interpreter_receiver.try_recv().is_ok() {}
+        }
+        Capture::from_screen(self.interpreter.get_output().get_screen())
+    }
+
+    /// the hex keys of the pressed keys, bit `n` being hex key `n`
+    fn get_keyboard_hex_keys(&self, pressed_keys: &HashSet<Scancode>) -> u16 {
+        (0..16)
+            .filter(|h| self.interpreter.get_keymap().is_pressed(*h, pressed_keys))
+            .fold(0, |keys, hex| keys | 1 << hex)
+    }
+
+    /// the hex keys of the player's pressed controller inputs, bit `n` being hex key `n`
+    fn get_controller_hex_keys(
+        &self,
+        player: usize,
+        pressed: &HashSet<(usize, ControllerInput)>,
+    ) -> u16 {
+        pressed
+            .iter()
+            .filter(|(p, _)| *p == player)
+            .filter_map(|(player, input)| {
+                self.controllers
+                    .get(*player)
+                    .or(self.controllers.last())?
+                    .get_hex(input)
+            })
+            .fold(0, |keys, hex| keys | 1 << hex)
+    }
+
+    /// keeps the keys the source may press and tells the interpreter and the window if they
+    /// changed
+    fn press_hex_keys(&mut self, pressed: &mut PressedHexKeys, source: InputSource, keys: u16) {
+        if pressed.set(source, keys & self.inputs.get_keys(&source)) {
+            self.interpreter.set_hex_keys(pressed.get_device_keys());
+            self.app_state_event_sender
+                .send(IncomingEvent::HexKeys(pressed.clone()))
+                .elog("sending the pressed hex keys");
+        }
+    }
+
+    /// binds the pressed key to the hex key of the dialog (Tab keeps the keys of the hex key, Esc
+    /// closes the dialog) and asks for the next hex key
+    fn answer_keymap_dialog(&mut self, hex: u8, key: Scancode) {
+        let next = match key {
+            Scancode::Escape => None,
+            Scancode::Tab => (hex < 0xF).then_some(hex + 1),
+            _ => {
+                let mut keymap = self.interpreter.get_keymap().clone();
+                keymap.bind(hex, key);
+                self.interpreter.set_keymap(keymap);
+                (hex < 0xF).then_some(hex + 1)
+            }
+        };
         self.app_state_event_sender
-            .send(IncomingEvent::Pause(true))
-            .elog("sending pause after load");
+            .send(IncomingEvent::KeymapDialog(next))
+            .elog("asking for the next key");
+    }
+
+    /// shows the rom browser until a rom is chosen, `None` if the emulator should quit
+    pub fn browse(&mut self, browser: &mut RomBrowser) -> Option<PathBuf> {
+        let frame_duration = Duration::from_secs_f32(1.0 / self.fps);
+        self.interpreter.get_output_mut().clear();
+        let mut page = Some(browser.get_page());
+        let mut last_frame = SystemTime::now();
+        let chosen = loop {
+            if let Some(page) = page.take() {
+                let output = self.interpreter.get_output();
+                output.send_to_app_state(ScreenEvent::Browser(Some(page)));
+                output.publish_frame();
+                last_frame = SystemTime::now();
+            } else if last_frame.elapsed().unwrap() >= frame_duration {
+                // keeps toasts and the size of the window up to date
+                self.interpreter.get_output().publish_frame();
+                last_frame = SystemTime::now();
+            }
+            match self.interpreter_receiver.recv_timeout(frame_duration) {
+                Ok(IncomingEvent::Input(input)) => {
+                    let navigation = match Navigation::from_input(&input) {
+                        Some(navigation) => navigation,
+                        None => continue,
+                    };
+                    match browser.navigate(navigation) {
+                        Some(Choice::Rom(path)) => break Some(path),
+                        Some(Choice::FileDialog) => {
+                            if let Some(path) = get_fd("roms")
+                                .add_filter("Chip8 Binary", &ROM_EXTENSIONS)
+                                .add_filter("all", &["*"])
+                                .set_title("Choose a Chip8 binary file")
+                                .pick_file()
+                            {
+                                break Some(path);
+                            }
+                        }
+                        None => {}
+                    }
+                    page = Some(browser.get_page());
+                }
+                Ok(IncomingEvent::OpenRom(path)) => break Some(path),
+                Ok(IncomingEvent::Interpreter(InterpreterEvent::LoadFile(_))) => {
+                    "Saves can only be loaded into a running game.".wlog()
+                }
+                Ok(IncomingEvent::RequestTermination) | Err(RecvTimeoutError::Disconnected) => {
+                    break None
+                }
+                Ok(_) | Err(RecvTimeoutError::Timeout) => {}
+            }
+        };
+        self.interpreter
+            .get_output()
+            .send_to_app_state(ScreenEvent::Browser(None));
+        chosen
     }
 
     pub fn run(&mut self) -> End {
         self.app_state_event_sender
             .send(IncomingEvent::Pause(false))
             .elog("sending unpause before run");
+        self.app_state_event_sender
+            .send(IncomingEvent::SetSpeed(self.speed))
+            .elog("sending speed before run");
         let mut pressed_keys = HashSet::new();
         let mut pause = false;
-        let mut speed = 1.0;
+        let mut keymap_dialog = None;
+        let mut pressed_controller_inputs = HashSet::new();
+        let mut pressed_keypad_keys = HashMap::new();
+        let mut pressed_hex_keys = PressedHexKeys::default();
+        let mut speed = settings().get_speed_factor(self.speed);
         let mut last_frame = SystemTime::now();
         let millis_between_frames = (1_000_000.0 / self.fps) as u128;
+        let mut stats_start = SystemTime::now();
+        let mut stats_frames = 0;
+        let mut stats_opcodes = self.opcode_counter;
         if std::fs::read_dir("./saves").is_err() {
             std::fs::create_dir("./saves").elog("creating save directory");
         }
         'main: loop {
             if last_frame.elapsed().unwrap().as_micros() > millis_between_frames {
-                self.app_state_event_sender
-                    .send(IncomingEvent::Screen(ScreenEvent::Update))
-                    .elog("updating");
+                self.interpreter.get_output().publish_frame();
                 last_frame = SystemTime::now();
+                stats_frames += 1;
+            }
+            if stats_start.elapsed().unwrap() >= STATS_INTERVAL {
+                self.interpreter
+                    .get_output()
+                    .send_to_app_state(ScreenEvent::Stats(Stats {
+                        frames: stats_frames,
+                        instructions: self.opcode_counter.wrapping_sub(stats_opcodes),
+                        speed: (speed * 100.0).round() as u32,
+                    }));
+                stats_start = SystemTime::now();
+                stats_frames = 0;
+                stats_opcodes = self.opcode_counter;
             }
             while let Ok(e) = self.interpreter_receiver.try_recv() {
                 match e {
                     IncomingEvent::Restart => return End::Restart,
                     IncomingEvent::NewGame => return End::NewGame,
+                    IncomingEvent::OpenRom(path) => return End::Open(path),
                     IncomingEvent::Pause(p) => pause = p,
-                    IncomingEvent::Input(i_e) => match i_e {
-                        InputEvent::KeyDown(k) => pressed_keys.insert(k).into_empty(),
-                        InputEvent::KeyUp(k) => pressed_keys.remove(&k).into_empty(),
-                        InputEvent::ClearKeys => pressed_keys.clear(),
-                        _ => {}
-                    },
+                    IncomingEvent::KeymapDialog(d) => {
+                        if keymap_dialog.is_none() {
+                            pressed_keys.clear();
+                        }
+                        keymap_dialog = d;
+                        let keys = self.get_keyboard_hex_keys(&pressed_keys);
+                        self.press_hex_keys(&mut pressed_hex_keys, InputSource::Keyboard, keys);
+                    }
+                    IncomingEvent::Input(InputEvent::KeyDown(k)) if keymap_dialog.is_some() => {
+                        self.answer_keymap_dialog(keymap_dialog.unwrap_or_default(), k)
+                    }
+                    // plugins may send any hex key, only 0-F exist
+                    IncomingEvent::Input(
+                        InputEvent::KeypadDown(_, hex) | InputEvent::KeypadUp(_, hex),
+                    ) if hex >= 16 => {}
+                    IncomingEvent::Input(i_e) => {
+                        match i_e {
+                            InputEvent::KeyDown(k) => pressed_keys.insert(k).into_empty(),
+                            InputEvent::KeyUp(k) => pressed_keys.remove(&k).into_empty(),
+                            InputEvent::ClearKeys => pressed_keys.clear(),
+                            InputEvent::ControllerDown(player, input) => pressed_controller_inputs
+                                .insert((player, input))
+                                .into_empty(),
+                            InputEvent::ControllerUp(player, input) => pressed_controller_inputs
+                                .remove(&(player, input))
+                                .into_empty(),
+                            InputEvent::KeypadDown(keypad, hex) => {
+                                *pressed_keypad_keys.entry(keypad).or_default() |= 1 << hex
+                            }
+                            InputEvent::KeypadUp(keypad, hex) => {
+                                *pressed_keypad_keys.entry(keypad).or_default() &= !(1 << hex)
+                            }
+                            _ => {}
+                        }
+                        let (source, keys) = match i_e {
+                            InputEvent::KeyDown(_)
+                            | InputEvent::KeyUp(_)
+                            | InputEvent::ClearKeys => (
+                                InputSource::Keyboard,
+                                self.get_keyboard_hex_keys(&pressed_keys),
+                            ),
+                            InputEvent::ControllerDown(player, _)
+                            | InputEvent::ControllerUp(player, _) => (
+                                InputSource::Controller(player),
+                                self.get_controller_hex_keys(player, &pressed_controller_inputs),
+                            ),
+                            InputEvent::KeypadDown(keypad, _) | InputEvent::KeypadUp(keypad, _) => {
+                                (InputSource::Keypad(keypad), pressed_keypad_keys[&keypad])
+                            }
+                            _ => continue,
+                        };
+                        self.press_hex_keys(&mut pressed_hex_keys, source, keys);
+                    }
                     IncomingEvent::RequestTermination => break 'main,
-                    IncomingEvent::SetSpeed(s) => speed = SPEED_CHANGE_PER_KEYPRESS.powi(s as i32),
+                    IncomingEvent::SetSpeed(s) => {
+                        self.speed = s;
+                        speed = settings().get_speed_factor(s);
+                    }
                     IncomingEvent::Interpreter(i_e) => match i_e {
-                        InterpreterEvent::SetPixel(x, y, c) => {
+                        InterpreterEvent::SetPixel(x, y, on) => {
                             let scale = self.interpreter.get_output().get_screen().get_scale();
-                            self.interpreter.get_output_mut().set(
-                                x / scale,
-                                y / scale,
-                                c.into_bool(),
-                            );
+                            self.interpreter
+                                .get_output_mut()
+                                .set(x / scale, y / scale, on);
                         }
                         InterpreterEvent::RedrawAll => {
-                            self.interpreter.get_output_mut().redraw_all()
+                            self.interpreter.get_output_mut().publish_frame()
                         }
                         InterpreterEvent::QuickSave => self.save(
                             format!(
-                                "./saves/quicksave-{}.ch8",
+                                "./saves/quicksave-{}.ch8-save",
                                 SystemTime::now()
                                     .duration_since(SystemTime::UNIX_EPOCH)
                                     .unwrap()
@@ -207,27 +720,17 @@ impl Emulator<Chip8Interpreter> {
                             )
                             .as_str(),
                         ),
-                        InterpreterEvent::QuickLoad => self.load(
-                            format!(
-                                "./saves/{}",
-                                std::fs::read_dir("./saves/")
-                                    .unwrap()
-                                    .filter_map(|f| f.ok())
+                        InterpreterEvent::QuickLoad => {
+                            match std::fs::read_dir("./saves/").ok().and_then(|d| {
+                                d.filter_map(|f| f.ok())
                                     .filter_map(|f| f.file_name().into_string().ok())
-                                    .reduce(|a, b| {
-                                        if a.starts_with("quicksave-")
-                                            && b.starts_with("quicksave-")
-                                            && a.cmp(&b).is_lt()
-                                        {
-                                            b
-                                        } else {
-                                            a
-                                        }
-                                    })
-                                    .unwrap()
-                            )
-                            .as_str(),
-                        ),
+                                    .filter(|f| f.starts_with("quicksave-"))
+                                    .max()
+                            }) {
+                                Some(newest) => self.load(format!("./saves/{}", newest)),
+                                None => "Couldn't find quicksaves.".wlog(),
+                            }
+                        }
                         InterpreterEvent::Save => {
                             if let Some(path) = get_fd("saves")
                                 .set_file_name("quicksave-untitled.ch8-save")
@@ -242,6 +745,7 @@ impl Emulator<Chip8Interpreter> {
                                     .elog("resuming emulation");
                             }
                         }
+                        InterpreterEvent::LoadFile(path) => self.load(path),
                         InterpreterEvent::Load => {
                             if let Some(path) = get_fd("saves")
                                 .add_filter("Chip8 Save", &["ch8-save"])
@@ -252,12 +756,11 @@ impl Emulator<Chip8Interpreter> {
                                 self.load(path);
                             }
                         }
-                        _ => {}
                     },
                     _ => {}
                 }
             }
-            if pause {
+            if pause || keymap_dialog.is_some() {
                 continue;
             }
 
@@ -265,20 +768,116 @@ impl Emulator<Chip8Interpreter> {
             while wait_time > self.last_opcode.elapsed().unwrap().as_micros() as u64 {}
             self.last_opcode = SystemTime::now();
 
-            self.interpreter.interpret_next(&pressed_keys);
+            self.step(&pressed_keys);
 
-            if self.opcode_counter % self.opcodes_per_frame == 0 {
-                self.interpreter.next_frame();
+            if self.opcode_counter.is_multiple_of(self.opcodes_per_frame) {
+                self.next_frame();
             }
             self.opcode_counter += 1;
         }
+        self.interpreter.shutdown();
         End::Quit
     }
 }
 
-#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Clone, PartialEq, Eq)]
 pub enum End {
     Quit,
     Restart,
     NewGame,
+    /// the rom replacing the game, e.g. one dropped onto the window
+    Open(PathBuf),
+}
+
+/// saves start with `SAVE_MAGIC`, older ones are recognized by the extension of the save dialog
+/// and the names of quicksaves (older quicksaves have the extension `.ch8`)
+pub fn is_save_file(path: &Path) -> bool {
+    if !path.is_file() {
+        return false;
+    }
+    let mut magic = [0; SAVE_MAGIC.len()];
+    if File::open(path)
+        .and_then(|mut f| f.read_exact(&mut magic))
+        .is_ok_and(|()| magic == *SAVE_MAGIC)
+    {
+        return true;
+    }
+    path.extension().is_some_and(|e| e == "ch8-save")
+        || path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .is_some_and(|n| n.starts_with("quicksave-"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+
+    use crate::emulator::{is_save_file, Emulator, End, SAVE_MAGIC};
+    use crate::events::input::InputEvent;
+    use crate::sdl2_interaction::event_manager::IncomingEvent;
+    use crate::{FPS, OPCODES_PER_FRAME};
+
+    #[test]
+    fn saves() {
+        let mut cut = Emulator::new_headless_chip8(FPS, OPCODES_PER_FRAME);
+        cut.load_memory(vec![0x61, 0x05]);
+        let save = cut.get_save();
+        assert!(save.starts_with(SAVE_MAGIC));
+        assert_eq!(cut.load_state(save.clone()), Ok(()));
+        // saves of other roms and older saves without a hash are loaded as well
+        cut.load_memory(vec![0x12, 0x00]);
+        assert_eq!(cut.load_state(save.clone()), Ok(()));
+        let state = save[SAVE_MAGIC.len() + 16..].to_vec();
+        assert_eq!(cut.load_state(state), Ok(()));
+        assert!(cut.load_state(SAVE_MAGIC.to_vec()).is_err());
+    }
+
+    #[test]
+    fn invalid_keypad_keys() {
+        let had_saves = Path::new("./saves").exists();
+        let mut cut = Emulator::new_headless_chip8(FPS, OPCODES_PER_FRAME);
+        for event in [InputEvent::KeypadDown(3, 16), InputEvent::KeypadUp(4, 0xFF)] {
+            cut.app_state_event_sender
+                .send(IncomingEvent::Input(event))
+                .unwrap();
+        }
+        cut.app_state_event_sender
+            .send(IncomingEvent::RequestTermination)
+            .unwrap();
+        assert!(cut.run() == End::Quit);
+        if !had_saves {
+            fs::remove_dir("./saves").unwrap();
+        }
+    }
+
+    #[test]
+    fn save_files() {
+        let directory =
+            std::env::temp_dir().join(format!("chip8-save-files-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let mut cut = Emulator::new_headless_chip8(FPS, OPCODES_PER_FRAME);
+        cut.load_memory(vec![0x12, 0x00]);
+        let save = directory.join("state.bin");
+        fs::write(&save, cut.get_save()).unwrap();
+        let rom = directory.join("pong.ch8");
+        fs::write(&rom, [0x12, 0x00]).unwrap();
+        assert!(is_save_file(&save));
+        assert!(!is_save_file(&rom));
+        for (name, is_save) in [
+            ("quicksave-1", true),
+            ("quicksave-2.ch8", true),
+            ("untitled.ch8-save", true),
+        ] {
+            fs::write(directory.join(name), [0x12, 0x00]).unwrap();
+            assert_eq!(is_save_file(&directory.join(name)), is_save, "{}", name);
+        }
+        let quicksave = directory.join("quicksave-3.ch8");
+        fs::write(&quicksave, cut.get_save()).unwrap();
+        assert!(is_save_file(&quicksave));
+        assert!(!is_save_file(&directory.join("missing.ch8-save")));
+        assert!(!is_save_file(&directory));
+        fs::remove_dir_all(directory).unwrap();
+    }
 }
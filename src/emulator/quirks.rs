@@ -0,0 +1,78 @@
+/// behaviour that differs between chip-8 platforms
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Quirks {
+    /// `8XY6` and `8XYE` shift `VY` into `VX` instead of shifting `VX`
+    pub shift_vy: bool,
+    /// `FX55` and `FX65` increase `I` by `X + 1`
+    pub increment_i: bool,
+    /// `8XY1`, `8XY2` and `8XY3` set `VF` to 0
+    pub reset_vf: bool,
+    /// `BNNN` jumps to `NNN + VX` instead of `NNN + V0`
+    pub jump_vx: bool,
+    /// sprites are cut off at the edges of the screen instead of wrapping around
+    pub clip_sprites: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Platform {
+    /// the behaviour of this emulator before platforms could be chosen
+    Default,
+    /// the original COSMAC VIP interpreter
+    Chip8,
+    /// SUPER-CHIP 1.1
+    SuperChip,
+    XoChip,
+}
+impl Platform {
+    pub fn get_names() -> &'static [&'static str] {
+        &["default", "chip8", "schip", "xochip"]
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Some(match name.to_lowercase().as_str() {
+            "default" => Self::Default,
+            "chip8" | "chip-8" | "vip" => Self::Chip8,
+            "schip" | "superchip" | "super-chip" => Self::SuperChip,
+            "xochip" | "xo-chip" => Self::XoChip,
+            _ => return None,
+        })
+    }
+
+    pub fn get_quirks(&self) -> Quirks {
+        let (shift_vy, increment_i, reset_vf, jump_vx, clip_sprites) = match self {
+            Self::Default => (false, false, false, false, false),
+            Self::Chip8 => (true, true, true, false, true),
+            Self::SuperChip => (false, false, false, true, true),
+            Self::XoChip => (true, true, false, false, false),
+        };
+        Quirks {
+            shift_vy,
+            increment_i,
+            reset_vf,
+            jump_vx,
+            clip_sprites,
+        }
+    }
+}
+impl Default for Quirks {
+    fn default() -> Self {
+        Platform::Default.get_quirks()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::emulator::quirks::{Platform, Quirks};
+
+    #[test]
+    fn platforms() {
+        for name in Platform::get_names() {
+            assert!(Platform::parse(name).is_some(), "{}", name);
+        }
+        assert_eq!(Platform::parse("SCHIP"), Some(Platform::SuperChip));
+        assert_eq!(Platform::parse("megachip"), None);
+        assert_eq!(Quirks::default(), Platform::Default.get_quirks());
+        assert!(Platform::Chip8.get_quirks().reset_vf);
+        assert!(Platform::SuperChip.get_quirks().jump_vx);
+    }
+}
@@ -1,25 +1,32 @@
+use std::path::PathBuf;
+
 use crate::emulator::interpreter::InterpreterEvent;
+use crate::emulator::is_save_file;
 use crate::events::EventManager;
-use crate::sdl2_interaction::event_manager::{Event, IncomingEvent};
+use crate::sdl2_interaction::event_manager::{EventKind, IncomingEvent};
+use crate::sdl2_interaction::output::ScreenEvent;
+use crate::{has_rom_extension, LogWarning};
 
-#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+#[derive(PartialEq, Eq, Clone, Debug)]
 pub enum AppEvent {
     WindowSizeChange(i32, i32),
     SetFocus(bool),
-    Any,
-}
-impl Event for AppEvent {
-    fn is_any(&self) -> bool {
-        matches!(&self, &AppEvent::Any)
-    }
+    /// a file dropped onto the window, a rom or a save
+    DropFile(PathBuf),
 }
 pub struct AppEventManager {}
 
-impl EventManager for AppEventManager {
-    fn new() -> Self {
+impl AppEventManager {
+    pub fn new() -> Self {
         Self {}
     }
-
+}
+impl Default for AppEventManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl EventManager for AppEventManager {
     fn update(&mut self, event: &IncomingEvent) -> Option<IncomingEvent> {
         Some(match event {
             IncomingEvent::App(AppEvent::WindowSizeChange(_, _)) => {
@@ -27,14 +34,62 @@ impl EventManager for AppEventManager {
             }
             IncomingEvent::App(AppEvent::SetFocus(false)) => IncomingEvent::Pause(true),
             IncomingEvent::Pause(false) => IncomingEvent::Interpreter(InterpreterEvent::RedrawAll),
+            IncomingEvent::App(AppEvent::DropFile(path)) if is_save_file(path) => {
+                IncomingEvent::Interpreter(InterpreterEvent::LoadFile(path.clone()))
+            }
+            IncomingEvent::App(AppEvent::DropFile(path))
+                if path.is_file() && has_rom_extension(path) =>
+            {
+                IncomingEvent::OpenRom(path.clone())
+            }
+            IncomingEvent::App(AppEvent::DropFile(path)) => {
+                format!("{:?} is neither a rom nor a save.", path)
+                    .as_str()
+                    .wlog();
+                IncomingEvent::Screen(ScreenEvent::Toast("NOT A ROM OR SAVE".to_owned()))
+            }
             _ => return None,
         })
     }
 
-    fn get_callbacks(&self) -> &[IncomingEvent] {
-        &[
-            IncomingEvent::App(AppEvent::Any),
-            IncomingEvent::Pause(false),
-        ]
+    fn get_subscriptions(&self) -> &[EventKind] {
+        &[EventKind::App, EventKind::Pause]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::emulator::interpreter::InterpreterEvent;
+    use crate::events::app::{AppEvent, AppEventManager};
+    use crate::events::EventManager;
+    use crate::sdl2_interaction::event_manager::IncomingEvent;
+    use crate::sdl2_interaction::output::ScreenEvent;
+
+    #[test]
+    fn drops() {
+        let directory = std::env::temp_dir().join(format!("chip8-drops-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let rom = directory.join("pong.CH8");
+        fs::write(&rom, [0x12, 0x00]).unwrap();
+        let save = directory.join("untitled.ch8-save");
+        fs::write(&save, [0x00]).unwrap();
+        let notes = directory.join("notes.txt");
+        fs::write(&notes, "").unwrap();
+        let mut cut = AppEventManager::new();
+        let mut drop = |path| cut.update(&IncomingEvent::App(AppEvent::DropFile(path)));
+        assert!(matches!(drop(rom.clone()), Some(IncomingEvent::OpenRom(p)) if p == rom));
+        assert!(matches!(
+            drop(save.clone()),
+            Some(IncomingEvent::Interpreter(InterpreterEvent::LoadFile(p))) if p == save
+        ));
+        for path in [notes, directory.join("games.ch8"), directory.clone()] {
+            assert!(matches!(
+                drop(path),
+                Some(IncomingEvent::Screen(ScreenEvent::Toast(_)))
+            ));
+        }
+        fs::remove_dir_all(directory).unwrap();
     }
 }
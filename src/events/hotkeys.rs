@@ -0,0 +1,340 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+
+use sdl2::keyboard::Scancode;
+use serde::{Deserialize, Serialize};
+
+/// everything a hotkey can do, the names in the config file are snake case (e.g. `quick_save`)
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    Pause,
+    ResetSpeed,
+    SlowDown,
+    SpeedUp,
+    CheatMode,
+    QuickSave,
+    Restart,
+    NewGame,
+    QuickLoad,
+    Save,
+    Load,
+    Fullscreen,
+    EditKeymap,
+    ShowInputs,
+    ShowStats,
+    CrtFilter,
+    Upscaler,
+    Help,
+    Quit,
+}
+impl Action {
+    pub fn get_all() -> &'static [Action] {
+        &[
+            Action::Pause,
+            Action::ResetSpeed,
+            Action::SlowDown,
+            Action::SpeedUp,
+            Action::CheatMode,
+            Action::QuickSave,
+            Action::Restart,
+            Action::NewGame,
+            Action::QuickLoad,
+            Action::Save,
+            Action::Load,
+            Action::Fullscreen,
+            Action::EditKeymap,
+            Action::ShowInputs,
+            Action::ShowStats,
+            Action::CrtFilter,
+            Action::Upscaler,
+            Action::Help,
+            Action::Quit,
+        ]
+    }
+
+    pub fn get_description(&self) -> &'static str {
+        match self {
+            Action::Pause => "Pause/unpause",
+            Action::ResetSpeed => "Reset speed",
+            Action::SlowDown => "Slow down",
+            Action::SpeedUp => "Speed up",
+            Action::CheatMode => "Cheat mode",
+            Action::QuickSave => "Quicksave",
+            Action::Restart => "Restart game",
+            Action::NewGame => "Load game",
+            Action::QuickLoad => "Quickload",
+            Action::Save => "Save state",
+            Action::Load => "Load state",
+            Action::Fullscreen => "Fullscreen",
+            Action::EditKeymap => "Change keymap",
+            Action::ShowInputs => "Show inputs",
+            Action::ShowStats => "Show stats",
+            Action::CrtFilter => "CRT filter",
+            Action::Upscaler => "Upscaler",
+            Action::Help => "Show hotkeys",
+            Action::Quit => "Quit",
+        }
+    }
+
+    fn get_default_bindings(&self) -> &'static str {
+        match self {
+            Action::Pause => "Escape",
+            Action::ResetSpeed => "F1",
+            Action::SlowDown => "F2",
+            Action::SpeedUp => "F3",
+            Action::CheatMode => "F4",
+            Action::QuickSave => "F5",
+            Action::Restart => "F6",
+            Action::NewGame => "F7",
+            Action::QuickLoad => "F8",
+            Action::Save => "F9",
+            Action::Load => "F10",
+            Action::Fullscreen => "F11",
+            Action::EditKeymap => "F12",
+            Action::ShowInputs => "Ctrl+I",
+            Action::ShowStats => "Ctrl+T",
+            Action::CrtFilter => "Ctrl+F",
+            Action::Upscaler => "Ctrl+U",
+            Action::Help => "Ctrl+H",
+            Action::Quit => "Ctrl+Q",
+        }
+    }
+}
+
+/// the held modifier keys, left and right keys aren't distinguished
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Modifiers {
+    pub ctrl: bool,
+    pub shift: bool,
+    pub alt: bool,
+}
+impl Modifiers {
+    pub fn is_modifier(scancode: &Scancode) -> bool {
+        matches!(
+            scancode,
+            Scancode::LCtrl
+                | Scancode::RCtrl
+                | Scancode::LShift
+                | Scancode::RShift
+                | Scancode::LAlt
+                | Scancode::RAlt
+        )
+    }
+
+    pub fn from_pressed(pressed_keys: &HashSet<Scancode>) -> Self {
+        Self {
+            ctrl: pressed_keys.contains(&Scancode::LCtrl)
+                || pressed_keys.contains(&Scancode::RCtrl),
+            shift: pressed_keys.contains(&Scancode::LShift)
+                || pressed_keys.contains(&Scancode::RShift),
+            alt: pressed_keys.contains(&Scancode::LAlt) || pressed_keys.contains(&Scancode::RAlt),
+        }
+    }
+}
+
+/// a key with the modifiers that have to be held, e.g. `Ctrl+S`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Hotkey {
+    pub key: Scancode,
+    pub modifiers: Modifiers,
+}
+impl Hotkey {
+    /// parses `[Ctrl+][Shift+][Alt+]KEY`, `KEY` being an SDL key name
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let mut modifiers = Modifiers::default();
+        let mut rest = s.trim();
+        loop {
+            let (modifier, key) = match rest.split_once('+') {
+                Some((modifier, key)) if !key.is_empty() => (modifier.trim(), key.trim()),
+                _ => break,
+            };
+            match modifier.to_lowercase().as_str() {
+                "ctrl" => modifiers.ctrl = true,
+                "shift" => modifiers.shift = true,
+                "alt" => modifiers.alt = true,
+                _ => break,
+            }
+            rest = key;
+        }
+        let key = Scancode::from_name(rest).ok_or(format!("unknown key `{}`", rest))?;
+        if Modifiers::is_modifier(&key) {
+            return Err(format!("`{}` is a modifier", rest));
+        }
+        Ok(Self { key, modifiers })
+    }
+}
+impl fmt::Display for Hotkey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.modifiers.ctrl {
+            write!(f, "Ctrl+")?;
+        }
+        if self.modifiers.shift {
+            write!(f, "Shift+")?;
+        }
+        if self.modifiers.alt {
+            write!(f, "Alt+")?;
+        }
+        write!(f, "{}", self.key.name())
+    }
+}
+
+/// the hotkeys of every action, an action can have several (or no) hotkeys
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(
+    try_from = "BTreeMap<Action, Vec<String>>",
+    into = "BTreeMap<Action, Vec<String>>"
+)]
+pub struct Hotkeys {
+    bindings: BTreeMap<Action, Vec<Hotkey>>,
+}
+impl Default for Hotkeys {
+    fn default() -> Self {
+        Self {
+            bindings: Action::get_all()
+                .iter()
+                .map(|a| {
+                    (
+                        *a,
+                        vec![Hotkey::parse(a.get_default_bindings())
+                            .expect("invalid default hotkey")],
+                    )
+                })
+                .collect(),
+        }
+    }
+}
+impl Hotkeys {
+    /// the action of a key if exactly its modifiers are held
+    pub fn get_action(&self, key: Scancode, modifiers: Modifiers) -> Option<Action> {
+        let hotkey = Hotkey { key, modifiers };
+        self.bindings
+            .iter()
+            .find(|(_, hotkeys)| hotkeys.contains(&hotkey))
+            .map(|(action, _)| *action)
+    }
+
+    pub fn get_hotkeys(&self, action: Action) -> &[Hotkey] {
+        self.bindings.get(&action).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn set_hotkeys(&mut self, action: Action, hotkeys: Vec<Hotkey>) {
+        self.bindings.insert(action, hotkeys);
+    }
+
+    /// one line per action with its description and hotkeys
+    pub fn get_help(&self) -> String {
+        Action::get_all()
+            .iter()
+            .map(|a| {
+                format!(
+                    "{:<14}{}\n",
+                    a.get_description(),
+                    self.get_hotkeys(*a)
+                        .iter()
+                        .map(Hotkey::to_string)
+                        .collect::<Vec<String>>()
+                        .join(", ")
+                )
+            })
+            .collect()
+    }
+}
+impl TryFrom<BTreeMap<Action, Vec<String>>> for Hotkeys {
+    type Error = String;
+
+    /// missing actions keep their default hotkeys
+    fn try_from(map: BTreeMap<Action, Vec<String>>) -> Result<Self, Self::Error> {
+        let mut hotkeys = Self::default();
+        for (action, keys) in map {
+            hotkeys.set_hotkeys(
+                action,
+                keys.iter()
+                    .map(|k| Hotkey::parse(k))
+                    .collect::<Result<_, _>>()?,
+            );
+        }
+        Ok(hotkeys)
+    }
+}
+impl From<Hotkeys> for BTreeMap<Action, Vec<String>> {
+    /// only the actions that differ from the default hotkeys
+    fn from(hotkeys: Hotkeys) -> Self {
+        let default = Hotkeys::default();
+        Action::get_all()
+            .iter()
+            .filter(|a| hotkeys.get_hotkeys(**a) != default.get_hotkeys(**a))
+            .map(|a| {
+                (
+                    *a,
+                    hotkeys
+                        .get_hotkeys(*a)
+                        .iter()
+                        .map(Hotkey::to_string)
+                        .collect(),
+                )
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeMap, HashSet};
+
+    use sdl2::keyboard::Scancode;
+
+    use crate::events::hotkeys::{Action, Hotkey, Hotkeys, Modifiers};
+
+    #[test]
+    fn parse_hotkey() {
+        let cut = Hotkey::parse("ctrl+Shift+S").unwrap();
+        assert_eq!(cut.key, Scancode::S);
+        assert_eq!(
+            cut.modifiers,
+            Modifiers {
+                ctrl: true,
+                shift: true,
+                alt: false
+            }
+        );
+        assert_eq!(cut.to_string(), "Ctrl+Shift+S");
+        assert_eq!(Hotkey::parse("F5").unwrap().modifiers, Modifiers::default());
+        assert!(Hotkey::parse("Ctrl+").is_err());
+        assert!(Hotkey::parse("Ctrl+Left Shift").is_err());
+        assert!(Hotkey::parse("Meta+S").is_err());
+    }
+
+    #[test]
+    fn actions() {
+        let mut cut = Hotkeys::default();
+        assert_eq!(
+            cut.get_action(Scancode::F6, Modifiers::default()),
+            Some(Action::Restart)
+        );
+        let ctrl = Modifiers::from_pressed(&HashSet::from([Scancode::RCtrl]));
+        assert_eq!(cut.get_action(Scancode::F6, ctrl), None);
+        assert_eq!(cut.get_action(Scancode::Q, ctrl), Some(Action::Quit));
+        cut.set_hotkeys(
+            Action::QuickSave,
+            vec![
+                Hotkey::parse("F5").unwrap(),
+                Hotkey::parse("Ctrl+S").unwrap(),
+            ],
+        );
+        assert_eq!(cut.get_action(Scancode::S, ctrl), Some(Action::QuickSave));
+        assert!(cut.get_help().contains("Quicksave     F5, Ctrl+S\n"));
+    }
+
+    #[test]
+    fn map_round_trip() {
+        let mut cut = Hotkeys::default();
+        cut.set_hotkeys(Action::Pause, vec![Hotkey::parse("Alt+P").unwrap()]);
+        let map = BTreeMap::from(cut.clone());
+        assert_eq!(
+            map,
+            BTreeMap::from([(Action::Pause, vec!["Alt+P".to_owned()])])
+        );
+        assert_eq!(Hotkeys::try_from(map), Ok(cut));
+    }
+}
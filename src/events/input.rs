@@ -1,14 +1,18 @@
+use std::collections::HashSet;
+
 use sdl2::keyboard::Scancode;
 use sdl2::mouse::MouseButton;
 
+use crate::config::settings;
 use crate::emulator::interpreter::InterpreterEvent;
+use crate::events::hotkeys::{Action, Hotkeys, Modifiers};
 use crate::events::EventManager;
-use crate::sdl2_interaction::event_manager::{Event, IncomingEvent};
+use crate::sdl2_interaction::controller::ControllerInput;
+use crate::sdl2_interaction::event_manager::{EventKind, IncomingEvent};
 use crate::sdl2_interaction::output::ScreenEvent;
-use crate::sdl2_interaction::screen::Chip8BoolToColor;
 use crate::LogWarning;
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum InputEvent {
     KeyPress(Scancode),
     KeyDown(Scancode),
@@ -19,95 +23,156 @@ pub enum InputEvent {
     MouseButtonUp(MouseButton),
     ClearMouseButtons,
     UpdateMouseCoordinates(i32, i32),
-    Any,
-}
-impl Event for InputEvent {
-    fn is_any(&self) -> bool {
-        matches!(&self, &InputEvent::Any)
-    }
+    /// an input of the controller of a player (starting at 0)
+    ControllerDown(usize, ControllerInput),
+    ControllerUp(usize, ControllerInput),
+    /// a hex key of a serial keypad (starting at 0)
+    KeypadDown(usize, u8),
+    KeypadUp(usize, u8),
 }
 pub struct InputEventManager {
     pause_state: bool,
     speed: i8,
     is_in_cheat_mode: bool,
     mouse_coordinates: (usize, usize),
+    /// hotkeys are ignored while the keymap dialog is open
+    keymap_dialog: Option<u8>,
+    hotkeys: Hotkeys,
+    pressed_modifiers: HashSet<Scancode>,
 }
-impl EventManager for InputEventManager {
-    fn new() -> Self {
+impl InputEventManager {
+    /// uses the hotkeys of the global settings
+    pub fn new() -> Self {
         Self {
             pause_state: false,
             speed: 0,
             is_in_cheat_mode: false,
             mouse_coordinates: (0, 0),
+            keymap_dialog: None,
+            hotkeys: settings().hotkeys.clone(),
+            pressed_modifiers: HashSet::new(),
         }
     }
 
+    fn perform(&self, action: Action) -> Option<IncomingEvent> {
+        Some(match action {
+            Action::Pause => IncomingEvent::Pause(!self.pause_state),
+            Action::ResetSpeed => IncomingEvent::SetSpeed(0),
+            Action::SlowDown => {
+                if self.speed > i8::MIN {
+                    IncomingEvent::SetSpeed(self.speed - 1)
+                } else {
+                    "Reached min speed!".wlog();
+                    return None;
+                }
+            }
+            Action::SpeedUp => {
+                if self.speed < i8::MAX {
+                    IncomingEvent::SetSpeed(self.speed + 1)
+                } else {
+                    "Reached max speed!".wlog();
+                    return None;
+                }
+            }
+            Action::CheatMode => IncomingEvent::SetCheatMode(!self.is_in_cheat_mode),
+            Action::QuickSave => IncomingEvent::Interpreter(InterpreterEvent::QuickSave),
+            Action::Restart => IncomingEvent::Restart,
+            Action::NewGame => IncomingEvent::NewGame,
+            Action::QuickLoad => IncomingEvent::Interpreter(InterpreterEvent::QuickLoad),
+            Action::Save => IncomingEvent::Interpreter(InterpreterEvent::Save),
+            Action::Load => IncomingEvent::Interpreter(InterpreterEvent::Load),
+            Action::Fullscreen => IncomingEvent::Screen(ScreenEvent::ToggleFullscreen),
+            Action::EditKeymap => IncomingEvent::KeymapDialog(Some(0)),
+            Action::ShowInputs => IncomingEvent::Screen(ScreenEvent::ToggleInputs),
+            Action::ShowStats => IncomingEvent::Screen(ScreenEvent::ToggleStats),
+            Action::CrtFilter => IncomingEvent::Screen(ScreenEvent::NextCrtFilter),
+            Action::Upscaler => IncomingEvent::Screen(ScreenEvent::NextUpscaler),
+            Action::Help => IncomingEvent::Screen(ScreenEvent::ToggleHelp),
+            Action::Quit => IncomingEvent::RequestTermination,
+        })
+    }
+}
+impl Default for InputEventManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl EventManager for InputEventManager {
     fn update(&mut self, event: &IncomingEvent) -> Option<IncomingEvent> {
         match event {
             IncomingEvent::SetSpeed(s) => self.speed = *s,
             IncomingEvent::Pause(p) => self.pause_state = *p,
             IncomingEvent::SetCheatMode(c) => self.is_in_cheat_mode = *c,
+            IncomingEvent::KeymapDialog(d) => self.keymap_dialog = *d,
             IncomingEvent::Input(InputEvent::UpdateMouseCoordinates(x, y)) => {
                 self.mouse_coordinates = (*x as usize, *y as usize)
             }
-            _ => {
-                return Some(match event {
-                    IncomingEvent::Input(InputEvent::MouseButtonPress(m))
-                        if self.is_in_cheat_mode =>
-                    {
-                        IncomingEvent::Interpreter(InterpreterEvent::SetPixel(
-                            self.mouse_coordinates.0,
-                            self.mouse_coordinates.1,
-                            match m {
-                                MouseButton::Left => true,
-                                MouseButton::Right => false,
-                                _ => return None,
-                            }
-                            .into_color(),
-                        ))
-                    }
-                    IncomingEvent::Input(InputEvent::KeyDown(k)) => match k {
-                        Scancode::F1 => IncomingEvent::SetSpeed(0),
-                        Scancode::F2 => {
-                            if self.speed > i8::MIN {
-                                IncomingEvent::SetSpeed(self.speed - 1)
-                            } else {
-                                "Reached min speed!".wlog();
-                                return None;
-                            }
-                        }
-                        Scancode::F3 => {
-                            if self.speed < i8::MAX {
-                                IncomingEvent::SetSpeed(self.speed + 1)
-                            } else {
-                                "Reached max speed!".wlog();
-                                return None;
-                            }
-                        }
-                        Scancode::F4 => IncomingEvent::SetCheatMode(!self.is_in_cheat_mode),
-                        Scancode::F5 => IncomingEvent::Interpreter(InterpreterEvent::QuickSave),
-                        Scancode::F6 => IncomingEvent::Restart,
-                        Scancode::F7 => IncomingEvent::NewGame,
-                        Scancode::F8 => IncomingEvent::Interpreter(InterpreterEvent::QuickLoad),
-                        Scancode::F9 => IncomingEvent::Interpreter(InterpreterEvent::Save),
-                        Scancode::F10 => IncomingEvent::Interpreter(InterpreterEvent::Load),
-                        Scancode::F11 => IncomingEvent::Screen(ScreenEvent::ToggleFullscreen),
-                        Scancode::Escape => IncomingEvent::Pause(!self.pause_state),
+            IncomingEvent::Input(InputEvent::KeyDown(k)) if Modifiers::is_modifier(k) => {
+                self.pressed_modifiers.insert(*k);
+            }
+            IncomingEvent::Input(InputEvent::KeyUp(k)) => {
+                self.pressed_modifiers.remove(k);
+            }
+            IncomingEvent::Input(InputEvent::ClearKeys) => self.pressed_modifiers.clear(),
+            IncomingEvent::Input(InputEvent::KeyDown(k)) if self.keymap_dialog.is_none() => {
+                return self.perform(
+                    self.hotkeys
+                        .get_action(*k, Modifiers::from_pressed(&self.pressed_modifiers))?,
+                );
+            }
+            IncomingEvent::Input(InputEvent::MouseButtonPress(m)) if self.is_in_cheat_mode => {
+                return Some(IncomingEvent::Interpreter(InterpreterEvent::SetPixel(
+                    self.mouse_coordinates.0,
+                    self.mouse_coordinates.1,
+                    match m {
+                        MouseButton::Left => true,
+                        MouseButton::Right => false,
                         _ => return None,
                     },
-                    _ => return None,
-                });
+                )));
             }
+            _ => {}
         }
         None
     }
 
-    fn get_callbacks(&self) -> &[IncomingEvent] {
+    fn get_subscriptions(&self) -> &[EventKind] {
         &[
-            IncomingEvent::Pause(false),
-            IncomingEvent::Input(InputEvent::Any),
-            IncomingEvent::SetSpeed(0),
-            IncomingEvent::SetCheatMode(true),
+            EventKind::Pause,
+            EventKind::Input,
+            EventKind::SetSpeed,
+            EventKind::SetCheatMode,
+            EventKind::KeymapDialog,
         ]
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use sdl2::keyboard::Scancode;
+
+    use crate::events::input::{InputEvent, InputEventManager};
+    use crate::events::EventManager;
+    use crate::sdl2_interaction::event_manager::{EventKind, IncomingEvent};
+
+    fn press(cut: &mut InputEventManager, key: Scancode) -> Option<EventKind> {
+        cut.update(&IncomingEvent::Input(InputEvent::KeyDown(key)))
+            .map(|e| e.kind())
+    }
+
+    #[test]
+    fn hotkeys() {
+        let mut cut = InputEventManager::new();
+        assert_eq!(press(&mut cut, Scancode::F6), Some(EventKind::Restart));
+        assert_eq!(press(&mut cut, Scancode::Q), None);
+        assert_eq!(press(&mut cut, Scancode::LCtrl), None);
+        assert_eq!(
+            press(&mut cut, Scancode::Q),
+            Some(EventKind::RequestTermination)
+        );
+        assert_eq!(press(&mut cut, Scancode::F6), None);
+        cut.update(&IncomingEvent::Input(InputEvent::KeyUp(Scancode::LCtrl)));
+        cut.update(&IncomingEvent::KeymapDialog(Some(0)));
+        assert_eq!(press(&mut cut, Scancode::F6), None);
+    }
+}
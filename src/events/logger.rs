@@ -1,13 +1,16 @@
+use crate::config::settings;
 use crate::events::EventManager;
-use crate::sdl2_interaction::event_manager::IncomingEvent;
-use crate::{LogInfo, LogWarning, SPEED_CHANGE_PER_KEYPRESS};
+use crate::sdl2_interaction::event_manager::{EventKind, IncomingEvent};
+use crate::sdl2_interaction::keypad::KeypadEvent;
+use crate::{LogInfo, LogWarning};
 
 pub struct Logger {}
-impl EventManager for Logger {
-    fn new() -> Self {
+impl Logger {
+    pub fn new() -> Self {
         Self {}
     }
-
+}
+impl EventManager for Logger {
     fn update(&mut self, event: &IncomingEvent) -> Option<IncomingEvent> {
         match event {
             IncomingEvent::Pause(p) => {
@@ -24,9 +27,26 @@ impl EventManager for Logger {
                     "Cheat mode turned off.".wlog();
                 }
             }
+            IncomingEvent::KeymapDialog(Some(hex)) => format!(
+                "Press the key for 0x{:X} (Tab keeps its keys, Esc closes the dialog).",
+                hex
+            )
+            .as_str()
+            .log(),
+            IncomingEvent::KeymapDialog(None) => "Closed the keymap dialog.".log(),
+            IncomingEvent::Keypad(KeypadEvent::Connected(slot, port, device)) => {
+                format!("Keypad {} `{}` connected ({}).", slot + 1, port, device)
+                    .as_str()
+                    .log()
+            }
+            IncomingEvent::Keypad(KeypadEvent::Disconnected(slot, port)) => {
+                format!("Keypad {} `{}` disconnected.", slot + 1, port)
+                    .as_str()
+                    .wlog()
+            }
             IncomingEvent::SetSpeed(s) => format!(
                 "Changed speed to {}%.",
-                (SPEED_CHANGE_PER_KEYPRESS.powi(*s as i32) * 100.0) as i32
+                (settings().get_speed_factor(*s) * 100.0) as i32
             )
             .as_str()
             .log(),
@@ -35,7 +55,13 @@ impl EventManager for Logger {
         None
     }
 
-    fn get_callbacks(&self) -> &[IncomingEvent] {
-        &[IncomingEvent::Any]
+    fn get_subscriptions(&self) -> &[EventKind] {
+        &[
+            EventKind::Pause,
+            EventKind::SetCheatMode,
+            EventKind::SetSpeed,
+            EventKind::KeymapDialog,
+            EventKind::Keypad,
+        ]
     }
 }
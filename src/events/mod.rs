@@ -1,64 +1,168 @@
 use std::collections::VecDeque;
-use std::sync::mpsc;
+use std::sync::{mpsc, Arc, Mutex};
 use std::thread;
 
 use crate::events::app::AppEventManager;
 use crate::events::input::InputEventManager;
 use crate::events::logger::Logger;
-use crate::sdl2_interaction::event_manager::{AppEventSender, Event, IncomingEvent};
-use crate::LogError;
+use crate::sdl2_interaction::event_manager::{
+    AppEventReceiver, AppEventSender, EventKind, IncomingEvent,
+};
 
 pub mod app;
+pub mod hotkeys;
 pub mod input;
 mod logger;
+pub mod plugin;
 
+/// reacts to events of the bus, see `plugin::Plugin` for hooking into the emulator from outside
 pub trait EventManager {
-    fn new() -> Self
-    where
-        Self: Sized;
     fn update(&mut self, app_event: &IncomingEvent) -> Option<IncomingEvent>;
-    fn get_callbacks(&self) -> &[IncomingEvent];
+    /// the kinds of events passed to `update`
+    fn get_subscriptions(&self) -> &[EventKind];
 }
 
-pub struct EventRedirectManager {
+struct Subscriber {
+    sender: AppEventSender,
+    kinds: Vec<EventKind>,
+}
+
+/// everything the dispatching thread reads, shared so that consumers and managers can be added
+/// while the bus is running
+#[derive(Default)]
+struct Registry {
+    subscribers: Vec<Subscriber>,
+    managers: Vec<Box<dyn EventManager + Send>>,
+}
+impl Registry {
+    /// delivers the event to every subscriber (in subscription order), then to every manager (in
+    /// registration order), events returned by managers are dispatched the same way before the
+    /// next sent event
+    fn dispatch(&mut self, event: IncomingEvent) {
+        let mut pending_events = VecDeque::from([event]);
+        while let Some(event) = pending_events.pop_front() {
+            let kind = event.kind();
+            self.subscribers
+                .retain(|s| !s.kinds.contains(&kind) || s.sender.send(event.clone()).is_ok());
+            for manager in self.managers.iter_mut() {
+                if manager.get_subscriptions().contains(&kind) {
+                    if let Some(returned_event) = manager.update(&event) {
+                        pending_events.push_back(returned_event);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// a publish/subscribe bus, events are dispatched one after another by a single thread that
+/// blocks until an event is sent; an event and all events caused by it reach every subscriber
+/// before any event sent afterwards (e.g. `Pause(true)` always reaches the interpreter and the
+/// window before the next frame)
+pub struct EventBus {
     event_sender: AppEventSender,
+    registry: Arc<Mutex<Registry>>,
 }
-impl EventRedirectManager {
-    pub fn new(callbacks: Vec<(AppEventSender, Vec<IncomingEvent>)>) -> Self {
+impl EventBus {
+    /// a bus with the built-in managers (input, app state and logger)
+    pub fn new() -> Self {
+        let bus = Self::empty();
+        bus.register(Box::new(InputEventManager::new()));
+        bus.register(Box::new(AppEventManager::new()));
+        bus.register(Box::new(Logger::new()));
+        bus
+    }
+
+    /// a bus without any manager, the thread stops once every sender is dropped
+    pub fn empty() -> Self {
         let (event_sender, event_receiver) = mpsc::channel();
+        let registry = Arc::new(Mutex::new(Registry::default()));
 
+        let dispatch_registry = Arc::clone(&registry);
         thread::spawn(move || {
-            let mut pending_events = VecDeque::new();
-            let mut event_managers: [Box<dyn EventManager>; 3] = [
-                Box::new(InputEventManager::new()),
-                Box::new(AppEventManager::new()),
-                Box::new(Logger::new()),
-            ];
-            loop {
-                while let Ok(event) = event_receiver.try_recv() {
-                    pending_events.push_back(event);
-                }
-                while let Some(event) = pending_events.pop_front() {
-                    for sender in callbacks
-                        .iter()
-                        .filter_map(|(s, c)| c.iter().any(|a_e| a_e.equals(&event)).then(|| s))
-                    {
-                        sender.send(event.clone()).elog("sending event");
-                    }
-                    for manager in event_managers.iter_mut() {
-                        if manager.get_callbacks().iter().any(|e| e.equals(&event)) {
-                            if let Some(returned_event) = manager.update(&event) {
-                                pending_events.push_back(returned_event);
-                            }
-                        }
-                    }
-                }
+            while let Ok(event) = event_receiver.recv() {
+                dispatch_registry.lock().unwrap().dispatch(event);
             }
         });
-        Self { event_sender }
+        Self {
+            event_sender,
+            registry,
+        }
+    }
+
+    /// returns a receiver of all events of the given kinds sent from now on
+    pub fn subscribe(&self, kinds: &[EventKind]) -> AppEventReceiver {
+        let (sender, receiver) = mpsc::channel();
+        self.registry.lock().unwrap().subscribers.push(Subscriber {
+            sender,
+            kinds: kinds.to_vec(),
+        });
+        receiver
+    }
+
+    /// adds a manager after all previously registered ones, it receives all events of its
+    /// subscriptions sent from now on
+    pub fn register(&self, manager: Box<dyn EventManager + Send>) {
+        self.registry.lock().unwrap().managers.push(manager);
     }
 
     pub fn get_event_sender(&self) -> AppEventSender {
         self.event_sender.clone()
     }
 }
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::events::{EventBus, EventManager};
+    use crate::sdl2_interaction::event_manager::{EventKind, IncomingEvent};
+
+    struct Pauser {}
+    impl EventManager for Pauser {
+        fn update(&mut self, _: &IncomingEvent) -> Option<IncomingEvent> {
+            Some(IncomingEvent::Pause(true))
+        }
+
+        fn get_subscriptions(&self) -> &[EventKind] {
+            &[EventKind::NewGame]
+        }
+    }
+
+    fn kinds(receiver: &std::sync::mpsc::Receiver<IncomingEvent>) -> Vec<EventKind> {
+        std::iter::from_fn(|| receiver.recv_timeout(Duration::from_millis(100)).ok())
+            .map(|e| e.kind())
+            .collect()
+    }
+
+    #[test]
+    fn subscriptions() {
+        let cut = EventBus::empty();
+        let pauses = cut.subscribe(&[EventKind::Pause]);
+        let both = cut.subscribe(&[EventKind::Pause, EventKind::Restart]);
+        let sender = cut.get_event_sender();
+        sender.send(IncomingEvent::Restart).unwrap();
+        sender.send(IncomingEvent::Pause(false)).unwrap();
+        assert_eq!(kinds(&pauses), [EventKind::Pause]);
+        assert_eq!(kinds(&both), [EventKind::Restart, EventKind::Pause]);
+    }
+
+    #[test]
+    fn caused_events_come_first() {
+        let cut = EventBus::empty();
+        let receiver = cut.subscribe(&[EventKind::Pause, EventKind::NewGame, EventKind::Restart]);
+        cut.register(Box::new(Pauser {}));
+        let sender = cut.get_event_sender();
+        sender.send(IncomingEvent::NewGame).unwrap();
+        sender.send(IncomingEvent::Restart).unwrap();
+        assert_eq!(
+            kinds(&receiver),
+            [EventKind::NewGame, EventKind::Pause, EventKind::Restart]
+        );
+    }
+}
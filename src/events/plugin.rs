@@ -0,0 +1,144 @@
+use std::sync::{Arc, Mutex};
+
+use crate::emulator::interpreter::InterpreterState;
+use crate::events::EventManager;
+use crate::sdl2_interaction::event_manager::{AppEventSender, EventKind, IncomingEvent};
+use crate::sdl2_interaction::overlay::Overlay;
+use crate::LogError;
+
+/// a plugin shared between the event, emulation and window thread
+pub type SharedPlugin = Arc<Mutex<Box<dyn Plugin>>>;
+
+/// a hook into the emulator, registered with `EmulatorBuilder::plugin`; every method does nothing
+/// by default
+pub trait Plugin: Send {
+    /// the kinds of events passed to `on_event`, read once when the plugin is registered
+    fn get_subscriptions(&self) -> &[EventKind] {
+        &[]
+    }
+
+    /// called by the event thread for every event of the subscriptions
+    fn on_event(&mut self, _event: &IncomingEvent, _context: &PluginContext) {}
+
+    /// called by the emulation thread after every emulated frame
+    fn on_frame(&mut self, _state: &InterpreterState, _context: &PluginContext) {}
+
+    /// called by the window thread after the screen is drawn
+    fn draw_overlay(&mut self, _overlay: &mut Overlay) {}
+}
+
+/// lets plugins inject events
+#[derive(Clone)]
+pub struct PluginContext {
+    event_sender: AppEventSender,
+}
+impl PluginContext {
+    pub fn new(event_sender: AppEventSender) -> Self {
+        Self { event_sender }
+    }
+
+    /// the event is queued to the bus and dispatched asynchronously, it may be handled before
+    /// or after other events that are in flight
+    pub fn send(&self, event: IncomingEvent) {
+        self.event_sender
+            .send(event)
+            .elog("sending event from plugin");
+    }
+}
+
+/// passes the events of the bus to a plugin
+pub struct PluginEventManager {
+    plugin: SharedPlugin,
+    context: PluginContext,
+    subscriptions: Vec<EventKind>,
+}
+impl PluginEventManager {
+    pub fn new(plugin: SharedPlugin, context: PluginContext) -> Self {
+        let subscriptions = plugin.lock().unwrap().get_subscriptions().to_vec();
+        Self {
+            plugin,
+            context,
+            subscriptions,
+        }
+    }
+}
+impl EventManager for PluginEventManager {
+    fn update(&mut self, event: &IncomingEvent) -> Option<IncomingEvent> {
+        self.plugin.lock().unwrap().on_event(event, &self.context);
+        None
+    }
+
+    fn get_subscriptions(&self) -> &[EventKind] {
+        &self.subscriptions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use crate::emulator::interpreter::InterpreterState;
+    use crate::emulator::Emulator;
+    use crate::events::plugin::{Plugin, PluginContext, PluginEventManager, SharedPlugin};
+    use crate::events::EventBus;
+    use crate::sdl2_interaction::event_manager::{EventKind, IncomingEvent};
+
+    #[derive(Default)]
+    struct Counter {
+        restarts: u32,
+        frames: u32,
+        last_pc: u16,
+    }
+    impl Plugin for Arc<Mutex<Counter>> {
+        fn get_subscriptions(&self) -> &[EventKind] {
+            &[EventKind::Restart]
+        }
+
+        fn on_event(&mut self, _: &IncomingEvent, context: &PluginContext) {
+            self.lock().unwrap().restarts += 1;
+            context.send(IncomingEvent::NewGame);
+        }
+
+        fn on_frame(&mut self, state: &InterpreterState, _: &PluginContext) {
+            let mut counter = self.lock().unwrap();
+            counter.frames += 1;
+            counter.last_pc = state.get_pc();
+        }
+    }
+
+    #[test]
+    fn observe_and_inject() {
+        let counter = Arc::new(Mutex::new(Counter::default()));
+        let bus = EventBus::empty();
+        let receiver = bus.subscribe(&[EventKind::NewGame]);
+        let plugin: SharedPlugin = Arc::new(Mutex::new(Box::new(counter.clone())));
+        bus.register(Box::new(PluginEventManager::new(
+            plugin,
+            PluginContext::new(bus.get_event_sender()),
+        )));
+        bus.get_event_sender()
+            .send(IncomingEvent::Pause(true))
+            .unwrap();
+        bus.get_event_sender().send(IncomingEvent::Restart).unwrap();
+        assert!(matches!(
+            receiver.recv_timeout(Duration::from_secs(1)),
+            Ok(IncomingEvent::NewGame)
+        ));
+        assert_eq!(counter.lock().unwrap().restarts, 1);
+    }
+
+    #[test]
+    fn frames() {
+        let counter = Arc::new(Mutex::new(Counter::default()));
+        let mut cut = Emulator::builder()
+            .opcodes_per_frame(1)
+            .plugin(counter.clone())
+            .build_headless_chip8();
+        // jumps to itself
+        cut.load_memory(vec![0x12, 0x00]);
+        cut.run_headless(3, None, &[]);
+        assert_eq!(counter.lock().unwrap().frames, 3);
+        assert_eq!(counter.lock().unwrap().last_pc, 0x200);
+    }
+}
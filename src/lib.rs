@@ -0,0 +1,70 @@
+use rfd::FileDialog;
+use sdl2::pixels::Color;
+use std::fmt::Debug;
+use std::path::Path;
+
+pub mod cli;
+pub mod config;
+pub mod emulator;
+pub mod events;
+pub mod sdl2_interaction;
+
+pub trait LogError {
+    fn elog(self, msg: &str);
+}
+impl<T, E: Debug> LogError for Result<T, E> {
+    fn elog(self, msg: &str) {
+        if let Err(e) = self {
+            eprintln!("ERROR while {}: {:?}", msg, e);
+        }
+    }
+}
+
+pub trait LogWarning {
+    fn wlog(self);
+}
+impl LogWarning for &str {
+    fn wlog(self) {
+        println!("WARNING: {}", self);
+    }
+}
+
+pub trait LogInfo {
+    fn log(self);
+}
+impl LogInfo for &str {
+    fn log(self) {
+        println!("INFO: {}", self);
+    }
+}
+
+/// defaults of `config::Settings`
+pub static SPEED_CHANGE_PER_KEYPRESS: f32 = 1.2;
+pub static FPS: f32 = 60.0;
+pub static PAUSE_TRANSPARENT_COLOR: Color = Color::RGBA(0xFF, 0xFF, 0xFF, 0x99);
+pub static OPCODES_PER_FRAME: u32 = 12;
+pub static SCREEN_WIDTH: u32 = 128;
+pub static SCREEN_HEIGHT: u32 = 64;
+pub static STARTING_SCALE: u32 = 10;
+pub static STANDARD_BUZZ_FREQUENCY: f32 = 440.0;
+
+/// the file extensions of roms (lowercase)
+pub static ROM_EXTENSIONS: [&str; 3] = ["ch8", "rom", "bin"];
+
+/// true if the file has one of the `ROM_EXTENSIONS` (in any case)
+pub fn has_rom_extension(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| ROM_EXTENSIONS.contains(&e.to_lowercase().as_str()))
+}
+
+pub fn get_fd(sub_dir: &str) -> FileDialog {
+    let mut fd = FileDialog::new();
+    if let Ok(cur) = std::env::current_dir() {
+        let joined = cur.join(sub_dir);
+        if let Some(valid_path) = [joined, cur].into_iter().find(|p| p.exists()) {
+            fd = fd.set_directory(valid_path);
+        }
+    }
+    fd
+}
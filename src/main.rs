@@ -1,97 +1,118 @@
-#![feature(result_option_inspect)]
-
-use rfd::FileDialog;
-use sdl2::pixels::Color;
-use std::fmt::Debug;
 use std::fs;
+use std::path::PathBuf;
 
-use crate::emulator::{Emulator, End};
-
-mod emulator;
-mod events;
-mod sdl2_interaction;
-
-pub trait LogError {
-    fn elog(self, msg: &str);
-}
-impl<T, E: Debug> LogError for Result<T, E> {
-    fn elog(self, msg: &str) {
-        if let Err(e) = self {
-            eprintln!("ERROR while {}: {:?}", msg, e);
-        }
-    }
-}
-
-pub trait LogWarning {
-    fn wlog(self);
-}
-impl LogWarning for &str {
-    fn wlog(self) {
-        println!("WARNING: {}", self);
-    }
-}
-
-pub trait LogInfo {
-    fn log(self);
-}
-impl LogInfo for &str {
-    fn log(self) {
-        println!("INFO: {}", self);
-    }
-}
-
-static SPEED_CHANGE_PER_KEYPRESS: f32 = 1.2;
-static FPS: f32 = 60.0;
-static PAUSE_TRANSPARENT_COLOR: Color = Color::RGBA(0xFF, 0xFF, 0xFF, 0x99);
-static OPCODES_PER_FRAME: u32 = 12;
-static SCREEN_WIDTH: u32 = 128;
-static SCREEN_HEIGHT: u32 = 64;
-static STARTING_SCALE: u32 = 10;
-static STANDARD_BUZZ_FREQUENCY: f32 = 440.0;
+use chip8_emulator::cli::{self, Command, RunOptions, HELP};
+use chip8_emulator::config::{init_settings, Config, WindowRecorder};
+use chip8_emulator::emulator::conformance;
+use chip8_emulator::emulator::headless;
+use chip8_emulator::emulator::{Emulator, End};
+use chip8_emulator::sdl2_interaction::rom_browser::RomBrowser;
+use chip8_emulator::LogError;
 
-pub fn get_fd(sub_dir: &str) -> FileDialog {
-    let mut fd = FileDialog::new();
-    if let Ok(cur) = std::env::current_dir() {
-        let joined = cur.join(sub_dir);
-        if let Some(valid_path) = [joined, cur].into_iter().find(|p| p.exists()) {
-            fd = fd.set_directory(valid_path);
+fn main() {
+    let command = match Command::from_args(std::env::args().skip(1)) {
+        Ok(command) => command,
+        Err(e) => {
+            eprintln!("ERROR while parsing arguments: {}", e);
+            eprintln!("Use `--help` to see all options.");
+            std::process::exit(headless::EXIT_ERROR);
         }
+    };
+    match command {
+        Command::Run(options) => run(options),
+        Command::Headless(options) => std::process::exit(headless::run(&options)),
+        Command::Disassemble(options) => std::process::exit(cli::disassemble(&options)),
+        Command::Info(rom) => std::process::exit(cli::info(&rom)),
+        Command::Test(options) => std::process::exit(conformance::run(&options)),
+        Command::Help => print!("{}", HELP),
     }
-    fd
 }
 
-fn main() {
+fn run(options: RunOptions) {
     println!("---(SUPER) CHIP8 EMULATOR BY M1ngXU---");
-    let mut emulator = Emulator::new_chip8(FPS, OPCODES_PER_FRAME);
-    let mut arg_path = std::env::args().nth(1);
+    let mut config = Config::load();
+    let mut settings = config.settings.clone();
+    options.apply_settings(&mut settings);
+    init_settings(settings.clone());
+    let window_recorder = WindowRecorder::new(&settings);
+    let mut emulator = match options.emulation.apply(
+        Emulator::builder()
+            .settings(&settings)
+            .plugin(window_recorder.clone()),
+    ) {
+        Ok(builder) => builder.build_chip8(),
+        Err(e) => {
+            eprintln!("ERROR while parsing arguments: {}", e);
+            std::process::exit(headless::EXIT_ERROR);
+        }
+    };
+    let mut arg_path = options.rom.clone().map(PathBuf::from);
+    let mut browser = RomBrowser::new(settings.get_rom_directory(), settings.file_dialog);
     'main: loop {
-        if let Some(path) = arg_path.take().or_else(|| {
-            get_fd("roms")
-                .add_filter("Chip8 Binary", &["rom", "ch8", "bin"])
-                .add_filter("all", &["*"])
-                .set_title("Choose a Chip8 binary file")
-                .pick_file()
-                .and_then(|p| p.to_str().map(|s| s.to_owned()))
-        }) {
-            arg_path.take();
-            match fs::read(&path) {
-                Ok(bin) => {
-                    while {
-                        emulator.load_memory(bin.clone());
-                        match emulator.run() {
-                            End::Quit => break 'main,
-                            End::Restart => true,
-                            End::NewGame => continue 'main,
-                        }
-                    } {
-                        println!("Restarting");
+        let path = match arg_path.take() {
+            Some(path) => path,
+            None => {
+                browser.open(&config);
+                match emulator.browse(&mut browser) {
+                    Some(path) => path,
+                    None => break 'main,
+                }
+            }
+        };
+        match fs::read(&path) {
+            Ok(bin) => {
+                config.add_recent(&path);
+                let mut rom_settings = config.get_rom_settings(&bin);
+                options.apply_settings(&mut rom_settings);
+                emulator.apply_settings(&rom_settings);
+                let mut state = options.emulation.state.is_some();
+                let end = loop {
+                    emulator.load_memory(bin.clone());
+                    // the state belongs to the rom of the command line
+                    if std::mem::take(&mut state) {
+                        options
+                            .emulation
+                            .load_state(&mut emulator)
+                            .elog("loading state");
                     }
+                    match emulator.run() {
+                        End::Restart => println!("Restarting"),
+                        end => break end,
+                    }
+                };
+                if emulator.get_speed() != rom_settings.speed {
+                    config.get_rom_mut(&bin).speed = Some(emulator.get_speed());
+                }
+                if emulator.get_keymap() != &rom_settings.keymap {
+                    config.get_rom_mut(&bin).keymap = Some(emulator.get_keymap().clone());
+                }
+                match end {
+                    End::Quit => break 'main,
+                    End::Open(path) => arg_path = Some(path),
+                    _ => {}
                 }
-                Err(e) => eprintln!("Failed to read file \"{}\" - error: \"{}\".", path, e),
             }
-        } else {
-            eprintln!("Failed to get file!");
+            Err(e) => eprintln!(
+                "Failed to read file \"{}\" - error: \"{}\".",
+                path.display(),
+                e
+            ),
         }
     }
+    // only changes made at runtime are saved, not the ones of the command line
+    let window_changes = window_recorder.get_changes();
+    if window_changes.scale != settings.scale {
+        config.settings.scale = window_changes.scale;
+    }
+    if window_changes.fullscreen != settings.fullscreen {
+        config.settings.fullscreen = window_changes.fullscreen;
+    }
+    if window_changes.crt_filter != settings.crt_filter {
+        config.settings.crt_filter = window_changes.crt_filter;
+    }
+    if window_changes.upscaler != settings.upscaler {
+        config.settings.upscaler = window_changes.upscaler;
+    }
+    config.save();
     println!("EMULATION TERMINATED");
 }
@@ -1,18 +1,12 @@
 use sdl2::audio::{AudioCallback, AudioDevice, AudioSpecDesired};
 use sdl2::Sdl;
 
-use crate::sdl2_interaction::event_manager::{AppEventReceiver, Event, IncomingEvent};
-use crate::{SPEED_CHANGE_PER_KEYPRESS, STANDARD_BUZZ_FREQUENCY};
+use crate::config::settings;
+use crate::sdl2_interaction::event_manager::{AppEventReceiver, IncomingEvent};
 
 #[derive(PartialEq, Eq, Copy, Clone, Debug)]
 pub enum AudioEvent {
     Buzz(bool),
-    Any,
-}
-impl Event for AudioEvent {
-    fn is_any(&self) -> bool {
-        matches!(self, &AudioEvent::Any)
-    }
 }
 
 pub struct SquareWave {
@@ -81,8 +75,7 @@ impl<'a> AudioManager<'a> {
                     samples: None,
                 },
                 |spec| SquareWave {
-                    phase_inc: SPEED_CHANGE_PER_KEYPRESS.powi(speed as i32)
-                        * STANDARD_BUZZ_FREQUENCY
+                    phase_inc: settings().get_speed_factor(speed) * settings().buzz_frequency
                         / spec.freq as f32,
                     phase: 0.0,
                     volume: 0.25,
@@ -0,0 +1,387 @@
+use std::collections::{BTreeMap, HashSet};
+
+use sdl2::controller::{Axis, Button, GameController};
+use sdl2::event::Event as SdlEvent;
+use sdl2::GameControllerSubsystem;
+use serde::{Deserialize, Serialize};
+
+use crate::events::input::InputEvent;
+use crate::LogInfo;
+
+/// how far a stick or trigger has to be moved to count as pressed
+static AXIS_THRESHOLD: i16 = i16::MAX / 2;
+
+static BUTTON_NAMES: [(&str, Button); 15] = [
+    ("a", Button::A),
+    ("b", Button::B),
+    ("x", Button::X),
+    ("y", Button::Y),
+    ("back", Button::Back),
+    ("guide", Button::Guide),
+    ("start", Button::Start),
+    ("leftstick", Button::LeftStick),
+    ("rightstick", Button::RightStick),
+    ("leftshoulder", Button::LeftShoulder),
+    ("rightshoulder", Button::RightShoulder),
+    ("dpup", Button::DPadUp),
+    ("dpdown", Button::DPadDown),
+    ("dpleft", Button::DPadLeft),
+    ("dpright", Button::DPadRight),
+];
+static AXIS_NAMES: [(&str, Axis); 6] = [
+    ("leftx", Axis::LeftX),
+    ("lefty", Axis::LeftY),
+    ("rightx", Axis::RightX),
+    ("righty", Axis::RightY),
+    ("lefttrigger", Axis::TriggerLeft),
+    ("righttrigger", Axis::TriggerRight),
+];
+
+/// a button or the direction of an axis (e.g. the left stick moved up is `lefty-`)
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ControllerInput {
+    Button(Button),
+    Axis(Axis, bool),
+}
+impl ControllerInput {
+    /// the SDL names of buttons (e.g. `a` or `dpup`) and of axes with a direction (e.g. `leftx+`)
+    pub fn parse(name: &str) -> Option<Self> {
+        let name = name.trim().to_lowercase();
+        if let Some((_, button)) = BUTTON_NAMES.iter().find(|(n, _)| *n == name) {
+            return Some(ControllerInput::Button(*button));
+        }
+        let (axis, positive) = match name.split_at(name.len().saturating_sub(1)) {
+            (axis, "+") => (axis, true),
+            (axis, "-") => (axis, false),
+            _ => return None,
+        };
+        AXIS_NAMES
+            .iter()
+            .find(|(n, _)| *n == axis)
+            .map(|(_, a)| ControllerInput::Axis(*a, positive))
+    }
+
+    pub fn get_name(&self) -> String {
+        match self {
+            ControllerInput::Button(button) => BUTTON_NAMES
+                .iter()
+                .find(|(_, b)| b == button)
+                .map_or("", |(n, _)| n)
+                .to_owned(),
+            ControllerInput::Axis(axis, positive) => format!(
+                "{}{}",
+                AXIS_NAMES
+                    .iter()
+                    .find(|(_, a)| a == axis)
+                    .map_or("", |(n, _)| n),
+                if *positive { '+' } else { '-' }
+            ),
+        }
+    }
+
+    /// the direction an axis is pressed in, if it is moved far enough
+    pub fn from_axis(axis: Axis, value: i16) -> Option<Self> {
+        if value > AXIS_THRESHOLD {
+            Some(ControllerInput::Axis(axis, true))
+        } else if value < -AXIS_THRESHOLD {
+            Some(ControllerInput::Axis(axis, false))
+        } else {
+            None
+        }
+    }
+}
+
+/// the controller inputs of every hex key
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(
+    try_from = "BTreeMap<String, Vec<String>>",
+    into = "BTreeMap<String, Vec<String>>"
+)]
+pub struct ControllerMap {
+    inputs: [Vec<ControllerInput>; 16],
+}
+impl Default for ControllerMap {
+    /// the d-pad and the left stick are 2/4/6/8, A is 5
+    fn default() -> Self {
+        let mut map = Self {
+            inputs: Default::default(),
+        };
+        for (hex, names) in [
+            (0x2, ["dpup", "lefty-"]),
+            (0x4, ["dpleft", "leftx-"]),
+            (0x6, ["dpright", "leftx+"]),
+            (0x8, ["dpdown", "lefty+"]),
+        ] {
+            map.inputs[hex] = names
+                .iter()
+                .filter_map(|n| ControllerInput::parse(n))
+                .collect();
+        }
+        for (hex, button) in [
+            (0x5, Button::A),
+            (0x0, Button::B),
+            (0x7, Button::X),
+            (0x9, Button::Y),
+            (0x1, Button::LeftShoulder),
+            (0xC, Button::RightShoulder),
+            (0xE, Button::Back),
+            (0xF, Button::Start),
+        ] {
+            map.inputs[hex] = vec![ControllerInput::Button(button)];
+        }
+        map
+    }
+}
+impl ControllerMap {
+    /// the first hex key the input is mapped to
+    pub fn get_hex(&self, input: &ControllerInput) -> Option<u8> {
+        self.inputs
+            .iter()
+            .position(|i| i.contains(input))
+            .map(|h| h as u8)
+    }
+
+    pub fn get_inputs(&self, hex: u8) -> &[ControllerInput] {
+        &self.inputs[hex as usize % 16]
+    }
+
+    pub fn set_inputs(&mut self, hex: u8, inputs: Vec<ControllerInput>) {
+        self.inputs[hex as usize % 16] = inputs;
+    }
+}
+impl TryFrom<BTreeMap<String, Vec<String>>> for ControllerMap {
+    type Error = String;
+
+    /// missing hex keys keep the default inputs that aren't used by the given ones
+    fn try_from(map: BTreeMap<String, Vec<String>>) -> Result<Self, Self::Error> {
+        let mut given = Self {
+            inputs: Default::default(),
+        };
+        let mut given_hex_keys = Vec::new();
+        for (hex, names) in map {
+            let hex = u8::from_str_radix(hex.trim(), 16)
+                .ok()
+                .filter(|h| *h < 16)
+                .ok_or(format!("invalid hex key `{}`", hex.trim()))?;
+            given.set_inputs(
+                hex,
+                names
+                    .iter()
+                    .map(|n| {
+                        ControllerInput::parse(n).ok_or(format!("unknown controller input `{}`", n))
+                    })
+                    .collect::<Result<_, _>>()?,
+            );
+            given_hex_keys.push(hex);
+        }
+        let mut controller_map = Self::default();
+        for hex in 0..16 {
+            if given_hex_keys.contains(&hex) {
+                controller_map.set_inputs(hex, given.get_inputs(hex).to_vec());
+            } else {
+                controller_map.inputs[hex as usize].retain(|i| given.get_hex(i).is_none());
+            }
+        }
+        Ok(controller_map)
+    }
+}
+impl From<ControllerMap> for BTreeMap<String, Vec<String>> {
+    /// only the hex keys that differ from the default map
+    fn from(map: ControllerMap) -> Self {
+        let default = ControllerMap::default();
+        (0..16)
+            .filter(|h| map.get_inputs(*h) != default.get_inputs(*h))
+            .map(|h| {
+                (
+                    format!("{:X}", h),
+                    map.get_inputs(h)
+                        .iter()
+                        .map(ControllerInput::get_name)
+                        .collect(),
+                )
+            })
+            .collect()
+    }
+}
+
+struct Pad {
+    controller: GameController,
+    player: usize,
+    pressed: HashSet<ControllerInput>,
+}
+
+/// opens controllers when they are connected and turns their events into `InputEvent`s, the
+/// player of a controller is the lowest one not used by another connected controller
+pub struct ControllerManager {
+    subsystem: GameControllerSubsystem,
+    pads: Vec<Pad>,
+}
+impl ControllerManager {
+    /// controllers connected before are reported by SDL as added as well
+    pub fn new(subsystem: GameControllerSubsystem) -> Self {
+        Self {
+            subsystem,
+            pads: Vec::new(),
+        }
+    }
+
+    /// the number of connected controllers
+    pub fn get_count(&self) -> usize {
+        self.pads.len()
+    }
+
+    fn get_pad(&mut self, instance_id: u32) -> Option<&mut Pad> {
+        self.pads
+            .iter_mut()
+            .find(|p| p.controller.instance_id() == instance_id)
+    }
+
+    fn press(pad: &mut Pad, input: ControllerInput, down: bool) -> Option<InputEvent> {
+        if down && pad.pressed.insert(input) {
+            Some(InputEvent::ControllerDown(pad.player, input))
+        } else if !down && pad.pressed.remove(&input) {
+            Some(InputEvent::ControllerUp(pad.player, input))
+        } else {
+            None
+        }
+    }
+
+    pub fn update(&mut self, event: &SdlEvent) -> Vec<InputEvent> {
+        match event {
+            SdlEvent::ControllerDeviceAdded { which, .. } => {
+                if let Some(controller) = self.subsystem.open(*which).ok().filter(|c| {
+                    self.pads
+                        .iter()
+                        .all(|p| p.controller.instance_id() != c.instance_id())
+                }) {
+                    let player = (0..)
+                        .find(|n| self.pads.iter().all(|p| p.player != *n))
+                        .unwrap_or_default();
+                    format!(
+                        "Controller `{}` connected as player {}.",
+                        controller.name(),
+                        player + 1
+                    )
+                    .as_str()
+                    .log();
+                    self.pads.push(Pad {
+                        controller,
+                        player,
+                        pressed: HashSet::new(),
+                    });
+                }
+                Vec::new()
+            }
+            SdlEvent::ControllerDeviceRemoved { which, .. } => {
+                match self
+                    .pads
+                    .iter()
+                    .position(|p| p.controller.instance_id() == *which)
+                {
+                    Some(i) => {
+                        let pad = self.pads.remove(i);
+                        format!("Controller `{}` disconnected.", pad.controller.name())
+                            .as_str()
+                            .log();
+                        pad.pressed
+                            .iter()
+                            .map(|i| InputEvent::ControllerUp(pad.player, *i))
+                            .collect()
+                    }
+                    None => Vec::new(),
+                }
+            }
+            SdlEvent::ControllerButtonDown { which, button, .. } => self
+                .get_pad(*which)
+                .and_then(|p| Self::press(p, ControllerInput::Button(*button), true))
+                .into_iter()
+                .collect(),
+            SdlEvent::ControllerButtonUp { which, button, .. } => self
+                .get_pad(*which)
+                .and_then(|p| Self::press(p, ControllerInput::Button(*button), false))
+                .into_iter()
+                .collect(),
+            SdlEvent::ControllerAxisMotion {
+                which, axis, value, ..
+            } => match self.get_pad(*which) {
+                Some(pad) => {
+                    let pressed = ControllerInput::from_axis(*axis, *value);
+                    [true, false]
+                        .into_iter()
+                        .filter_map(|positive| {
+                            let input = ControllerInput::Axis(*axis, positive);
+                            Self::press(pad, input, pressed == Some(input))
+                        })
+                        .collect()
+                }
+                None => Vec::new(),
+            },
+            _ => Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use sdl2::controller::{Axis, Button};
+
+    use crate::sdl2_interaction::controller::{ControllerInput, ControllerMap};
+
+    #[test]
+    fn inputs() {
+        assert_eq!(
+            ControllerInput::parse("DPUp"),
+            Some(ControllerInput::Button(Button::DPadUp))
+        );
+        assert_eq!(
+            ControllerInput::parse("leftx-"),
+            Some(ControllerInput::Axis(Axis::LeftX, false))
+        );
+        assert_eq!(ControllerInput::parse("leftx"), None);
+        assert_eq!(ControllerInput::parse("z"), None);
+        assert_eq!(
+            ControllerInput::Axis(Axis::RightY, true).get_name(),
+            "righty+"
+        );
+        assert_eq!(ControllerInput::from_axis(Axis::LeftX, 100), None);
+        assert_eq!(
+            ControllerInput::from_axis(Axis::LeftX, i16::MIN),
+            Some(ControllerInput::Axis(Axis::LeftX, false))
+        );
+    }
+
+    #[test]
+    fn map() {
+        let mut cut = ControllerMap::default();
+        assert_eq!(
+            cut.get_hex(&ControllerInput::Axis(Axis::LeftY, false)),
+            Some(0x2)
+        );
+        assert_eq!(cut.get_hex(&ControllerInput::Button(Button::A)), Some(0x5));
+        cut.set_inputs(0x5, vec![ControllerInput::Button(Button::Guide)]);
+        let map = BTreeMap::from(cut.clone());
+        assert_eq!(
+            map,
+            BTreeMap::from([("5".to_owned(), vec!["guide".to_owned()])])
+        );
+        assert_eq!(ControllerMap::try_from(map), Ok(cut));
+        let cut =
+            ControllerMap::try_from(BTreeMap::from([("C".to_owned(), vec!["dpup".to_owned()])]))
+                .unwrap();
+        assert_eq!(
+            cut.get_hex(&ControllerInput::Button(Button::DPadUp)),
+            Some(0xC)
+        );
+        assert_eq!(
+            cut.get_inputs(0x2),
+            [ControllerInput::Axis(Axis::LeftY, false)]
+        );
+        assert!(ControllerMap::try_from(BTreeMap::from([(
+            "5".to_owned(),
+            vec!["trigger".to_owned()]
+        )]))
+        .is_err());
+    }
+}
@@ -0,0 +1,217 @@
+use serde::{Deserialize, Serialize};
+
+use crate::sdl2_interaction::image::Image;
+
+/// how bright the dark lines between the rows of pixels are
+static SCANLINE_BRIGHTNESS: f32 = 0.5;
+/// how bright the borders of the pixels are
+static GRID_BRIGHTNESS: f32 = 0.7;
+/// how much of the blurred picture is added
+static BLOOM_STRENGTH: f32 = 0.35;
+/// how much darker the corners are than the center
+static VIGNETTE_STRENGTH: f32 = 0.4;
+
+/// post-processing of the upscaled picture, done on the CPU
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CrtFilter {
+    #[default]
+    None,
+    /// darkens the bottom of every row of pixels
+    Scanlines,
+    /// darkens the borders of every pixel
+    PixelGrid,
+    /// lets bright pixels glow into their neighbors
+    Bloom,
+    /// darkens the corners
+    Vignette,
+    /// scanlines, bloom and vignette
+    Crt,
+}
+impl CrtFilter {
+    pub fn get_all() -> &'static [CrtFilter] {
+        &[
+            CrtFilter::None,
+            CrtFilter::Scanlines,
+            CrtFilter::PixelGrid,
+            CrtFilter::Bloom,
+            CrtFilter::Vignette,
+            CrtFilter::Crt,
+        ]
+    }
+
+    /// the filter after this one, `None` after the last one
+    pub fn next(self) -> Self {
+        let all = Self::get_all();
+        let i = all.iter().position(|f| *f == self).unwrap();
+        all[(i + 1) % all.len()]
+    }
+
+    /// filters an image whose pixels of the framebuffer are (about) squares of `scale` pixels
+    pub fn apply(&self, image: &mut Image, scale: usize) {
+        match self {
+            CrtFilter::None => {}
+            CrtFilter::Scanlines => scanlines(image, scale),
+            CrtFilter::PixelGrid => pixel_grid(image, scale),
+            CrtFilter::Bloom => bloom(image, scale),
+            CrtFilter::Vignette => vignette(image),
+            CrtFilter::Crt => {
+                scanlines(image, scale);
+                bloom(image, scale);
+                vignette(image);
+            }
+        }
+    }
+}
+
+fn darken(image: &mut Image, x: usize, y: usize, brightness: f32) {
+    let rgb = image.get(x, y).map(|c| (c as f32 * brightness) as u8);
+    image.set(x, y, rgb);
+}
+
+/// the bottom third of every row of pixels, nothing if a pixel is a single line
+fn scanlines(image: &mut Image, scale: usize) {
+    if scale < 2 {
+        return;
+    }
+    let dark = (scale / 3).max(1);
+    for y in (0..image.get_height()).filter(|y| y % scale >= scale - dark) {
+        for x in 0..image.get_width() {
+            darken(image, x, y, SCANLINE_BRIGHTNESS);
+        }
+    }
+}
+
+/// the right and bottom line of every pixel
+fn pixel_grid(image: &mut Image, scale: usize) {
+    if scale < 2 {
+        return;
+    }
+    for y in 0..image.get_height() {
+        for x in 0..image.get_width() {
+            if x % scale == scale - 1 || y % scale == scale - 1 {
+                darken(image, x, y, GRID_BRIGHTNESS);
+            }
+        }
+    }
+}
+
+/// adds a box blur of a framebuffer pixel's radius
+fn bloom(image: &mut Image, scale: usize) {
+    let (width, height) = (image.get_width(), image.get_height());
+    let radius = scale.max(1);
+    // a box blur is separable, a horizontal pass followed by a vertical one
+    let blur = |get: &dyn Fn(usize) -> [u8; 3], length: usize| -> Vec<[f32; 3]> {
+        let mut sum = [0.0; 3];
+        let mut blurred = vec![[0.0; 3]; length];
+        let add = |sum: &mut [f32; 3], rgb: [u8; 3], sign: f32| {
+            for (s, c) in sum.iter_mut().zip(rgb) {
+                *s += sign * c as f32;
+            }
+        };
+        for i in 0..radius.min(length) {
+            add(&mut sum, get(i), 1.0);
+        }
+        for (i, b) in blurred.iter_mut().enumerate() {
+            if i + radius < length {
+                add(&mut sum, get(i + radius), 1.0);
+            }
+            if i > radius {
+                add(&mut sum, get(i - radius - 1), -1.0);
+            }
+            let count = (i + radius).min(length - 1) + 1 - i.saturating_sub(radius);
+            *b = sum.map(|s| s / count as f32);
+        }
+        blurred
+    };
+    let mut horizontal = Image::new(width, height);
+    for y in 0..height {
+        for (x, rgb) in blur(&|x| image.get(x, y), width).into_iter().enumerate() {
+            horizontal.set(x, y, rgb.map(|c| c as u8));
+        }
+    }
+    for x in 0..width {
+        for (y, glow) in blur(&|y| horizontal.get(x, y), height)
+            .into_iter()
+            .enumerate()
+        {
+            let rgb = image.get(x, y);
+            image.set(
+                x,
+                y,
+                [0, 1, 2].map(|c| (rgb[c] as f32 + glow[c] * BLOOM_STRENGTH).min(255.0) as u8),
+            );
+        }
+    }
+}
+
+/// darker by the squared distance to the center
+fn vignette(image: &mut Image) {
+    let (width, height) = (image.get_width(), image.get_height());
+    for y in 0..height {
+        let dy = 2.0 * (y as f32 + 0.5) / height as f32 - 1.0;
+        for x in 0..width {
+            let dx = 2.0 * (x as f32 + 0.5) / width as f32 - 1.0;
+            darken(
+                image,
+                x,
+                y,
+                1.0 - VIGNETTE_STRENGTH * (dx * dx + dy * dy) / 2.0,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sdl2_interaction::crt::CrtFilter;
+    use crate::sdl2_interaction::image::Image;
+
+    fn white(width: usize, height: usize) -> Image {
+        let mut image = Image::new(width, height);
+        image.get_pixels_mut().fill(0xFF);
+        image
+    }
+
+    #[test]
+    fn filters() {
+        let mut cut = white(6, 6);
+        CrtFilter::Scanlines.apply(&mut cut, 3);
+        assert_eq!(cut.get(0, 1), [0xFF; 3]);
+        assert_eq!(cut.get(4, 2), [0x7F; 3]);
+        assert_eq!(cut.get(4, 5), [0x7F; 3]);
+
+        let mut cut = white(4, 4);
+        CrtFilter::PixelGrid.apply(&mut cut, 2);
+        assert_eq!(cut.get(0, 0), [0xFF; 3]);
+        assert_eq!(cut.get(1, 0), [0xB2; 3]);
+        assert_eq!(cut.get(2, 3), [0xB2; 3]);
+
+        // a single bright pixel glows into its neighbors
+        let mut cut = Image::new(5, 5);
+        cut.set(2, 2, [0xFF, 0, 0]);
+        CrtFilter::Bloom.apply(&mut cut, 1);
+        assert_eq!(cut.get(2, 2), [0xFF, 0, 0]);
+        assert!(cut.get(1, 2)[0] > 0 && cut.get(3, 3)[0] > 0);
+        assert_eq!(cut.get(0, 0), [0, 0, 0]);
+
+        let mut cut = white(10, 10);
+        CrtFilter::Vignette.apply(&mut cut, 1);
+        assert!(cut.get(0, 0)[0] < cut.get(5, 5)[0]);
+        assert_eq!(cut.get(0, 0), cut.get(9, 9));
+
+        let mut cut = white(4, 4);
+        CrtFilter::None.apply(&mut cut, 2);
+        assert_eq!(cut, white(4, 4));
+    }
+
+    #[test]
+    fn next() {
+        let mut cut = CrtFilter::default();
+        for _ in 0..CrtFilter::get_all().len() - 1 {
+            cut = cut.next();
+            assert_ne!(cut, CrtFilter::None);
+        }
+        assert_eq!(cut.next(), CrtFilter::None);
+    }
+}
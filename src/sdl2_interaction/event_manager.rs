@@ -1,5 +1,5 @@
 use std::collections::HashSet;
-use std::mem::discriminant;
+use std::path::PathBuf;
 use std::sync::mpsc;
 
 use sdl2::event::{Event as SdlEvent, WindowEvent};
@@ -10,28 +10,15 @@ use crate::emulator::interpreter::InterpreterEvent;
 use crate::events::app::AppEvent;
 use crate::events::input::InputEvent;
 use crate::sdl2_interaction::audio_manager::AudioEvent;
+use crate::sdl2_interaction::input_sources::PressedHexKeys;
+use crate::sdl2_interaction::keypad::KeypadEvent;
 use crate::sdl2_interaction::output::ScreenEvent;
+use crate::sdl2_interaction::viewport::Viewport;
 use crate::LogError;
 
 pub type AppEventSender = mpsc::Sender<IncomingEvent>;
 pub type AppEventReceiver = mpsc::Receiver<IncomingEvent>;
 
-pub trait Event {
-    fn is_any(&self) -> bool;
-    fn compare_value(&self, other: &Self) -> bool
-    where
-        Self: Sized,
-    {
-        discriminant(self) == discriminant(other)
-    }
-    fn equals(&self, other: &Self) -> bool
-    where
-        Self: Sized,
-    {
-        self.is_any() || other.is_any() || self.compare_value(other)
-    }
-}
-
 #[derive(Clone, Debug)]
 pub enum IncomingEvent {
     RequestTermination,
@@ -43,23 +30,56 @@ pub enum IncomingEvent {
     Audio(AudioEvent),
     App(AppEvent),
     SetCheatMode(bool),
+    /// the hex key the keymap dialog asks a host key for, `None` closes the dialog
+    KeymapDialog(Option<u8>),
+    /// a serial keypad was (dis)connected or should light keys
+    Keypad(KeypadEvent),
+    /// the hex keys held on every input source changed
+    HexKeys(PressedHexKeys),
     Restart,
     NewGame,
-    Any,
+    /// replaces the game by the rom, e.g. one dropped onto the window
+    OpenRom(PathBuf),
 }
 
-impl Event for IncomingEvent {
-    fn is_any(&self) -> bool {
-        matches!(&self, &IncomingEvent::Any)
-    }
-    fn compare_value(&self, other: &Self) -> bool {
-        match (self, other) {
-            (IncomingEvent::App(s), IncomingEvent::App(o)) => s.equals(o),
-            (IncomingEvent::Input(s), IncomingEvent::Input(o)) => s.equals(o),
-            (IncomingEvent::Interpreter(s), IncomingEvent::Interpreter(o)) => s.equals(o),
-            (IncomingEvent::Screen(s), IncomingEvent::Screen(o)) => s.equals(o),
-            (IncomingEvent::Audio(s), IncomingEvent::Audio(o)) => s.equals(o),
-            _ => discriminant(self) == discriminant(other),
+/// the variant of an `IncomingEvent` without its value, used to subscribe to events
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum EventKind {
+    RequestTermination,
+    Pause,
+    SetSpeed,
+    Input,
+    Interpreter,
+    Screen,
+    Audio,
+    App,
+    SetCheatMode,
+    KeymapDialog,
+    Keypad,
+    HexKeys,
+    Restart,
+    NewGame,
+    OpenRom,
+}
+
+impl IncomingEvent {
+    pub fn kind(&self) -> EventKind {
+        match self {
+            IncomingEvent::RequestTermination => EventKind::RequestTermination,
+            IncomingEvent::Pause(_) => EventKind::Pause,
+            IncomingEvent::SetSpeed(_) => EventKind::SetSpeed,
+            IncomingEvent::Input(_) => EventKind::Input,
+            IncomingEvent::Interpreter(_) => EventKind::Interpreter,
+            IncomingEvent::Screen(_) => EventKind::Screen,
+            IncomingEvent::Audio(_) => EventKind::Audio,
+            IncomingEvent::App(_) => EventKind::App,
+            IncomingEvent::SetCheatMode(_) => EventKind::SetCheatMode,
+            IncomingEvent::KeymapDialog(_) => EventKind::KeymapDialog,
+            IncomingEvent::Keypad(_) => EventKind::Keypad,
+            IncomingEvent::HexKeys(_) => EventKind::HexKeys,
+            IncomingEvent::Restart => EventKind::Restart,
+            IncomingEvent::NewGame => EventKind::NewGame,
+            IncomingEvent::OpenRom(_) => EventKind::OpenRom,
         }
     }
 }
@@ -80,7 +100,8 @@ impl AppEventManager {
     }
 
     /// multithreading possible
-    pub fn update(&mut self, event: SdlEvent, scale: (u32, u32)) {
+    /// mouse positions are mapped to framebuffer pixels by the viewport
+    pub fn update(&mut self, event: SdlEvent, viewport: &Viewport) {
         for key in self.pressed_keys.iter() {
             self.app_event_sender
                 .send(IncomingEvent::Input(InputEvent::KeyPress(*key)))
@@ -139,13 +160,20 @@ impl AppEventManager {
                 {
                     IncomingEvent::Input(InputEvent::MouseButtonUp(mouse_btn))
                 }
-                SdlEvent::MouseMotion { x, y, .. } => IncomingEvent::Input(
-                    InputEvent::UpdateMouseCoordinates(x / scale.0 as i32, y / scale.1 as i32),
-                ),
+                // the bars around the viewport aren't part of the screen
+                SdlEvent::MouseMotion { x, y, .. } => match viewport.to_framebuffer(x, y) {
+                    Some((x, y)) => {
+                        IncomingEvent::Input(InputEvent::UpdateMouseCoordinates(x as i32, y as i32))
+                    }
+                    None => return,
+                },
                 SdlEvent::Window {
                     win_event: WindowEvent::SizeChanged(w, h),
                     ..
                 } => IncomingEvent::App(AppEvent::WindowSizeChange(w, h)),
+                SdlEvent::DropFile { filename, .. } => {
+                    IncomingEvent::App(AppEvent::DropFile(PathBuf::from(filename)))
+                }
                 SdlEvent::Window {
                     win_event: WindowEvent::Close,
                     ..
@@ -0,0 +1,173 @@
+/// one row of a plane, the most significant bit is the leftmost pixel
+pub type Row = u128;
+
+pub const WIDTH: usize = Row::BITS as usize;
+pub const HEIGHT: usize = 64;
+
+/// fixed-size bit planes of 128x64 pixels, a lower resolution is emulated by setting multiple
+/// pixels at once (see `Screen`)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Framebuffer {
+    planes: Vec<[Row; HEIGHT]>,
+}
+impl Framebuffer {
+    pub fn new(planes: usize) -> Self {
+        Self {
+            planes: vec![[0; HEIGHT]; planes.max(1)],
+        }
+    }
+
+    pub fn get_plane_count(&self) -> usize {
+        self.planes.len()
+    }
+
+    fn mask(x: usize) -> Row {
+        1 << (WIDTH - 1 - x)
+    }
+
+    /// pixels outside of the framebuffer (or of missing planes) are always `off`
+    pub fn get(&self, plane: usize, x: usize, y: usize) -> bool {
+        x < WIDTH && self.get_row(plane, y) & Self::mask(x) != 0
+    }
+
+    /// returns true if the pixel changed, pixels outside of the framebuffer (or of missing
+    /// planes) are ignored
+    pub fn set(&mut self, plane: usize, x: usize, y: usize, v: bool) -> bool {
+        if x >= WIDTH || self.get(plane, x, y) == v {
+            return false;
+        }
+        match self.planes.get_mut(plane).and_then(|p| p.get_mut(y)) {
+            Some(row) => *row ^= Self::mask(x),
+            None => return false,
+        }
+        true
+    }
+
+    /// the bits of a pixel of all planes, bit `n` being plane `n`
+    pub fn get_value(&self, x: usize, y: usize) -> u8 {
+        (0..self.planes.len())
+            .filter(|p| self.get(*p, x, y))
+            .fold(0, |v, p| v | 1 << p)
+    }
+
+    /// rows outside of the framebuffer (or of missing planes) are empty
+    pub fn get_row(&self, plane: usize, y: usize) -> Row {
+        self.planes
+            .get(plane)
+            .and_then(|p| p.get(y))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// all pixels that are `on` in any plane
+    pub fn iter_on(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        (0..HEIGHT).flat_map(move |y| {
+            let row = self.planes.iter().fold(0, |r, p| r | p[y]);
+            (0..WIDTH)
+                .filter(move |x| row & Self::mask(*x) != 0)
+                .map(move |x| (x, y))
+        })
+    }
+
+    pub fn clear(&mut self) {
+        for plane in self.planes.iter_mut() {
+            *plane = [0; HEIGHT];
+        }
+    }
+
+    /// moves the rows of a plane down, rows moved out of the framebuffer are lost
+    pub fn scroll_down(&mut self, plane: usize, amount: usize) {
+        let amount = amount.min(HEIGHT);
+        let Some(rows) = self.planes.get_mut(plane) else {
+            return;
+        };
+        rows.rotate_right(amount);
+        rows[..amount].fill(0);
+    }
+
+    /// moves the pixels of a plane to the right (or to the left if negative)
+    pub fn scroll_side(&mut self, plane: usize, amount: isize) {
+        let shift = amount.unsigned_abs() as u32;
+        for row in self.planes.get_mut(plane).into_iter().flatten() {
+            *row = if amount < 0 {
+                row.checked_shl(shift)
+            } else {
+                row.checked_shr(shift)
+            }
+            .unwrap_or(0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sdl2_interaction::framebuffer::{Framebuffer, HEIGHT, WIDTH};
+
+    #[test]
+    fn set_and_get() {
+        let mut cut = Framebuffer::new(1);
+        assert!(!cut.get(0, 0, 0));
+        assert!(cut.set(0, 0, 0, true));
+        assert!(!cut.set(0, 0, 0, true));
+        assert!(cut.get(0, 0, 0));
+        assert_eq!(cut.get_row(0, 0), 1 << 127);
+        assert!(cut.set(0, WIDTH - 1, HEIGHT - 1, true));
+        assert_eq!(cut.get_row(0, HEIGHT - 1), 1);
+        assert!(cut.set(0, 0, 0, false));
+        assert!(!cut.get(0, 0, 0));
+    }
+
+    #[test]
+    fn outside_is_ignored() {
+        let mut cut = Framebuffer::new(1);
+        assert!(!cut.set(0, WIDTH, 0, true));
+        assert!(!cut.set(0, 0, HEIGHT, true));
+        assert!(!cut.get(0, WIDTH, 0));
+        assert!(!cut.set(1, 0, 0, true));
+        assert!(!cut.get(1, 0, 0));
+        assert_eq!(cut.get_row(0, HEIGHT), 0);
+        assert_eq!(cut.get_row(1, 0), 0);
+        cut.scroll_down(1, 1);
+        cut.scroll_side(1, 1);
+        assert_eq!(cut.iter_on().count(), 0);
+    }
+
+    #[test]
+    fn planes() {
+        let mut cut = Framebuffer::new(2);
+        assert_eq!(cut.get_plane_count(), 2);
+        cut.set(0, 1, 1, true);
+        cut.set(1, 2, 1, true);
+        cut.set(0, 3, 1, true);
+        cut.set(1, 3, 1, true);
+        assert_eq!(
+            (0..4).map(|x| cut.get_value(x, 1)).collect::<Vec<u8>>(),
+            [0, 1, 2, 3]
+        );
+        assert_eq!(
+            cut.iter_on().collect::<Vec<(usize, usize)>>(),
+            [(1, 1), (2, 1), (3, 1)]
+        );
+        cut.clear();
+        assert_eq!(cut.iter_on().count(), 0);
+        assert_eq!(Framebuffer::new(0).get_plane_count(), 1);
+    }
+
+    #[test]
+    fn scroll() {
+        let mut cut = Framebuffer::new(2);
+        cut.set(0, 0, 0, true);
+        cut.set(1, 0, 0, true);
+        cut.scroll_down(0, 3);
+        cut.scroll_side(0, 4);
+        assert_eq!(
+            cut.iter_on().collect::<Vec<(usize, usize)>>(),
+            [(0, 0), (4, 3)]
+        );
+        cut.scroll_side(0, -5);
+        assert_eq!(cut.iter_on().collect::<Vec<(usize, usize)>>(), [(0, 0)]);
+        cut.scroll_down(1, HEIGHT);
+        cut.scroll_side(1, -(WIDTH as isize));
+        assert_eq!(cut.iter_on().count(), 0);
+    }
+}
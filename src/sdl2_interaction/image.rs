@@ -0,0 +1,95 @@
+/// an RGB24 picture
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Image {
+    width: usize,
+    height: usize,
+    pixels: Vec<u8>,
+}
+impl Image {
+    /// a black picture
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![0; width * height * 3],
+        }
+    }
+
+    /// keeps the allocation if it is large enough, the pixels have to be written afterwards
+    pub fn resize(&mut self, width: usize, height: usize) {
+        self.width = width;
+        self.height = height;
+        self.pixels.resize(width * height * 3, 0);
+    }
+
+    pub fn get_width(&self) -> usize {
+        self.width
+    }
+
+    pub fn get_height(&self) -> usize {
+        self.height
+    }
+
+    /// the bytes of a row
+    pub fn get_pitch(&self) -> usize {
+        self.width * 3
+    }
+
+    /// row by row, three bytes per pixel
+    pub fn get_pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    pub fn get_pixels_mut(&mut self) -> &mut [u8] {
+        &mut self.pixels
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> [u8; 3] {
+        let i = (y * self.width + x) * 3;
+        [self.pixels[i], self.pixels[i + 1], self.pixels[i + 2]]
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, rgb: [u8; 3]) {
+        let i = (y * self.width + x) * 3;
+        self.pixels[i..i + 3].copy_from_slice(&rgb);
+    }
+
+    /// every pixel becomes a square of `scale`x`scale` pixels
+    pub fn scale(&self, scale: usize) -> Self {
+        let mut scaled = Self::default();
+        self.scale_into(scale, &mut scaled);
+        scaled
+    }
+
+    /// like `scale`, but reuses the allocation of `scaled`
+    pub fn scale_into(&self, scale: usize, scaled: &mut Self) {
+        scaled.resize(self.width * scale, self.height * scale);
+        for (y, row) in scaled.pixels.chunks_mut(self.width * scale * 3).enumerate() {
+            let source = &self.pixels[y / scale * self.get_pitch()..][..self.get_pitch()];
+            for (x, pixel) in row.chunks_mut(3).enumerate() {
+                pixel.copy_from_slice(&source[x / scale * 3..][..3]);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sdl2_interaction::image::Image;
+
+    #[test]
+    fn scale() {
+        let mut cut = Image::new(2, 1);
+        cut.set(1, 0, [1, 2, 3]);
+        let scaled = cut.scale(2);
+        assert_eq!((scaled.get_width(), scaled.get_height()), (4, 2));
+        assert_eq!(scaled.get(1, 1), [0, 0, 0]);
+        assert_eq!(scaled.get(2, 0), [1, 2, 3]);
+        assert_eq!(scaled.get(3, 1), [1, 2, 3]);
+        assert_eq!(cut.scale(1), cut);
+
+        let mut reused = Image::new(8, 8);
+        cut.scale_into(2, &mut reused);
+        assert_eq!(reused, scaled);
+    }
+}
@@ -0,0 +1,197 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::sdl2_interaction::pressed_key::Keymap;
+
+/// a device pressing hex keys
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum InputSource {
+    Keyboard,
+    /// the controller of a player, starting at 0
+    Controller(usize),
+    /// a serial keypad in the order they were connected, starting at 0
+    Keypad(usize),
+}
+impl InputSource {
+    /// parses `keyboard`, `playerN` or `keypadN`, `N` starting at 1
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let s = s.trim().to_lowercase();
+        let number = |prefix: &str| {
+            s.strip_prefix(prefix)?
+                .parse::<usize>()
+                .ok()
+                .filter(|n| *n > 0)
+                .map(|n| n - 1)
+        };
+        if s == "keyboard" {
+            Ok(InputSource::Keyboard)
+        } else if let Some(player) = number("player") {
+            Ok(InputSource::Controller(player))
+        } else if let Some(keypad) = number("keypad") {
+            Ok(InputSource::Keypad(keypad))
+        } else {
+            Err(format!("unknown input source `{}`", s))
+        }
+    }
+}
+impl fmt::Display for InputSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InputSource::Keyboard => write!(f, "keyboard"),
+            InputSource::Controller(player) => write!(f, "player{}", player + 1),
+            InputSource::Keypad(keypad) => write!(f, "keypad{}", keypad + 1),
+        }
+    }
+}
+
+/// the hex keys every source may press (bit `n` being hex key `n`), sources that aren't listed
+/// may press all of them
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(
+    try_from = "BTreeMap<String, Vec<String>>",
+    into = "BTreeMap<String, Vec<String>>"
+)]
+pub struct InputAssignment {
+    keys: BTreeMap<InputSource, u16>,
+}
+impl InputAssignment {
+    pub fn get_keys(&self, source: &InputSource) -> u16 {
+        self.keys.get(source).copied().unwrap_or(u16::MAX)
+    }
+
+    pub fn set_keys(&mut self, source: InputSource, keys: u16) {
+        self.keys.insert(source, keys);
+    }
+}
+impl TryFrom<BTreeMap<String, Vec<String>>> for InputAssignment {
+    type Error = String;
+
+    fn try_from(map: BTreeMap<String, Vec<String>>) -> Result<Self, Self::Error> {
+        let mut assignment = Self::default();
+        for (source, hex_keys) in map {
+            assignment.set_keys(
+                InputSource::parse(&source)?,
+                hex_keys
+                    .iter()
+                    .map(|h| Keymap::parse_hex(h))
+                    .try_fold(0, |keys, hex| hex.map(|h| keys | 1 << h))?,
+            );
+        }
+        Ok(assignment)
+    }
+}
+impl From<InputAssignment> for BTreeMap<String, Vec<String>> {
+    fn from(assignment: InputAssignment) -> Self {
+        assignment
+            .keys
+            .iter()
+            .map(|(source, keys)| {
+                (
+                    source.to_string(),
+                    (0..16)
+                        .filter(|h| keys & 1 << h != 0)
+                        .map(|h| format!("{:X}", h))
+                        .collect(),
+                )
+            })
+            .collect()
+    }
+}
+
+/// the hex keys held on every source
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PressedHexKeys {
+    pressed: BTreeMap<InputSource, u16>,
+}
+impl PressedHexKeys {
+    /// returns true if the keys of the source changed
+    pub fn set(&mut self, source: InputSource, keys: u16) -> bool {
+        let before = self.get(&source);
+        if keys == 0 {
+            self.pressed.remove(&source);
+        } else {
+            self.pressed.insert(source, keys);
+        }
+        before != keys
+    }
+
+    pub fn get(&self, source: &InputSource) -> u16 {
+        self.pressed.get(source).copied().unwrap_or_default()
+    }
+
+    /// the hex keys held on controllers and keypads
+    pub fn get_device_keys(&self) -> u16 {
+        self.pressed
+            .iter()
+            .filter(|(source, _)| **source != InputSource::Keyboard)
+            .fold(0, |all, (_, keys)| all | keys)
+    }
+
+    /// one line per source holding keys, e.g. `player2 C D`
+    pub fn get_description(&self) -> String {
+        self.pressed
+            .iter()
+            .map(|(source, keys)| {
+                format!(
+                    "{}{}\n",
+                    source,
+                    (0..16)
+                        .filter(|h| keys & 1 << h != 0)
+                        .map(|h| format!(" {:X}", h))
+                        .collect::<String>()
+                )
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use crate::sdl2_interaction::input_sources::{InputAssignment, InputSource, PressedHexKeys};
+
+    #[test]
+    fn assignment() {
+        let map = BTreeMap::from([
+            ("keyboard".to_owned(), vec!["1".to_owned(), "4".to_owned()]),
+            ("Player2".to_owned(), vec!["c".to_owned(), "D".to_owned()]),
+        ]);
+        let cut = InputAssignment::try_from(map).unwrap();
+        assert_eq!(cut.get_keys(&InputSource::Keyboard), 0b10010);
+        assert_eq!(cut.get_keys(&InputSource::Controller(1)), 0x3000);
+        assert_eq!(cut.get_keys(&InputSource::Keypad(0)), u16::MAX);
+        assert_eq!(
+            BTreeMap::from(cut.clone())["player2"],
+            ["C".to_owned(), "D".to_owned()]
+        );
+        assert_eq!(
+            InputAssignment::try_from(BTreeMap::from(cut.clone())),
+            Ok(cut)
+        );
+        assert!(InputSource::parse("player0").is_err());
+        assert!(InputAssignment::try_from(BTreeMap::from([(
+            "keypad1".to_owned(),
+            vec!["G".to_owned()]
+        )]))
+        .is_err());
+    }
+
+    #[test]
+    fn pressed() {
+        let mut cut = PressedHexKeys::default();
+        assert!(cut.set(InputSource::Keyboard, 1 << 0x5));
+        assert!(cut.set(InputSource::Keypad(1), 1 << 0xC | 1 << 0xD));
+        assert!(!cut.set(InputSource::Keypad(1), 1 << 0xC | 1 << 0xD));
+        assert!(cut.set(InputSource::Controller(0), 1 << 0x1));
+        assert_eq!(cut.get_device_keys(), 1 << 0x1 | 1 << 0xC | 1 << 0xD);
+        assert_eq!(
+            cut.get_description(),
+            "keyboard 5\nplayer1 1\nkeypad2 C D\n"
+        );
+        assert!(cut.set(InputSource::Keyboard, 0));
+        assert_eq!(cut.get(&InputSource::Keyboard), 0);
+    }
+}
@@ -0,0 +1,512 @@
+//! the serial protocol of `arduino/KeypadProtocol.ino`
+//!
+//! The host starts with `HELLO VERSION`, `VERSION` being the newest protocol version it speaks.
+//! The device answers `HELLO VERSION ID_HIGH ID_LOW CAPABILITIES` with the version both speak, its
+//! id and what it can do besides reading keys. Afterwards the host sends:
+//! - `POLL`, answered with `STATUS HIGH LOW`: the pressed keys (bit `n` being key `n` of the
+//!   keypad's rows) and `STATUS_AMBIGUOUS` if keys of several rows and columns are pressed
+//! - `BUZZER ON`: turns the buzzer on (`1`) or off (`0`), only if it has `CAPABILITY_BUZZER`
+//! - `LEDS HIGH LOW`: turns on the LEDs of the set bits (in the order of the keys), only if it has
+//!   `CAPABILITY_LEDS`
+//!
+//! Devices that don't answer `HELLO` speak version 0: `LEGACY_HANDSHAKE` is answered with itself
+//! and `POLL` with `HIGH LOW`, `0xFFFF` meaning ambiguous.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::sync::mpsc::TryRecvError;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use serialport::{ClearBuffer, SerialPort, SerialPortInfo, SerialPortType};
+
+use crate::events::input::InputEvent;
+use crate::sdl2_interaction::audio_manager::AudioEvent;
+use crate::sdl2_interaction::event_manager::{AppEventReceiver, AppEventSender, IncomingEvent};
+use crate::LogError;
+
+/// the newest protocol version the emulator speaks
+pub static PROTOCOL_VERSION: u8 = 1;
+pub static HELLO: u8 = 0xFC;
+pub static LEGACY_HANDSHAKE: u8 = 0xFB;
+pub static POLL: u8 = 0x01;
+pub static BUZZER: u8 = 0x02;
+pub static LEDS: u8 = 0x03;
+pub static CAPABILITY_BUZZER: u8 = 1 << 0;
+pub static CAPABILITY_LEDS: u8 = 1 << 1;
+pub static STATUS_AMBIGUOUS: u8 = 1 << 0;
+/// the baud rate of `arduino/KeypadProtocol.ino`
+static STANDARD_BAUD_RATE: u32 = 9601;
+static TIMEOUT: Duration = Duration::from_millis(100);
+static POLL_INTERVAL: Duration = Duration::from_millis(5);
+/// the hex key of every bit of the pressed keys, in the order of the keypad's rows
+static HEX_LAYOUT: [u8; 16] = [
+    0x1, 0x2, 0x3, 0xC, 0x4, 0x5, 0x6, 0xD, 0x7, 0x8, 0x9, 0xE, 0xA, 0x0, 0xB, 0xF,
+];
+
+/// the `[keypad]` section of the config, the keypad is only used if `port` or `vid` is set so
+/// that no other serial device is written to
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KeypadSettings {
+    /// e.g. `/dev/ttyACM0` or `COM3`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub port: Option<String>,
+    /// the USB vendor id, used if there is no `port`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vid: Option<u16>,
+    /// the USB product id, any product of the vendor if missing
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pid: Option<u16>,
+    pub baud_rate: u32,
+    /// milliseconds to wait after the first failed attempt to connect, doubled after every
+    /// further one up to `max_backoff`
+    pub min_backoff: u64,
+    pub max_backoff: u64,
+}
+impl Default for KeypadSettings {
+    fn default() -> Self {
+        Self {
+            port: None,
+            vid: None,
+            pid: None,
+            baud_rate: STANDARD_BAUD_RATE,
+            min_backoff: 500,
+            max_backoff: 10_000,
+        }
+    }
+}
+impl KeypadSettings {
+    pub fn is_enabled(&self) -> bool {
+        self.port.is_some() || self.vid.is_some()
+    }
+
+    /// true if the port is the configured one or a USB device with the configured ids
+    pub fn matches(&self, info: &SerialPortInfo) -> bool {
+        match (&self.port, &info.port_type) {
+            (Some(port), _) => port == &info.port_name,
+            (None, SerialPortType::UsbPort(usb)) => {
+                self.vid == Some(usb.vid) && self.pid.is_none_or(|p| p == usb.pid)
+            }
+            _ => false,
+        }
+    }
+
+    /// the names of the ports to try, the configured port doesn't have to be listed by the OS
+    /// (e.g. a pseudo terminal)
+    pub fn find_ports(&self) -> Vec<String> {
+        match &self.port {
+            Some(port) => vec![port.clone()],
+            None => serialport::available_ports()
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|i| self.matches(i))
+                .map(|i| i.port_name)
+                .collect(),
+        }
+    }
+}
+
+/// doubles the waiting time after every failed attempt
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Backoff {
+    min: Duration,
+    max: Duration,
+    next: Duration,
+}
+impl Backoff {
+    pub fn new(min: Duration, max: Duration) -> Self {
+        Self {
+            min,
+            max: max.max(min),
+            next: min,
+        }
+    }
+
+    /// the time to wait before the next attempt
+    pub fn next_wait(&mut self) -> Duration {
+        let wait = self.next;
+        self.next = (self.next * 2).min(self.max);
+        wait
+    }
+
+    pub fn reset(&mut self) {
+        self.next = self.min;
+    }
+}
+
+/// what a keypad told about itself in the handshake
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DeviceInfo {
+    /// 0 if the device doesn't answer `HELLO`
+    pub version: u8,
+    pub id: u16,
+    pub capabilities: u8,
+}
+impl DeviceInfo {
+    pub fn has(&self, capability: u8) -> bool {
+        self.capabilities & capability != 0
+    }
+}
+impl fmt::Display for DeviceInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "id {:#06x}, protocol version {}", self.id, self.version)?;
+        if self.has(CAPABILITY_BUZZER) {
+            write!(f, ", buzzer")?;
+        }
+        if self.has(CAPABILITY_LEDS) {
+            write!(f, ", LEDs")?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum KeypadEvent {
+    /// the slot (see `InputSource::Keypad`), the port and the device
+    Connected(usize, String, DeviceInfo),
+    Disconnected(usize, String),
+    /// lights the keys the game reads, bit `n` being hex key `n`
+    SetLeds(u16),
+}
+
+/// an arduino keypad running `arduino/KeypadProtocol.ino`
+pub struct Keypad {
+    name: String,
+    /// the number of the keypad in its input events
+    slot: usize,
+    port: Box<dyn SerialPort>,
+    device: DeviceInfo,
+    pressed: u16,
+    buzzer: bool,
+    leds: u16,
+}
+impl Keypad {
+    /// opens the port and checks that a keypad answers the handshake
+    pub fn open(name: &str, baud_rate: u32) -> Result<Self, String> {
+        let port = serialport::new(name, baud_rate)
+            .timeout(TIMEOUT)
+            .open()
+            .map_err(|e| e.to_string())?;
+        Self::connect(name, port)
+    }
+
+    /// checks that a keypad answers the handshake on an open port, falls back to version 0 if it
+    /// doesn't answer `HELLO`
+    pub fn connect(name: &str, mut port: Box<dyn SerialPort>) -> Result<Self, String> {
+        let device = match Self::hello(&mut *port)? {
+            Some(device) => device,
+            None => {
+                port.clear(ClearBuffer::All).map_err(|e| e.to_string())?;
+                let mut byte = [0];
+                port.write_all(&[LEGACY_HANDSHAKE])
+                    .and_then(|_| port.read_exact(&mut byte))
+                    .map_err(|e| e.to_string())?;
+                if byte[0] != LEGACY_HANDSHAKE {
+                    return Err(format!("unexpected handshake {:#04x}", byte[0]));
+                }
+                DeviceInfo {
+                    version: 0,
+                    id: 0,
+                    capabilities: 0,
+                }
+            }
+        };
+        Ok(Self {
+            name: name.to_owned(),
+            slot: 0,
+            port,
+            device,
+            pressed: 0,
+            buzzer: false,
+            leds: 0,
+        })
+    }
+
+    /// `None` if the device doesn't answer
+    fn hello(port: &mut dyn SerialPort) -> Result<Option<DeviceInfo>, String> {
+        let mut bytes = [0; 5];
+        match port
+            .write_all(&[HELLO, PROTOCOL_VERSION])
+            .and_then(|_| port.read_exact(&mut bytes))
+        {
+            Ok(()) if bytes[0] == HELLO => {}
+            Ok(()) => return Ok(None),
+            Err(e) if e.kind() == io::ErrorKind::TimedOut => return Ok(None),
+            Err(e) => return Err(e.to_string()),
+        }
+        let [_, version, id_high, id_low, capabilities] = bytes;
+        if version == 0 || version > PROTOCOL_VERSION {
+            return Err(format!("unsupported protocol version {}", version));
+        }
+        Ok(Some(DeviceInfo {
+            version,
+            id: u16::from_be_bytes([id_high, id_low]),
+            capabilities,
+        }))
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn get_device(&self) -> &DeviceInfo {
+        &self.device
+    }
+
+    pub fn get_slot(&self) -> usize {
+        self.slot
+    }
+
+    /// the number of the keypad in its input events, 0 by default
+    pub fn set_slot(&mut self, slot: usize) {
+        self.slot = slot;
+    }
+
+    fn changes(&self, before: u16, now: u16) -> Vec<InputEvent> {
+        (0..16)
+            .filter(|b| (now & !before) & (1 << b) != 0)
+            .map(|b| InputEvent::KeypadDown(self.slot, HEX_LAYOUT[b]))
+            .chain(
+                (0..16)
+                    .filter(|b| (before & !now) & (1 << b) != 0)
+                    .map(|b| InputEvent::KeypadUp(self.slot, HEX_LAYOUT[b])),
+            )
+            .collect()
+    }
+
+    /// asks for the pressed keys and returns the keys pressed and released since the last poll,
+    /// an error means the keypad is disconnected
+    pub fn poll(&mut self) -> io::Result<Vec<InputEvent>> {
+        self.port.write_all(&[POLL])?;
+        let (ambiguous, pressed) = if self.device.version == 0 {
+            let mut bytes = [0, 0];
+            self.port.read_exact(&mut bytes)?;
+            let pressed = u16::from_be_bytes(bytes);
+            (pressed == u16::MAX, pressed)
+        } else {
+            let mut bytes = [0, 0, 0];
+            self.port.read_exact(&mut bytes)?;
+            (
+                bytes[0] & STATUS_AMBIGUOUS != 0,
+                u16::from_be_bytes([bytes[1], bytes[2]]),
+            )
+        };
+        if ambiguous {
+            // keys of several rows and columns are pressed, the keypad can't tell which ones
+            return Ok(Vec::new());
+        }
+        let changes = self.changes(self.pressed, pressed);
+        self.pressed = pressed;
+        Ok(changes)
+    }
+
+    /// the key ups of all pressed keys
+    pub fn release_all(&mut self) -> Vec<InputEvent> {
+        let changes = self.changes(self.pressed, 0);
+        self.pressed = 0;
+        changes
+    }
+
+    /// does nothing if the keypad has no buzzer
+    pub fn set_buzzer(&mut self, on: bool) -> io::Result<()> {
+        if self.device.has(CAPABILITY_BUZZER) && on != self.buzzer {
+            self.port.write_all(&[BUZZER, on as u8])?;
+            self.buzzer = on;
+        }
+        Ok(())
+    }
+
+    /// lights the given hex keys (bit `n` being hex key `n`), does nothing if the keypad has no
+    /// LEDs
+    pub fn set_leds(&mut self, hex_keys: u16) -> io::Result<()> {
+        if self.device.has(CAPABILITY_LEDS) && hex_keys != self.leds {
+            let leds = (0..16)
+                .filter(|b| hex_keys & (1 << HEX_LAYOUT[*b]) != 0)
+                .fold(0u16, |leds, b| leds | 1 << b);
+            let [high, low] = leds.to_be_bytes();
+            self.port.write_all(&[LEDS, high, low])?;
+            self.leds = hex_keys;
+        }
+        Ok(())
+    }
+}
+
+/// the buzzer and LED state every keypad is told
+#[derive(Clone, Copy, Debug, Default)]
+struct KeypadOutputs {
+    buzzer: bool,
+    leds: u16,
+}
+
+/// scans for the configured keypads (retrying every setting with its own backoff), connects
+/// every keypad found in its own thread and forwards the buzzer and LED events of the receiver to
+/// all of them; the scan stops once the receiver is disconnected, a keypad's thread once its
+/// events can't be sent anymore
+///
+/// the first keypad of setting `n` gets slot `n`, further keypads of a setting get the free
+/// slots after the last setting
+pub fn spawn(settings: Vec<KeypadSettings>, sender: AppEventSender, receiver: AppEventReceiver) {
+    thread::spawn(move || {
+        let mut scans = settings
+            .into_iter()
+            .map(|s| {
+                let backoff = Backoff::new(
+                    Duration::from_millis(s.min_backoff),
+                    Duration::from_millis(s.max_backoff),
+                );
+                (s, backoff, Instant::now())
+            })
+            .collect::<Vec<_>>();
+        let extra_slots = scans.len();
+        let outputs = Arc::new(Mutex::new(KeypadOutputs::default()));
+        // the port of every connected keypad by its slot
+        let connected = Arc::new(Mutex::new(BTreeMap::<usize, String>::new()));
+        loop {
+            loop {
+                match receiver.try_recv() {
+                    Ok(IncomingEvent::Audio(AudioEvent::Buzz(b))) => {
+                        outputs.lock().unwrap().buzzer = b
+                    }
+                    Ok(IncomingEvent::Keypad(KeypadEvent::SetLeds(l))) => {
+                        outputs.lock().unwrap().leds = l
+                    }
+                    Ok(_) => {}
+                    Err(TryRecvError::Empty) => break,
+                    Err(TryRecvError::Disconnected) => return,
+                }
+            }
+            for (index, (settings, backoff, next_scan)) in scans.iter_mut().enumerate() {
+                if Instant::now() < *next_scan {
+                    continue;
+                }
+                let ports = settings
+                    .find_ports()
+                    .into_iter()
+                    .filter(|p| !connected.lock().unwrap().values().any(|c| c == p))
+                    .collect::<Vec<String>>();
+                let mut failed = !ports.is_empty();
+                for port in ports {
+                    if let Ok(mut keypad) = Keypad::open(&port, settings.baud_rate) {
+                        let mut connected_ports = connected.lock().unwrap();
+                        let slot = [index]
+                            .into_iter()
+                            .chain(extra_slots..)
+                            .find(|s| !connected_ports.contains_key(s))
+                            .unwrap_or_default();
+                        connected_ports.insert(slot, port);
+                        keypad.set_slot(slot);
+                        spawn_session(
+                            keypad,
+                            sender.clone(),
+                            Arc::clone(&outputs),
+                            Arc::clone(&connected),
+                        );
+                        failed = false;
+                    }
+                }
+                if failed {
+                    *next_scan = Instant::now() + backoff.next_wait();
+                } else {
+                    // new keypads are searched for at the minimal backoff
+                    backoff.reset();
+                    *next_scan = Instant::now() + backoff.min;
+                }
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+    });
+}
+
+/// polls the keypad until it is disconnected
+fn spawn_session(
+    mut keypad: Keypad,
+    sender: AppEventSender,
+    outputs: Arc<Mutex<KeypadOutputs>>,
+    connected: Arc<Mutex<BTreeMap<usize, String>>>,
+) {
+    thread::spawn(move || {
+        let slot = keypad.get_slot();
+        let name = keypad.get_name().to_owned();
+        let connected_event = KeypadEvent::Connected(slot, name.clone(), *keypad.get_device());
+        let mut sending = sender.send(IncomingEvent::Keypad(connected_event)).is_ok();
+        while sending {
+            let KeypadOutputs { buzzer, leds } = *outputs.lock().unwrap();
+            match keypad
+                .set_buzzer(buzzer)
+                .and_then(|_| keypad.set_leds(leds))
+                .and_then(|_| keypad.poll())
+            {
+                Ok(events) => {
+                    sending = events
+                        .into_iter()
+                        .all(|e| sender.send(IncomingEvent::Input(e)).is_ok());
+                }
+                Err(_) => break,
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+        if sending {
+            for event in keypad
+                .release_all()
+                .into_iter()
+                .map(IncomingEvent::Input)
+                .chain([IncomingEvent::Keypad(KeypadEvent::Disconnected(slot, name))])
+            {
+                sender
+                    .send(event)
+                    .elog("sending the keypad's disconnection");
+            }
+        }
+        connected.lock().unwrap().remove(&slot);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use serialport::{SerialPortInfo, SerialPortType, UsbPortInfo};
+
+    use crate::sdl2_interaction::keypad::{Backoff, KeypadSettings};
+
+    #[test]
+    fn port_filter() {
+        let usb = |vid, pid| SerialPortInfo {
+            port_name: "/dev/ttyACM0".to_owned(),
+            port_type: SerialPortType::UsbPort(UsbPortInfo {
+                vid,
+                pid,
+                serial_number: None,
+                manufacturer: None,
+                product: None,
+            }),
+        };
+        let mut cut = KeypadSettings::default();
+        assert!(!cut.is_enabled());
+        assert!(!cut.matches(&usb(0x2341, 0x43)));
+        cut.vid = Some(0x2341);
+        assert!(cut.is_enabled());
+        assert!(cut.matches(&usb(0x2341, 0x43)));
+        assert!(!cut.matches(&usb(0x1A86, 0x43)));
+        cut.pid = Some(0x01);
+        assert!(!cut.matches(&usb(0x2341, 0x43)));
+        cut.port = Some("/dev/ttyACM0".to_owned());
+        assert!(cut.matches(&usb(0x1A86, 0x43)));
+        assert_eq!(cut.find_ports(), ["/dev/ttyACM0"]);
+    }
+
+    #[test]
+    fn backoff() {
+        let mut cut = Backoff::new(Duration::from_millis(100), Duration::from_millis(300));
+        assert_eq!(cut.next_wait(), Duration::from_millis(100));
+        assert_eq!(cut.next_wait(), Duration::from_millis(200));
+        assert_eq!(cut.next_wait(), Duration::from_millis(300));
+        assert_eq!(cut.next_wait(), Duration::from_millis(300));
+        cut.reset();
+        assert_eq!(cut.next_wait(), Duration::from_millis(100));
+    }
+}
@@ -0,0 +1,178 @@
+//! the serial protocol of `arduino/LedMatrixProtocol.ino`
+//!
+//! The host sends every changed frame (and the current one every second, so that a reset device
+//! gets a picture) as `FRAME_START WIDTH HEIGHT DATA CHECKSUM`: `DATA` are the rows from top to
+//! bottom, every row being `ceil(WIDTH / 8)` bytes with the most significant bit as the leftmost
+//! pixel, and `CHECKSUM` is the xor of all bytes of `DATA`. The device ignores frames of another
+//! size or with a wrong checksum and waits for the next `FRAME_START`.
+
+use std::io::Write;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use serialport::SerialPort;
+
+use crate::sdl2_interaction::event_manager::{AppEventReceiver, IncomingEvent};
+use crate::sdl2_interaction::framebuffer::{Framebuffer, HEIGHT, WIDTH};
+use crate::sdl2_interaction::keypad::Backoff;
+use crate::sdl2_interaction::output::ScreenEvent;
+use crate::{LogInfo, LogWarning};
+
+pub static FRAME_START: u8 = 0xC8;
+static TIMEOUT: Duration = Duration::from_millis(100);
+/// how often an unchanged frame is sent again
+static RESEND_INTERVAL: Duration = Duration::from_secs(1);
+
+/// the `[led_matrix]` section of the config, the matrix is only used if `port` is set
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LedMatrixSettings {
+    /// e.g. `/dev/ttyACM1` or `COM4`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub port: Option<String>,
+    pub baud_rate: u32,
+    /// the size of the matrix in LEDs, the screen is scaled down to it (a LED is on if any of its
+    /// pixels is on)
+    pub width: u8,
+    pub height: u8,
+    /// milliseconds to wait after the first failed attempt to connect, doubled after every
+    /// further one up to `max_backoff`
+    pub min_backoff: u64,
+    pub max_backoff: u64,
+}
+impl Default for LedMatrixSettings {
+    fn default() -> Self {
+        Self {
+            port: None,
+            baud_rate: 250_000,
+            width: 64,
+            height: 32,
+            min_backoff: 500,
+            max_backoff: 10_000,
+        }
+    }
+}
+impl LedMatrixSettings {
+    pub fn is_enabled(&self) -> bool {
+        self.port.is_some()
+    }
+}
+
+/// the message of a frame for a matrix of `width`x`height` LEDs
+pub fn encode_frame(framebuffer: &Framebuffer, width: u8, height: u8) -> Vec<u8> {
+    let (width, height) = (width as usize, height as usize);
+    let block_width = (WIDTH / width.max(1)).max(1);
+    let block_height = (HEIGHT / height.max(1)).max(1);
+    let row_bytes = width.div_ceil(8);
+    let mut data = vec![0; row_bytes * height];
+    for (x, y) in framebuffer.iter_on() {
+        let (x, y) = (x / block_width, y / block_height);
+        if x < width && y < height {
+            data[y * row_bytes + x / 8] |= 0x80 >> (x % 8);
+        }
+    }
+    let checksum = data.iter().fold(0, |c, b| c ^ b);
+    [FRAME_START, width as u8, height as u8]
+        .into_iter()
+        .chain(data)
+        .chain([checksum])
+        .collect()
+}
+
+/// sends the frames of the receiver to the matrix (reconnecting with a backoff), the thread stops
+/// once the receiver is disconnected
+pub fn spawn(settings: LedMatrixSettings, receiver: AppEventReceiver) {
+    thread::spawn(move || {
+        let name = match &settings.port {
+            Some(name) => name.clone(),
+            None => return,
+        };
+        let mut backoff = Backoff::new(
+            Duration::from_millis(settings.min_backoff),
+            Duration::from_millis(settings.max_backoff),
+        );
+        let mut port: Option<Box<dyn SerialPort>> = None;
+        let mut next_attempt = Instant::now();
+        let mut last_sent: Option<(Vec<u8>, Instant)> = None;
+        while let Ok(event) = receiver.recv() {
+            // only the newest frame is sent if the matrix is slower than the emulator
+            let mut newest = None;
+            for event in [event].into_iter().chain(receiver.try_iter()) {
+                if let IncomingEvent::Screen(ScreenEvent::Frame(framebuffer, _)) = event {
+                    newest = Some(framebuffer);
+                }
+            }
+            let framebuffer = match newest {
+                Some(framebuffer) => framebuffer,
+                None => continue,
+            };
+            if port.is_none() && Instant::now() >= next_attempt {
+                match serialport::new(&name, settings.baud_rate)
+                    .timeout(TIMEOUT)
+                    .open()
+                {
+                    Ok(opened) => {
+                        format!("LED matrix `{}` connected.", name).as_str().log();
+                        backoff.reset();
+                        port = Some(opened);
+                        last_sent = None;
+                    }
+                    Err(_) => next_attempt = Instant::now() + backoff.next_wait(),
+                }
+            }
+            let opened = match port.as_mut() {
+                Some(opened) => opened,
+                None => continue,
+            };
+            let frame = encode_frame(&framebuffer, settings.width, settings.height);
+            if last_sent
+                .as_ref()
+                .is_some_and(|(f, t)| *f == frame && t.elapsed() < RESEND_INTERVAL)
+            {
+                continue;
+            }
+            if opened.write_all(&frame).is_ok() {
+                last_sent = Some((frame, Instant::now()));
+            } else {
+                format!("LED matrix `{}` disconnected.", name)
+                    .as_str()
+                    .wlog();
+                port = None;
+                next_attempt = Instant::now() + backoff.next_wait();
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sdl2_interaction::framebuffer::Framebuffer;
+    use crate::sdl2_interaction::led_matrix::{encode_frame, FRAME_START};
+
+    #[test]
+    fn frames() {
+        let mut framebuffer = Framebuffer::new(2);
+        // the top left LED (twice, as a low resolution pixel would be set)
+        framebuffer.set(0, 0, 0, true);
+        framebuffer.set(0, 1, 1, true);
+        // the LED at (9, 1) from a pixel of the second plane
+        framebuffer.set(1, 19, 3, true);
+        // the bottom right LED
+        framebuffer.set(0, 127, 63, true);
+        let cut = encode_frame(&framebuffer, 64, 32);
+        assert_eq!(cut.len(), 3 + 8 * 32 + 1);
+        assert_eq!(cut[..3], [FRAME_START, 64, 32]);
+        let data = &cut[3..cut.len() - 1];
+        assert_eq!(data[0], 0b1000_0000);
+        assert_eq!(data[8 + 1], 0b0100_0000);
+        assert_eq!(data[8 * 32 - 1], 0b0000_0001);
+        assert_eq!(data.iter().filter(|b| **b != 0).count(), 3);
+        assert_eq!(cut[cut.len() - 1], 0b1000_0000 ^ 0b0100_0000 ^ 0b0000_0001);
+
+        // a matrix wider than the screen and a width that isn't a multiple of 8
+        let cut = encode_frame(&framebuffer, 130, 64);
+        assert_eq!(cut.len(), 3 + 17 * 64 + 1);
+        assert_eq!(cut[3 + 63 * 17 + 15], 0b0000_0001);
+    }
+}
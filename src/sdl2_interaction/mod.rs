@@ -1,5 +1,20 @@
 pub mod audio_manager;
+pub mod controller;
+pub mod crt;
 pub mod event_manager;
+pub mod font;
+pub mod framebuffer;
+pub mod image;
+pub mod input_sources;
+pub mod keypad;
+pub mod led_matrix;
+pub mod osd;
 pub mod output;
+pub mod overlay;
+pub mod palette;
+pub mod persistence;
 pub mod pressed_key;
+pub mod rom_browser;
 pub mod screen;
+pub mod upscaler;
+pub mod viewport;
@@ -0,0 +1,182 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use sdl2::pixels::Color;
+
+use crate::config::settings;
+use crate::events::hotkeys::Action;
+use crate::sdl2_interaction::event_manager::IncomingEvent;
+use crate::sdl2_interaction::font::GLYPH_WIDTH;
+use crate::sdl2_interaction::framebuffer::{HEIGHT, WIDTH};
+use crate::sdl2_interaction::keypad::KeypadEvent;
+use crate::sdl2_interaction::output::ScreenEvent;
+use crate::sdl2_interaction::overlay::Overlay;
+
+/// how long a toast is shown
+static TOAST_DURATION: Duration = Duration::from_secs(3);
+/// the toasts shown at once, the oldest ones are dropped
+static MAX_TOASTS: usize = 4;
+/// the size of a character in framebuffer pixels, text has twice the resolution
+pub(crate) static CHARACTER_WIDTH: i32 = (GLYPH_WIDTH as i32 + 1) / 2;
+pub(crate) static LINE_HEIGHT: i32 = 3;
+
+/// what the emulation achieved during the last second
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Stats {
+    pub frames: u32,
+    pub instructions: u32,
+    /// in percent of the normal speed
+    pub speed: u32,
+}
+impl Stats {
+    pub fn get_line(&self) -> String {
+        format!(
+            "{} FPS  {} IPS  {}%",
+            self.frames, self.instructions, self.speed
+        )
+    }
+}
+
+/// the on-screen display: toasts about what changed, the stats line and the paused banner
+pub struct Osd {
+    toasts: VecDeque<(String, Instant)>,
+    stats: Option<Stats>,
+    show_stats: bool,
+    /// the first speed of a game isn't a change
+    speed: Option<i8>,
+}
+impl Osd {
+    pub fn new(show_stats: bool) -> Self {
+        Self {
+            toasts: VecDeque::new(),
+            stats: None,
+            show_stats,
+            speed: None,
+        }
+    }
+
+    pub fn toast(&mut self, text: impl Into<String>) {
+        self.toasts.push_back((text.into(), Instant::now()));
+        if self.toasts.len() > MAX_TOASTS {
+            self.toasts.pop_front();
+        }
+    }
+
+    /// the toasts that haven't expired, the oldest first
+    pub fn get_toasts(&mut self) -> Vec<&str> {
+        self.toasts
+            .retain(|(_, shown)| shown.elapsed() < TOAST_DURATION);
+        self.toasts.iter().map(|(text, _)| text.as_str()).collect()
+    }
+
+    /// toasts about the changes of the speed, the cheat mode and the keypads and the toasts and
+    /// stats sent to the screen
+    pub fn update(&mut self, event: &IncomingEvent) {
+        match event {
+            IncomingEvent::SetSpeed(s) if self.speed.replace(*s).is_some_and(|b| b != *s) => self
+                .toast(format!(
+                    "SPEED {}%",
+                    (settings().get_speed_factor(*s) * 100.0).round()
+                )),
+            IncomingEvent::SetCheatMode(c) => {
+                self.toast(format!("CHEAT MODE {}", if *c { "ON" } else { "OFF" }))
+            }
+            IncomingEvent::Keypad(KeypadEvent::Connected(slot, port, _)) => {
+                self.toast(format!("KEYPAD{} {} CONNECTED", slot + 1, port))
+            }
+            IncomingEvent::Keypad(KeypadEvent::Disconnected(slot, port)) => {
+                self.toast(format!("KEYPAD{} {} DISCONNECTED", slot + 1, port))
+            }
+            IncomingEvent::Screen(ScreenEvent::Toast(text)) => self.toast(text.clone()),
+            IncomingEvent::Screen(ScreenEvent::Stats(stats)) => self.stats = Some(*stats),
+            IncomingEvent::Screen(ScreenEvent::ToggleStats) => self.show_stats = !self.show_stats,
+            _ => {}
+        }
+    }
+
+    /// the stats line if it is shown and there are stats
+    pub fn get_stats_line(&self) -> Option<String> {
+        self.stats
+            .filter(|_| self.show_stats)
+            .map(|stats| stats.get_line())
+    }
+
+    /// the stats at the top right, the toasts at the bottom left and a banner across the middle
+    /// if the emulation is paused
+    pub fn draw(&mut self, overlay: &mut Overlay, paused: bool, color: Color) {
+        if let Some(line) = self.get_stats_line() {
+            let x = WIDTH as i32 - line.len() as i32 * CHARACTER_WIDTH;
+            overlay.draw_text(x, 1, &line, color);
+        }
+        let toasts = self.get_toasts().join("\n");
+        if !toasts.is_empty() {
+            let lines = toasts.lines().count() as i32;
+            overlay.draw_text(1, HEIGHT as i32 - lines * LINE_HEIGHT, &toasts, color);
+        }
+        if paused {
+            let hint = match settings().hotkeys.get_hotkeys(Action::Pause).first() {
+                Some(hotkey) => format!("{} TO RESUME", hotkey),
+                None => String::new(),
+            };
+            let top = HEIGHT as i32 / 2 - 2 * LINE_HEIGHT;
+            overlay.fill_rect(
+                0,
+                top,
+                WIDTH as u32,
+                4 * LINE_HEIGHT as u32,
+                settings().get_pause_color(),
+            );
+            for (line, text) in ["PAUSED", hint.as_str()].iter().enumerate() {
+                let x = (WIDTH as i32 - text.len() as i32 * CHARACTER_WIDTH) / 2;
+                let y = top + LINE_HEIGHT / 2 + line as i32 * (LINE_HEIGHT + 1);
+                overlay.draw_text(x, y, text, Color::BLACK);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sdl2_interaction::event_manager::IncomingEvent;
+    use crate::sdl2_interaction::osd::{Osd, Stats};
+    use crate::sdl2_interaction::output::ScreenEvent;
+
+    #[test]
+    fn toasts() {
+        let mut cut = Osd::new(false);
+        // the speed a game starts with isn't shown
+        cut.update(&IncomingEvent::SetSpeed(0));
+        cut.update(&IncomingEvent::SetSpeed(0));
+        assert!(cut.get_toasts().is_empty());
+        cut.update(&IncomingEvent::SetSpeed(2));
+        cut.update(&IncomingEvent::SetCheatMode(true));
+        cut.update(&IncomingEvent::Screen(ScreenEvent::Toast(
+            "SAVED quicksave.ch8".to_owned(),
+        )));
+        assert_eq!(
+            cut.get_toasts(),
+            ["SPEED 144%", "CHEAT MODE ON", "SAVED quicksave.ch8"]
+        );
+        for i in 0..3 {
+            cut.toast(i.to_string());
+        }
+        assert_eq!(cut.get_toasts(), ["SAVED quicksave.ch8", "0", "1", "2"]);
+    }
+
+    #[test]
+    fn stats() {
+        let mut cut = Osd::new(false);
+        let stats = Stats {
+            frames: 60,
+            instructions: 720,
+            speed: 100,
+        };
+        cut.update(&IncomingEvent::Screen(ScreenEvent::Stats(stats)));
+        assert_eq!(cut.get_stats_line(), None);
+        cut.update(&IncomingEvent::Screen(ScreenEvent::ToggleStats));
+        assert_eq!(
+            cut.get_stats_line(),
+            Some("60 FPS  720 IPS  100%".to_owned())
+        );
+    }
+}
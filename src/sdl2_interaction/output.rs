@@ -1,124 +1,92 @@
-use std::collections::HashMap;
 use std::thread;
 
-use sdl2::keyboard::Scancode;
-use sdl2::keyboard::Scancode::*;
-use sdl2::pixels::Color;
-use sdl2::rect::Rect;
+use sdl2::pixels::{Color, PixelFormatEnum};
 use sdl2::render::BlendMode;
 use sdl2::video::{FullscreenType, WindowPos};
 
+use crate::config::settings;
+use crate::emulator::FONT;
 use crate::events::app::AppEvent;
-use crate::events::input::InputEvent;
+use crate::events::plugin::SharedPlugin;
 use crate::sdl2_interaction::audio_manager::{AudioEvent, AudioManager};
+use crate::sdl2_interaction::controller::ControllerManager;
+use crate::sdl2_interaction::crt::CrtFilter;
 use crate::sdl2_interaction::event_manager::{
-    AppEventManager, AppEventReceiver, AppEventSender, Event, IncomingEvent,
+    AppEventManager, AppEventReceiver, AppEventSender, IncomingEvent,
 };
-use crate::sdl2_interaction::screen::{Chip8BoolToColor, Chip8ColorToBool, Screen};
-use crate::{LogError, PAUSE_TRANSPARENT_COLOR};
+use crate::sdl2_interaction::framebuffer::{Framebuffer, HEIGHT, WIDTH};
+use crate::sdl2_interaction::image::Image;
+use crate::sdl2_interaction::input_sources::PressedHexKeys;
+use crate::sdl2_interaction::keypad::{self, KeypadEvent, KeypadSettings};
+use crate::sdl2_interaction::osd::{Osd, Stats};
+use crate::sdl2_interaction::overlay::Overlay;
+use crate::sdl2_interaction::palette::Palette;
+use crate::sdl2_interaction::persistence::Persistence;
+use crate::sdl2_interaction::rom_browser::BrowserPage;
+use crate::sdl2_interaction::screen::Screen;
+use crate::sdl2_interaction::upscaler::UpscalerBuffers;
+use crate::sdl2_interaction::viewport::Viewport;
+use crate::{LogError, LogWarning};
 
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub enum ScreenEvent {
-    Clear,
-    Update,
     ToggleFullscreen,
-    RedrawAll(HashMap<usize, HashMap<usize, Color>>),
-    DrawPixel(usize, usize, Color),
-    ScrollDown(isize),
-    ScrollSide(isize),
-    Any,
-}
-impl Event for ScreenEvent {
-    fn is_any(&self) -> bool {
-        matches!(&self, &ScreenEvent::Any)
-    }
+    /// shows/hides the hotkeys
+    ToggleHelp,
+    /// shows/hides which input source holds which hex keys
+    ToggleInputs,
+    /// shows a message for a few seconds
+    Toast(String),
+    /// the stats of the last second
+    Stats(Stats),
+    /// shows/hides the stats
+    ToggleStats,
+    /// switches to the next `CrtFilter`
+    NextCrtFilter,
+    /// switches to the next `Upscaler`
+    NextUpscaler,
+    /// shows the rom browser instead of the game, `None` closes it
+    Browser(Option<BrowserPage>),
+    /// replaces the colors, e.g. by the palette of a rom
+    SetPalette(Palette),
+    /// a snapshot of the whole screen and its scale (2 in low resolution), published once per
+    /// frame
+    Frame(Framebuffer, usize),
 }
 
+/// the largest scale of the picture filtered by a `CrtFilter`, larger windows scale the filtered
+/// picture
+static MAX_FILTER_SCALE: u32 = 8;
+
 pub struct Output {
     app_event_sender: AppEventSender,
     screen: Screen,
 }
 
 impl Output {
+    /// opens a window of `width`x`height` times the scale of the settings
     pub fn new(
         width: u32,
         height: u32,
-        scale: u32,
         callback_receiver: AppEventReceiver,
         audio_callback_receiver: AppEventReceiver,
+        keypad_receiver: AppEventReceiver,
         app_event_sender: AppEventSender,
+        plugins: Vec<SharedPlugin>,
     ) -> Self {
-        let arduino_app_event_sender = app_event_sender.clone();
-        thread::spawn(move || {
-            use std::io::Write;
-
-            static LOOKUP_TABLE: [Scancode; 16] =
-                [Kp1, Kp2, Kp3, Kp4, Q, W, E, R, A, S, D, F, Z, X, C, V];
-
-            loop {
-                // find new ports
-                if let Ok(ports) = serialport::available_ports() {
-                    for port_info in ports {
-                        let mut byte = [0];
-                        if let Some(mut port) = serialport::new(&port_info.port_name, 9601)
-                            .timeout(std::time::Duration::from_millis(100))
-                            .open()
-                            .ok()
-                            .and_then(|mut port| {
-                                port.write(&[0xFB])
-                                    .ok()
-                                    .filter(|written| written == &1) // ensure that everything has been written
-                                    .filter(|_| matches!(port.read(&mut byte), Ok(1)))
-                                    .filter(|_| byte[0] == 0xFB)
-                                    .map(|_| port)
-                            })
-                        {
-                            println!("Arduino keyboard `{}` connected.", &port_info.port_name);
-                            let mut bytes = [0, 0];
-                            let mut pressed_before = 0;
-                            let mut currently_pressed;
-                            loop {
-                                if let Err(_) = port
-                                    .write(&[0x01])
-                                    .and_then(|_| port.read_exact(&mut bytes))
-                                {
-                                    break;
-                                }
-                                currently_pressed = u16::from_be_bytes(bytes);
-                                if currently_pressed == u16::MAX {
-                                    println!("Only press keys of one row/col.");
-                                    continue;
-                                }
-                                let holding = pressed_before & currently_pressed;
-                                let just_released = !holding & pressed_before;
-                                let just_pressed = !holding & currently_pressed;
-                                for key_event in (0..16)
-                                    .filter(|b| (just_pressed & (1 << b)) != 0)
-                                    .map(|n| LOOKUP_TABLE[n])
-                                    .map(InputEvent::KeyDown)
-                                    .chain(
-                                        (0..16)
-                                            .filter(|b| (just_released & (1 << b)) != 0)
-                                            .map(|n| LOOKUP_TABLE[n])
-                                            .map(InputEvent::KeyUp),
-                                    )
-                                    .map(IncomingEvent::Input)
-                                {
-                                    arduino_app_event_sender
-                                        .send(key_event)
-                                        .elog("sending arduino keypresses");
-                                }
-                                pressed_before = currently_pressed;
-                            }
-                            println!("Arduino keyboard `{}` disconnected.", port_info.port_name);
-                            break;
-                        }
-                    }
-                }
-            }
-        });
+        let keypads = settings()
+            .keypads
+            .iter()
+            .filter(|k| k.is_enabled())
+            .cloned()
+            .collect::<Vec<KeypadSettings>>();
+        if !keypads.is_empty() {
+            keypad::spawn(keypads, app_event_sender.clone(), keypad_receiver);
+        }
 
+        let scale = settings().scale;
         let mut event_manager = AppEventManager::new(app_event_sender.clone());
+        let controller_event_sender = app_event_sender.clone();
 
         thread::spawn(move || {
             let sdl_context = sdl2::init().unwrap();
@@ -136,93 +104,178 @@ impl Output {
 
             let mut canvas = window.into_canvas().build().unwrap();
             canvas.set_blend_mode(BlendMode::Blend);
+            if settings().fullscreen {
+                canvas
+                    .window_mut()
+                    .set_fullscreen(FullscreenType::Desktop)
+                    .elog("entering fullscreen");
+            }
 
             let mut audio_device = AudioManager::new(&sdl_context, audio_callback_receiver);
+            let mut controllers = sdl_context.game_controller().map(ControllerManager::new);
+            if let Err(e) = &controllers {
+                format!("Controllers aren't available: {}.", e)
+                    .as_str()
+                    .wlog();
+            }
 
-            let mut scale_x = scale;
-            let mut scale_y = scale;
+            let texture_creator = canvas.texture_creator();
+            let mut texture = texture_creator
+                .create_texture_streaming(PixelFormatEnum::RGB24, WIDTH as u32, HEIGHT as u32)
+                .unwrap();
+            let mut image = Image::new(WIDTH, HEIGHT);
+            let mut palette = settings().get_palette();
+            let mut persistence = Persistence::new(settings().persistence);
+            let mut crt_filter = settings().crt_filter;
+            let mut upscaler = settings().upscaler;
+            // reused by every frame
+            let mut upscaler_buffers = UpscalerBuffers::default();
+            let mut filtered = Image::default();
 
-            let mut current_viewport = Rect::new(0, 0, width, height);
+            let mut viewport =
+                Viewport::fit(width * scale, height * scale, settings().integer_scaling);
 
-            let mut pause_overlay = false;
+            let mut paused = false;
+            let mut keymap_dialog = None;
+            let mut help_overlay = false;
+            let mut browser: Option<BrowserPage> = None;
+            let mut osd = Osd::new(settings().show_stats);
+            let mut inputs_overlay = false;
+            let mut pressed_hex_keys = PressedHexKeys::default();
 
             let mut event_pump = sdl_context.event_pump().unwrap();
             loop {
                 audio_device.update();
                 while let Ok(app_event) = callback_receiver.try_recv() {
+                    osd.update(&app_event);
                     match app_event {
-                        IncomingEvent::Pause(true) if !pause_overlay => {
-                            pause_overlay = true;
-                            canvas.set_draw_color(PAUSE_TRANSPARENT_COLOR);
+                        IncomingEvent::Pause(p) => paused = p,
+                        IncomingEvent::HexKeys(pressed) => pressed_hex_keys = pressed,
+                        IncomingEvent::KeymapDialog(d) => {
+                            keymap_dialog = d;
                             canvas
-                                .fill_rect(Rect::new(
-                                    current_viewport.x() * scale_x as i32,
-                                    current_viewport.y() * scale_y as i32,
-                                    current_viewport.width() * scale_x,
-                                    current_viewport.height() * scale_y,
-                                ))
-                                .elog("making transparent pause overlay");
+                                .window_mut()
+                                .set_title(&match d {
+                                    Some(hex) => format!(
+                                        "CHIP 8 - press the key for 0x{:X} (Tab keeps it, Esc closes)",
+                                        hex
+                                    ),
+                                    None => "CHIP 8".to_owned(),
+                                })
+                                .elog("setting title");
                         }
                         IncomingEvent::Screen(s) => match s {
-                            ScreenEvent::ScrollDown(s) => {
-                                current_viewport.y += s as i32;
-                                canvas.set_viewport(Rect::new(
-                                    current_viewport.x() * scale_x as i32,
-                                    current_viewport.y() * scale_y as i32,
-                                    current_viewport.width() * scale_x,
-                                    current_viewport.height() * scale_y,
-                                ))
-                            }
-                            ScreenEvent::ScrollSide(s) => {
-                                current_viewport.x += s as i32;
-                                canvas.set_viewport(Rect::new(
-                                    current_viewport.x() * scale_x as i32,
-                                    current_viewport.y() * scale_y as i32,
-                                    current_viewport.width() * scale_x,
-                                    current_viewport.height() * scale_y,
-                                ))
-                            }
-                            ScreenEvent::DrawPixel(x, y, c) => {
-                                canvas.set_draw_color(c);
-                                canvas
-                                    .fill_rect(Rect::new(
-                                        x as i32 * scale_x as i32,
-                                        y as i32 * scale_y as i32,
-                                        scale_x,
-                                        scale_y,
-                                    ))
-                                    .elog("drawing pixel");
-                            }
-                            ScreenEvent::RedrawAll(pix) => {
-                                pause_overlay = false;
+                            ScreenEvent::Frame(framebuffer, screen_scale) => {
+                                persistence.render(&framebuffer, &palette, image.get_pixels_mut());
+                                let mut frame =
+                                    upscaler.apply(&image, screen_scale, &mut upscaler_buffers);
+                                if crt_filter != CrtFilter::None {
+                                    // filters need about the pixels of the window
+                                    let height = viewport.get_scale().clamp(1, MAX_FILTER_SCALE)
+                                        as usize
+                                        * HEIGHT;
+                                    frame.scale_into(
+                                        (height / frame.get_height()).max(1),
+                                        &mut filtered,
+                                    );
+                                    let filter_scale = filtered.get_height() / HEIGHT;
+                                    crt_filter.apply(&mut filtered, filter_scale);
+                                    frame = &filtered;
+                                }
+                                let query = texture.query();
+                                if (query.width as usize, query.height as usize)
+                                    != (frame.get_width(), frame.get_height())
+                                {
+                                    texture = texture_creator
+                                        .create_texture_streaming(
+                                            PixelFormatEnum::RGB24,
+                                            frame.get_width() as u32,
+                                            frame.get_height() as u32,
+                                        )
+                                        .unwrap();
+                                }
+                                texture
+                                    .update(None, frame.get_pixels(), frame.get_pitch())
+                                    .elog("uploading frame");
+                                let destination = viewport.get_rect();
                                 canvas.set_draw_color(Color::BLACK);
                                 canvas.clear();
-                                canvas.set_draw_color(Color::WHITE);
                                 canvas
-                                    .fill_rects(
-                                        &pix.iter()
-                                            .flat_map(|(y, r)| {
-                                                r.iter().filter(|(_, p)| **p == Color::WHITE).map(
-                                                    move |(x, _)| {
-                                                        Rect::new(
-                                                            (*x as u32 * scale_x) as i32,
-                                                            (*y as u32 * scale_y) as i32,
-                                                            scale_x,
-                                                            scale_y,
-                                                        )
-                                                    },
-                                                )
-                                            })
-                                            .collect::<Vec<Rect>>()[..],
-                                    )
-                                    .elog("redrawing all");
-                            }
-                            ScreenEvent::Update => canvas.present(),
-                            ScreenEvent::Clear => {
-                                canvas.set_draw_color(Color::BLACK);
-                                canvas.clear();
+                                    .copy(&texture, None, destination)
+                                    .elog("drawing frame");
+                                let mut overlay = Overlay::new(&mut canvas, &viewport);
+                                for plugin in plugins.iter() {
+                                    plugin.lock().unwrap().draw_overlay(&mut overlay);
+                                }
+                                if keymap_dialog.is_some() {
+                                    canvas.set_draw_color(settings().get_pause_color());
+                                    canvas
+                                        .fill_rect(destination)
+                                        .elog("making transparent keymap dialog overlay");
+                                }
+                                if help_overlay {
+                                    let mut overlay = Overlay::new(&mut canvas, &viewport);
+                                    overlay.fill_rect(
+                                        0,
+                                        0,
+                                        width,
+                                        height,
+                                        settings().get_pause_color(),
+                                    );
+                                    overlay.draw_text(
+                                        1,
+                                        1,
+                                        &format!("HOTKEYS\n\n{}", settings().hotkeys.get_help()),
+                                        palette.get_color(u8::MAX),
+                                    );
+                                }
+                                if inputs_overlay {
+                                    Overlay::new(&mut canvas, &viewport).draw_text(
+                                        1,
+                                        1,
+                                        &pressed_hex_keys.get_description(),
+                                        palette.get_color(u8::MAX),
+                                    );
+                                }
+                                if let Some(page) = &browser {
+                                    page.draw(
+                                        &mut Overlay::new(&mut canvas, &viewport),
+                                        palette.get_color(u8::MAX),
+                                    );
+                                }
+                                osd.draw(
+                                    &mut Overlay::new(&mut canvas, &viewport),
+                                    paused && keymap_dialog.is_none() && browser.is_none(),
+                                    palette.get_color(u8::MAX),
+                                );
+                                if let Some(hex) = keymap_dialog {
+                                    let sprite = &FONT[hex as usize * 5..][..5];
+                                    Overlay::new(&mut canvas, &viewport).draw_sprite(
+                                        (WIDTH as i32 - 16) / 2,
+                                        (HEIGHT as i32 - 20) / 2,
+                                        sprite,
+                                        4,
+                                        palette.get_color(u8::MAX),
+                                    );
+                                }
                                 canvas.present();
                             }
+                            ScreenEvent::ToggleHelp => help_overlay = !help_overlay,
+                            // handled by the OSD
+                            ScreenEvent::Toast(_)
+                            | ScreenEvent::Stats(_)
+                            | ScreenEvent::ToggleStats => {}
+                            ScreenEvent::ToggleInputs => inputs_overlay = !inputs_overlay,
+                            ScreenEvent::SetPalette(p) => palette = p,
+                            ScreenEvent::Browser(b) => browser = b,
+                            ScreenEvent::NextCrtFilter => {
+                                crt_filter = crt_filter.next();
+                                osd.toast(format!("CRT FILTER {:?}", crt_filter));
+                            }
+                            ScreenEvent::NextUpscaler => {
+                                upscaler = upscaler.next();
+                                osd.toast(format!("UPSCALER {:?}", upscaler));
+                            }
                             ScreenEvent::ToggleFullscreen => {
                                 if canvas.window().fullscreen_state() == FullscreenType::Desktop {
                                     canvas
@@ -249,21 +302,32 @@ impl Output {
                                         .elog("entering fullscreen");
                                 }
                             }
-                            _ => {}
                         },
                         IncomingEvent::App(AppEvent::WindowSizeChange(w, h)) => {
-                            scale_x = w as u32 / width;
-                            scale_y = h as u32 / height;
+                            viewport =
+                                Viewport::fit(w as u32, h as u32, settings().integer_scaling);
                         }
                         _ => {}
                     }
                 }
                 for event in event_pump.poll_iter() {
-                    event_manager.update(event, (scale_x, scale_y));
+                    if let Ok(controllers) = controllers.as_mut() {
+                        for input_event in controllers.update(&event) {
+                            controller_event_sender
+                                .send(IncomingEvent::Input(input_event))
+                                .elog("sending controller input");
+                        }
+                    }
+                    event_manager.update(event, &viewport);
                 }
             }
         });
 
+        Self::new_headless(app_event_sender)
+    }
+
+    /// creates an `Output` without a window, audio device or keypad thread
+    pub fn new_headless(app_event_sender: AppEventSender) -> Self {
         Self {
             app_event_sender,
             screen: Screen::new(),
@@ -272,19 +336,15 @@ impl Output {
 
     pub fn clear(&mut self) {
         self.screen.clear();
-        self.send_to_app_state(ScreenEvent::Clear);
     }
 
+    /// moves the pixels to the right (or to the left if negative)
     pub fn scroll_side(&mut self, amount: isize) {
-        self.send_to_app_state(ScreenEvent::ScrollSide(amount));
-        self.screen
-            .set_scroll_side((self.screen.get_scroll_side() as isize + amount) as usize);
+        self.screen.scroll_side(amount);
     }
 
     pub fn scroll_down(&mut self, amount: isize) {
-        self.send_to_app_state(ScreenEvent::ScrollDown(amount));
-        self.screen
-            .set_scroll_down((self.screen.get_scroll_down() as isize + amount) as usize);
+        self.screen.scroll_down(amount);
     }
 
     pub fn send_to_app_state(&self, s: ScreenEvent) {
@@ -293,15 +353,17 @@ impl Output {
             .elog("sending something");
     }
 
-    pub fn redraw_all(&self) {
-        self.send_to_app_state(ScreenEvent::RedrawAll(self.screen.get_pixels()))
+    /// sends a snapshot of the screen to the window
+    pub fn publish_frame(&self) {
+        self.send_to_app_state(ScreenEvent::Frame(
+            self.screen.get_framebuffer().clone(),
+            self.screen.get_scale(),
+        ))
     }
 
     pub fn set(&mut self, x: usize, y: usize, v: bool) {
         for (x, y) in self.screen.get_pix(x, y) {
-            if self.screen.set(x, y, v.into_color()) {
-                self.send_to_app_state(ScreenEvent::DrawPixel(x, y, v.into_color()));
-            }
+            self.screen.set(x, y, v);
         }
     }
 
@@ -313,9 +375,11 @@ impl Output {
         &mut self.screen
     }
 
-    /// returns true if a pix switched from `on` -> `off`
+    /// returns true if a pix switched from `on` -> `off`, wraps around the edges of the screen
     pub fn swap(&mut self, x: usize, y: usize) -> bool {
-        let old_val = self.screen.get(x, y).into_bool();
+        let x = x % self.screen.get_width();
+        let y = y % self.screen.get_height();
+        let old_val = self.screen.get(x, y);
         self.set(x, y, !old_val);
         old_val
     }
@@ -326,6 +390,13 @@ impl Output {
             .elog("buzzing");
     }
 
+    /// lights the given hex keys on the keypad, bit `n` being hex key `n`
+    pub fn light_keys(&self, hex_keys: u16) {
+        self.app_event_sender
+            .send(IncomingEvent::Keypad(KeypadEvent::SetLeds(hex_keys)))
+            .elog("lighting keys");
+    }
+
     pub fn stop_buzz(&self) {
         self.app_event_sender
             .send(IncomingEvent::Audio(AudioEvent::Buzz(false)))
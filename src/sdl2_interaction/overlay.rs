@@ -0,0 +1,95 @@
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::Canvas;
+use sdl2::video::Window;
+
+use crate::sdl2_interaction::font::{get_glyph, GLYPH_HEIGHT, GLYPH_WIDTH};
+use crate::sdl2_interaction::viewport::Viewport;
+use crate::LogError;
+
+/// draws on top of the screen, coordinates are framebuffer pixels (128x64) and scaled to the
+/// viewport like the frame itself
+pub struct Overlay<'a> {
+    canvas: &'a mut Canvas<Window>,
+    origin: (i32, i32),
+    scale: f32,
+}
+impl<'a> Overlay<'a> {
+    pub fn new(canvas: &'a mut Canvas<Window>, viewport: &Viewport) -> Self {
+        Self {
+            canvas,
+            origin: viewport.get_origin(),
+            scale: viewport.get_exact_scale(),
+        }
+    }
+
+    /// the size of a framebuffer pixel in window pixels, fractional without integer scaling
+    pub fn get_scale(&self) -> f32 {
+        self.scale
+    }
+
+    /// the window pixels of an area in (fractional) framebuffer pixels, the edges are rounded so
+    /// that neighboring areas neither overlap nor leave gaps
+    fn to_window(&self, x: f32, y: f32, width: f32, height: f32) -> Rect {
+        let edge = |origin: i32, v: f32| origin + (v * self.scale).round() as i32;
+        let (left, top) = (edge(self.origin.0, x), edge(self.origin.1, y));
+        let (right, bottom) = (
+            edge(self.origin.0, x + width),
+            edge(self.origin.1, y + height),
+        );
+        Rect::new(
+            left,
+            top,
+            (right - left).max(1) as u32,
+            (bottom - top).max(1) as u32,
+        )
+    }
+
+    /// colors with an alpha value below 255 are blended
+    pub fn fill_rect(&mut self, x: i32, y: i32, width: u32, height: u32, color: Color) {
+        let rect = self.to_window(x as f32, y as f32, width as f32, height as f32);
+        self.canvas.set_draw_color(color);
+        self.canvas.fill_rect(rect).elog("drawing overlay");
+    }
+
+    /// draws text (`\n` starts a new line) with the built-in 3x5 font starting at the framebuffer
+    /// pixel `x`, `y`, text has twice the resolution of the framebuffer
+    pub fn draw_text(&mut self, x: i32, y: i32, text: &str, color: Color) {
+        self.canvas.set_draw_color(color);
+        for (line, characters) in text.lines().enumerate() {
+            for (column, c) in characters.chars().enumerate() {
+                let left = x as f32 + (column as u32 * (GLYPH_WIDTH + 1)) as f32 / 2.0;
+                let top = y as f32 + (line as u32 * (GLYPH_HEIGHT + 1)) as f32 / 2.0;
+                for (row, bits) in get_glyph(c).iter().enumerate() {
+                    for pixel in
+                        (0..GLYPH_WIDTH).filter(|p| bits & (1 << (GLYPH_WIDTH - 1 - p)) != 0)
+                    {
+                        let rect = self.to_window(
+                            left + pixel as f32 / 2.0,
+                            top + row as f32 / 2.0,
+                            0.5,
+                            0.5,
+                        );
+                        self.canvas.fill_rect(rect).elog("drawing text");
+                    }
+                }
+            }
+        }
+    }
+
+    /// draws the `on` pixels of a chip8 sprite (one byte per row, the most significant bit is
+    /// the leftmost pixel), each pixel being a square of `size` framebuffer pixels
+    pub fn draw_sprite(&mut self, x: i32, y: i32, sprite: &[u8], size: u32, color: Color) {
+        for (row, byte) in sprite.iter().enumerate() {
+            for column in (0..8).filter(|c| byte & (0x80 >> c) != 0) {
+                self.fill_rect(
+                    x + column * size as i32,
+                    y + row as i32 * size as i32,
+                    size,
+                    size,
+                    color,
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,93 @@
+use sdl2::pixels::Color;
+
+/// the built-in palettes by name, with colors for the pixel values of two planes (off, first
+/// plane, second plane, both planes)
+pub static THEMES: [(&str, &str); 6] = [
+    ("classic", "000000,FFFFFF,AAAAAA,555555"),
+    ("amber", "1A0E00,FFB000,B36B00,FFD580"),
+    ("green", "0A1A0A,33FF33,1F991F,B3FFB3"),
+    ("lcd", "9BBC0F,0F380F,306230,8BAC0F"),
+    ("high-contrast", "000000,FFFFFF,FFFF00,00FFFF"),
+    // the Okabe-Ito colors, distinguishable with the common kinds of color blindness
+    ("colorblind", "000000,E69F00,56B4E9,F0E442"),
+];
+
+/// the colors of the pixel values (the bits of all planes), the first one being `off`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Palette {
+    colors: Vec<Color>,
+}
+impl Default for Palette {
+    fn default() -> Self {
+        Self::parse(THEMES[0].1).unwrap()
+    }
+}
+impl Palette {
+    /// parses the name of one of the `THEMES` or comma separated `RRGGBB` colors (optionally
+    /// starting with `#`), at least two
+    pub fn parse(s: &str) -> Result<Self, String> {
+        if let Some((_, colors)) = THEMES
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(s.trim()))
+        {
+            return Self::parse(colors);
+        }
+        let colors = s
+            .split(',')
+            .map(|c| {
+                let hex = c.trim().trim_start_matches('#');
+                u32::from_str_radix(hex, 16)
+                    .ok()
+                    .filter(|_| hex.len() == 6)
+                    .map(|c| Color::RGB((c >> 16) as u8, (c >> 8) as u8, c as u8))
+                    .ok_or(format!("invalid color `{}`, expected `RRGGBB`", c.trim()))
+            })
+            .collect::<Result<Vec<Color>, String>>()?;
+        if colors.len() < 2 {
+            return Err("a palette needs at least two colors".to_owned());
+        }
+        Ok(Self { colors })
+    }
+
+    /// values without a color of their own use the last one
+    pub fn get_color(&self, value: u8) -> Color {
+        self.colors[(value as usize).min(self.colors.len() - 1)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sdl2::pixels::Color;
+
+    use crate::sdl2_interaction::palette::{Palette, THEMES};
+
+    #[test]
+    fn parse() {
+        let cut = Palette::parse("#102030, ffaa00").unwrap();
+        assert_eq!(cut.get_color(0), Color::RGB(0x10, 0x20, 0x30));
+        assert_eq!(cut.get_color(1), Color::RGB(0xFF, 0xAA, 0x00));
+        assert_eq!(cut.get_color(3), cut.get_color(1));
+        assert_eq!(Palette::parse("Classic"), Ok(Palette::default()));
+        assert_eq!(
+            Palette::default().get_color(2),
+            Color::RGB(0xAA, 0xAA, 0xAA)
+        );
+        assert!(Palette::parse("000000").is_err());
+        assert!(Palette::parse("000000,fff").is_err());
+        assert!(Palette::parse("sepia").is_err());
+    }
+
+    #[test]
+    fn themes() {
+        for (name, _) in THEMES {
+            let cut = Palette::parse(name).unwrap();
+            // every plane combination of XO-CHIP games has its own color
+            let colors = (0..4).map(|v| cut.get_color(v)).collect::<Vec<Color>>();
+            assert!(
+                (1..4).all(|i| !colors[..i].contains(&colors[i])),
+                "{}",
+                name
+            );
+        }
+    }
+}
@@ -0,0 +1,93 @@
+use sdl2::pixels::Color;
+
+use crate::sdl2_interaction::framebuffer::{Framebuffer, HEIGHT, WIDTH};
+use crate::sdl2_interaction::palette::Palette;
+
+/// glow below which a pixel is drawn as background
+static MIN_GLOW: f32 = 1.0 / 256.0;
+
+/// renders frames like a phosphor screen: pixels that turn off fade from their last color into
+/// the background, so that sprites erased and redrawn by a game don't flicker
+#[derive(Clone, Debug)]
+pub struct Persistence {
+    strength: f32,
+    /// per pixel, 1 while it is on
+    glow: Vec<f32>,
+    /// per pixel, the value it had when it was on the last time
+    last_values: Vec<u8>,
+}
+impl Persistence {
+    /// `strength` is the part of the glow a pixel keeps every frame after it turned off, 0 turns
+    /// them off at once
+    pub fn new(strength: f32) -> Self {
+        Self {
+            strength: strength.clamp(0.0, 0.99),
+            glow: vec![0.0; WIDTH * HEIGHT],
+            last_values: vec![0; WIDTH * HEIGHT],
+        }
+    }
+
+    /// writes the frame into `pixels` (RGB24, `WIDTH`x`HEIGHT`)
+    pub fn render(&mut self, framebuffer: &Framebuffer, palette: &Palette, pixels: &mut [u8]) {
+        let background = palette.get_color(0);
+        for (i, pixel) in pixels.chunks_mut(3).enumerate() {
+            let value = framebuffer.get_value(i % WIDTH, i / WIDTH);
+            let c = if value != 0 {
+                self.glow[i] = 1.0;
+                self.last_values[i] = value;
+                palette.get_color(value)
+            } else {
+                self.glow[i] *= self.strength;
+                if self.glow[i] < MIN_GLOW {
+                    self.glow[i] = 0.0;
+                }
+                let glow = self.glow[i];
+                let last = palette.get_color(self.last_values[i]);
+                let fade =
+                    |from: u8, to: u8| (from as f32 + (to as f32 - from as f32) * glow) as u8;
+                Color::RGB(
+                    fade(background.r, last.r),
+                    fade(background.g, last.g),
+                    fade(background.b, last.b),
+                )
+            };
+            pixel.copy_from_slice(&[c.r, c.g, c.b]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sdl2_interaction::framebuffer::{Framebuffer, HEIGHT, WIDTH};
+    use crate::sdl2_interaction::palette::Palette;
+    use crate::sdl2_interaction::persistence::Persistence;
+
+    #[test]
+    fn fading() {
+        let palette = Palette::parse("000000,FF8000").unwrap();
+        let mut pixels = vec![0; WIDTH * HEIGHT * 3];
+        let mut framebuffer = Framebuffer::new(1);
+        framebuffer.set(0, 1, 0, true);
+        let mut cut = Persistence::new(0.5);
+        cut.render(&framebuffer, &palette, &mut pixels);
+        assert_eq!(pixels[..6], [0, 0, 0, 0xFF, 0x80, 0]);
+
+        framebuffer.clear();
+        cut.render(&framebuffer, &palette, &mut pixels);
+        assert_eq!(pixels[3..6], [0x7F, 0x40, 0]);
+        cut.render(&framebuffer, &palette, &mut pixels);
+        assert_eq!(pixels[3..6], [0x3F, 0x20, 0]);
+        for _ in 0..8 {
+            cut.render(&framebuffer, &palette, &mut pixels);
+        }
+        assert!(pixels.iter().all(|c| *c == 0));
+
+        // without persistence pixels turn off at once
+        let mut cut = Persistence::new(0.0);
+        framebuffer.set(0, 1, 0, true);
+        cut.render(&framebuffer, &palette, &mut pixels);
+        framebuffer.clear();
+        cut.render(&framebuffer, &palette, &mut pixels);
+        assert!(pixels.iter().all(|c| *c == 0));
+    }
+}
@@ -1,4 +1,7 @@
+use std::collections::{BTreeMap, HashSet};
+
 use sdl2::keyboard::Scancode;
+use serde::{Deserialize, Serialize};
 
 static KEYBOARD_LAYOUT: [Scancode; 16] = [
     Scancode::Num1,
@@ -43,3 +46,199 @@ impl HexToScancode for u8 {
             .map(|i| KEYBOARD_LAYOUT[i])
     }
 }
+
+/// the host keys of every hex key, a hex key can have several (or no) host keys
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(
+    try_from = "BTreeMap<String, Vec<String>>",
+    into = "BTreeMap<String, Vec<String>>"
+)]
+pub struct Keymap {
+    keys: [Vec<Scancode>; 16],
+}
+impl Default for Keymap {
+    fn default() -> Self {
+        let mut keys: [Vec<Scancode>; 16] = Default::default();
+        for (i, hex) in HEX_LAYOUT.iter().enumerate() {
+            keys[*hex as usize] = vec![KEYBOARD_LAYOUT[i]];
+        }
+        Self { keys }
+    }
+}
+impl Keymap {
+    /// the given keys, missing hex keys keep their default key if it isn't given for another one
+    fn with_defaults(given: Vec<(u8, Vec<Scancode>)>) -> Self {
+        let mut keymap = Self::default();
+        for keys in keymap.keys.iter_mut() {
+            keys.retain(|k| given.iter().all(|(_, g)| !g.contains(k)));
+        }
+        for (hex, keys) in given {
+            keymap.set_keys(hex, keys);
+        }
+        keymap
+    }
+
+    /// parses lines of `HEX = KEY[, KEY...]` (e.g. `5 = W, Up`), `KEY` being an SDL key name;
+    /// see `with_defaults` for missing hex keys, `#` starts a comment
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let mut given = Vec::new();
+        for line in s
+            .lines()
+            .map(|l| l.split('#').next().unwrap_or_default().trim())
+        {
+            if line.is_empty() {
+                continue;
+            }
+            let (hex, keys) = line
+                .split_once('=')
+                .ok_or(format!("expected `HEX = KEY`, got `{}`", line))?;
+            given.push((
+                Self::parse_hex(hex)?,
+                keys.split(',')
+                    .map(Self::parse_key)
+                    .collect::<Result<_, _>>()?,
+            ));
+        }
+        Ok(Self::with_defaults(given))
+    }
+
+    pub(crate) fn parse_hex(hex: &str) -> Result<u8, String> {
+        u8::from_str_radix(hex.trim(), 16)
+            .ok()
+            .filter(|h| *h < 16)
+            .ok_or(format!("invalid hex key `{}`", hex.trim()))
+    }
+
+    fn parse_key(key: &str) -> Result<Scancode, String> {
+        Scancode::from_name(key.trim()).ok_or(format!("unknown key `{}`", key.trim()))
+    }
+
+    /// the first hex key the host key is mapped to
+    pub fn get_hex(&self, scancode: &Scancode) -> Option<u8> {
+        self.keys
+            .iter()
+            .position(|k| k.contains(scancode))
+            .map(|h| h as u8)
+    }
+
+    pub fn get_scancodes(&self, hex: u8) -> &[Scancode] {
+        &self.keys[hex as usize % 16]
+    }
+
+    /// true if any host key of the hex key is pressed
+    pub fn is_pressed(&self, hex: u8, pressed_keys: &HashSet<Scancode>) -> bool {
+        self.get_scancodes(hex)
+            .iter()
+            .any(|k| pressed_keys.contains(k))
+    }
+
+    pub fn set_keys(&mut self, hex: u8, keys: Vec<Scancode>) {
+        self.keys[hex as usize % 16] = keys;
+    }
+
+    /// makes the host key the only key of the hex key, removing it from all other hex keys
+    pub fn bind(&mut self, hex: u8, scancode: Scancode) {
+        for keys in self.keys.iter_mut() {
+            keys.retain(|k| k != &scancode);
+        }
+        self.set_keys(hex, vec![scancode]);
+    }
+}
+impl TryFrom<BTreeMap<String, Vec<String>>> for Keymap {
+    type Error = String;
+
+    /// see `with_defaults` for missing hex keys
+    fn try_from(map: BTreeMap<String, Vec<String>>) -> Result<Self, Self::Error> {
+        Ok(Self::with_defaults(
+            map.iter()
+                .map(|(hex, keys)| {
+                    Ok((
+                        Self::parse_hex(hex)?,
+                        keys.iter()
+                            .map(|k| Self::parse_key(k))
+                            .collect::<Result<_, String>>()?,
+                    ))
+                })
+                .collect::<Result<_, String>>()?,
+        ))
+    }
+}
+impl From<Keymap> for BTreeMap<String, Vec<String>> {
+    /// only the hex keys that differ from the default layout
+    fn from(keymap: Keymap) -> Self {
+        let default = Keymap::default();
+        (0..16)
+            .filter(|h| keymap.get_scancodes(*h) != default.get_scancodes(*h))
+            .map(|h| {
+                (
+                    format!("{:X}", h),
+                    keymap
+                        .get_scancodes(h)
+                        .iter()
+                        .map(|k| k.name().to_owned())
+                        .collect(),
+                )
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeMap, HashSet};
+
+    use sdl2::keyboard::Scancode;
+
+    use crate::sdl2_interaction::pressed_key::{HexToScancode, Keymap, ScancodeToHex};
+
+    #[test]
+    fn default_layout() {
+        let cut = Keymap::default();
+        for hex in 0..16 {
+            assert_eq!(
+                cut.get_scancodes(hex).first(),
+                hex.try_into_scancode().as_ref()
+            );
+            assert_eq!(cut.get_scancodes(hex)[0].try_into_hex(), Some(hex));
+        }
+    }
+
+    #[test]
+    fn parse() {
+        let cut = Keymap::parse("# arrows\n5 = W, Up\n8=Down # comment\n").unwrap();
+        assert_eq!(cut.get_scancodes(0x5), [Scancode::W, Scancode::Up]);
+        assert_eq!(cut.get_hex(&Scancode::Up), Some(0x5));
+        assert_eq!(cut.get_hex(&Scancode::Down), Some(0x8));
+        assert_eq!(cut.get_scancodes(0x1), [Scancode::Num1]);
+        assert!(cut.is_pressed(0x5, &HashSet::from([Scancode::Up])));
+        assert!(!cut.is_pressed(0x5, &HashSet::from([Scancode::Down])));
+        let cut = Keymap::parse("5 = Q").unwrap();
+        assert_eq!(cut.get_hex(&Scancode::Q), Some(0x5));
+        assert_eq!(cut.get_scancodes(0x4), []);
+        assert!(Keymap::parse("G = W").is_err());
+        assert!(Keymap::parse("5 = NoSuchKey").is_err());
+        assert!(Keymap::parse("5 = W,").is_err());
+        assert!(Keymap::parse("5 W").is_err());
+    }
+
+    #[test]
+    fn bind() {
+        let mut cut = Keymap::default();
+        cut.bind(0x5, Scancode::Num1);
+        assert_eq!(cut.get_scancodes(0x5), [Scancode::Num1]);
+        assert_eq!(cut.get_scancodes(0x1), []);
+        assert_eq!(cut.get_hex(&Scancode::W), None);
+    }
+
+    #[test]
+    fn map_round_trip() {
+        let mut cut = Keymap::default();
+        cut.set_keys(0xA, vec![Scancode::Z, Scancode::Left]);
+        let map = BTreeMap::from(cut.clone());
+        assert_eq!(
+            map,
+            BTreeMap::from([("A".to_owned(), vec!["Z".to_owned(), "Left".to_owned()])])
+        );
+        assert_eq!(Keymap::try_from(map), Ok(cut));
+    }
+}
@@ -0,0 +1,425 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use sdl2::controller::{Axis, Button};
+use sdl2::keyboard::Scancode;
+use sdl2::pixels::Color;
+
+use crate::config::{rom_hash, settings, Config};
+use crate::events::input::InputEvent;
+use crate::has_rom_extension;
+use crate::sdl2_interaction::controller::ControllerInput;
+use crate::sdl2_interaction::framebuffer::WIDTH;
+use crate::sdl2_interaction::osd::{CHARACTER_WIDTH, LINE_HEIGHT};
+use crate::sdl2_interaction::overlay::Overlay;
+
+/// the entries below the header
+static VISIBLE_ENTRIES: usize = 18;
+/// the characters of a line, longer lines are cut
+static LINE_LENGTH: usize = (WIDTH as i32 / CHARACTER_WIDTH) as usize - 1;
+
+/// how the browser is navigated with the keyboard (arrows, Page Up/Down, Enter, Backspace) and
+/// controllers (d-pad or left stick, shoulders, A, B)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Navigation {
+    Up,
+    Down,
+    PageUp,
+    PageDown,
+    /// starts the rom or opens the directory
+    Open,
+    /// goes to the parent directory
+    Back,
+    /// switches between the recent roms and the directory
+    SwitchTab,
+}
+impl Navigation {
+    pub fn from_input(input: &InputEvent) -> Option<Self> {
+        Some(match input {
+            InputEvent::KeyDown(key) => match key {
+                Scancode::Up => Navigation::Up,
+                Scancode::Down => Navigation::Down,
+                Scancode::PageUp => Navigation::PageUp,
+                Scancode::PageDown => Navigation::PageDown,
+                Scancode::Return | Scancode::KpEnter => Navigation::Open,
+                Scancode::Backspace => Navigation::Back,
+                Scancode::Left | Scancode::Right | Scancode::Tab => Navigation::SwitchTab,
+                _ => return None,
+            },
+            InputEvent::ControllerDown(_, input) => match input {
+                ControllerInput::Button(Button::DPadUp)
+                | ControllerInput::Axis(Axis::LeftY, false) => Navigation::Up,
+                ControllerInput::Button(Button::DPadDown)
+                | ControllerInput::Axis(Axis::LeftY, true) => Navigation::Down,
+                ControllerInput::Button(Button::LeftShoulder) => Navigation::PageUp,
+                ControllerInput::Button(Button::RightShoulder) => Navigation::PageDown,
+                ControllerInput::Button(Button::A) => Navigation::Open,
+                ControllerInput::Button(Button::B) => Navigation::Back,
+                ControllerInput::Button(Button::DPadLeft | Button::DPadRight)
+                | ControllerInput::Axis(Axis::LeftX, _) => Navigation::SwitchTab,
+                _ => return None,
+            },
+            _ => return None,
+        })
+    }
+}
+
+/// what the user chose in the browser
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Choice {
+    Rom(PathBuf),
+    /// the rom should be picked with the file dialog of the system
+    FileDialog,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Entry {
+    FileDialog,
+    Parent(PathBuf),
+    Directory(PathBuf),
+    Rom { path: PathBuf, title: String },
+}
+impl Entry {
+    fn get_label(&self) -> String {
+        match self {
+            Entry::FileDialog => "OPEN FILE DIALOG...".to_owned(),
+            Entry::Parent(_) => "..".to_owned(),
+            Entry::Directory(path) => format!("{}/", get_file_name(path)),
+            Entry::Rom { title, .. } => title.clone(),
+        }
+    }
+}
+
+fn get_file_name(path: &Path) -> String {
+    path.file_name()
+        .unwrap_or(path.as_os_str())
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// keeps the end of the text, e.g. of a long path
+fn cut_front(text: &str, length: usize) -> String {
+    let characters = text.chars().count();
+    text.chars()
+        .skip(characters.saturating_sub(length))
+        .collect()
+}
+
+/// what the window shows of the browser
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BrowserPage {
+    pub header: String,
+    pub entries: Vec<String>,
+    /// the index of the selected entry in `entries`
+    pub selected: usize,
+}
+impl BrowserPage {
+    /// the header at the top and the entries below, the selected one on a bar
+    pub fn draw(&self, overlay: &mut Overlay, color: Color) {
+        overlay.draw_text(1, 1, &self.header, color);
+        if self.entries.is_empty() {
+            overlay.draw_text(1, 1 + 2 * LINE_HEIGHT, "NO ROMS FOUND", color);
+        }
+        for (i, entry) in self.entries.iter().enumerate() {
+            let y = 1 + (i as i32 + 2) * LINE_HEIGHT;
+            if i == self.selected {
+                overlay.fill_rect(
+                    0,
+                    y - 1,
+                    WIDTH as u32,
+                    LINE_HEIGHT as u32,
+                    settings().get_pause_color(),
+                );
+                overlay.draw_text(1, y, entry, Color::BLACK);
+            } else {
+                overlay.draw_text(1, y, entry, color);
+            }
+        }
+    }
+}
+
+/// lists the recent roms and the roms and subdirectories of a directory, roms are shown with
+/// the titles of their config sections
+pub struct RomBrowser {
+    directory: PathBuf,
+    file_dialog: bool,
+    /// the titles of the config keyed by `rom_hash`
+    titles: HashMap<String, String>,
+    /// the `rom_hash` of every file that was listed, kept until the file is modified
+    hashes: HashMap<PathBuf, (SystemTime, String)>,
+    recent: Vec<PathBuf>,
+    show_recent: bool,
+    entries: Vec<Entry>,
+    selected: usize,
+}
+impl RomBrowser {
+    /// starts in the directory, offering the file dialog of the system if `file_dialog`
+    pub fn new(directory: PathBuf, file_dialog: bool) -> Self {
+        Self {
+            directory,
+            file_dialog,
+            titles: HashMap::new(),
+            hashes: HashMap::new(),
+            recent: Vec::new(),
+            show_recent: false,
+            entries: Vec::new(),
+            selected: 0,
+        }
+    }
+
+    /// rereads the titles, the recent roms and the directory, the recent roms are shown first
+    /// if there are any
+    pub fn open(&mut self, config: &Config) {
+        self.titles = config
+            .roms
+            .iter()
+            .filter_map(|(hash, rom)| Some((hash.clone(), rom.title.clone()?)))
+            .collect();
+        self.recent = config
+            .recent
+            .iter()
+            .filter(|p| p.is_file())
+            .cloned()
+            .collect();
+        self.show_recent = !self.recent.is_empty();
+        self.list();
+    }
+
+    /// the title of the config or the file name, files are only read if there are titles
+    fn get_title(&mut self, path: &Path) -> String {
+        if self.titles.is_empty() {
+            return get_file_name(path);
+        }
+        self.get_hash(path)
+            .and_then(|hash| self.titles.get(&hash).cloned())
+            .unwrap_or_else(|| get_file_name(path))
+    }
+
+    /// the cached hash of the file or its new one if it was modified
+    fn get_hash(&mut self, path: &Path) -> Option<String> {
+        let modified = fs::metadata(path).and_then(|m| m.modified()).ok()?;
+        match self.hashes.get(path) {
+            Some((cached, hash)) if *cached == modified => Some(hash.clone()),
+            _ => {
+                let hash = rom_hash(&fs::read(path).ok()?);
+                self.hashes
+                    .insert(path.to_owned(), (modified, hash.clone()));
+                Some(hash)
+            }
+        }
+    }
+
+    fn get_rom(&mut self, path: PathBuf) -> Entry {
+        Entry::Rom {
+            title: self.get_title(&path),
+            path,
+        }
+    }
+
+    /// fills the entries of the current tab and selects the first one
+    fn list(&mut self) {
+        self.selected = 0;
+        self.entries = if self.show_recent {
+            self.recent
+                .clone()
+                .into_iter()
+                .map(|p| self.get_rom(p))
+                .collect()
+        } else {
+            let mut directories = Vec::new();
+            let mut roms = Vec::new();
+            for path in fs::read_dir(&self.directory)
+                .into_iter()
+                .flatten()
+                .filter_map(|f| f.ok())
+                .map(|f| f.path())
+                .filter(|p| !get_file_name(p).starts_with('.'))
+            {
+                if path.is_dir() {
+                    directories.push(Entry::Directory(path));
+                } else if has_rom_extension(&path) {
+                    roms.push(self.get_rom(path));
+                }
+            }
+            directories.sort_by_key(Entry::get_label);
+            roms.sort_by_key(|r| r.get_label().to_lowercase());
+            self.file_dialog
+                .then_some(Entry::FileDialog)
+                .into_iter()
+                .chain(self.directory.parent().map(|p| Entry::Parent(p.to_owned())))
+                .chain(directories)
+                .chain(roms)
+                .collect()
+        };
+    }
+
+    fn change_directory(&mut self, directory: PathBuf) {
+        let previous = std::mem::replace(&mut self.directory, directory);
+        self.list();
+        // going back selects the directory that was left
+        if let Some(i) = self
+            .entries
+            .iter()
+            .position(|e| *e == Entry::Directory(previous.clone()))
+        {
+            self.selected = i;
+        }
+    }
+
+    /// moves the selection or opens the selected entry, returns what the user chose
+    pub fn navigate(&mut self, navigation: Navigation) -> Option<Choice> {
+        let last = self.entries.len().saturating_sub(1);
+        match navigation {
+            Navigation::Up => self.selected = self.selected.checked_sub(1).unwrap_or(last),
+            Navigation::Down if self.selected < last => self.selected += 1,
+            Navigation::Down => self.selected = 0,
+            Navigation::PageUp => self.selected = self.selected.saturating_sub(VISIBLE_ENTRIES),
+            Navigation::PageDown => self.selected = (self.selected + VISIBLE_ENTRIES).min(last),
+            Navigation::SwitchTab => {
+                self.show_recent = !self.show_recent;
+                self.list();
+            }
+            Navigation::Back if !self.show_recent => {
+                if let Some(parent) = self.directory.parent() {
+                    self.change_directory(parent.to_owned());
+                }
+            }
+            Navigation::Back => {}
+            Navigation::Open => match self.entries.get(self.selected)?.clone() {
+                Entry::FileDialog => return Some(Choice::FileDialog),
+                Entry::Parent(path) | Entry::Directory(path) => self.change_directory(path),
+                Entry::Rom { path, .. } => return Some(Choice::Rom(path)),
+            },
+        }
+        None
+    }
+
+    /// the page of the selected entry
+    pub fn get_page(&self) -> BrowserPage {
+        let first = self.selected / VISIBLE_ENTRIES * VISIBLE_ENTRIES;
+        let header = if self.show_recent {
+            "< RECENT >".to_owned()
+        } else {
+            format!(
+                "< ROMS > {}",
+                cut_front(&self.directory.to_string_lossy(), LINE_LENGTH - 9)
+            )
+        };
+        BrowserPage {
+            header,
+            entries: self
+                .entries
+                .iter()
+                .skip(first)
+                .take(VISIBLE_ENTRIES)
+                .map(|e| e.get_label().chars().take(LINE_LENGTH).collect())
+                .collect(),
+            selected: self.selected - first,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::PathBuf;
+    use std::time::{Duration, SystemTime};
+
+    use sdl2::keyboard::Scancode;
+
+    use crate::config::Config;
+    use crate::events::input::InputEvent;
+    use crate::sdl2_interaction::rom_browser::{Choice, Navigation, RomBrowser};
+
+    fn directory(name: &str) -> PathBuf {
+        let directory =
+            std::env::temp_dir().join(format!("chip8-browser-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(directory.join("games")).unwrap();
+        fs::write(directory.join("pong.ch8"), [0x12, 0x00]).unwrap();
+        fs::write(directory.join("breakout.rom"), [0x00, 0xE0]).unwrap();
+        fs::write(directory.join("notes.txt"), "").unwrap();
+        directory
+    }
+
+    #[test]
+    fn navigation() {
+        let directory = directory("navigation");
+        let mut config = Config::default();
+        config.get_rom_mut(&[0x12, 0x00]).title = Some("Pong (1 player)".to_owned());
+        let mut cut = RomBrowser::new(directory.clone(), false);
+        cut.open(&config);
+        assert_eq!(
+            cut.get_page().entries,
+            ["..", "games/", "breakout.rom", "Pong (1 player)"]
+        );
+        assert_eq!(cut.navigate(Navigation::Up), None);
+        assert_eq!(
+            cut.navigate(Navigation::Open),
+            Some(Choice::Rom(directory.join("pong.ch8")))
+        );
+        cut.navigate(Navigation::Down);
+        cut.navigate(Navigation::Down);
+        cut.navigate(Navigation::Open);
+        assert_eq!(cut.get_page().entries, [".."]);
+        cut.navigate(Navigation::Back);
+        assert_eq!(cut.get_page().selected, 1);
+
+        config.add_recent(&directory.join("breakout.rom"));
+        cut.open(&config);
+        assert_eq!(cut.get_page().header, "< RECENT >");
+        assert_eq!(cut.get_page().entries, ["breakout.rom"]);
+        cut.navigate(Navigation::SwitchTab);
+        assert_eq!(cut.get_page().entries.len(), 4);
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn modified_roms() {
+        let directory = directory("modified_roms");
+        let mut config = Config::default();
+        config.get_rom_mut(&[0x12, 0x00]).title = Some("Pong".to_owned());
+        let mut cut = RomBrowser::new(directory.clone(), false);
+        cut.open(&config);
+        assert_eq!(cut.get_page().entries[3], "Pong");
+
+        let pong = directory.join("pong.ch8");
+        fs::write(&pong, [0x12, 0x02]).unwrap();
+        fs::File::options()
+            .write(true)
+            .open(&pong)
+            .unwrap()
+            .set_modified(SystemTime::now() + Duration::from_secs(1))
+            .unwrap();
+        cut.open(&config);
+        assert_eq!(cut.get_page().entries[3], "pong.ch8");
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn file_dialog() {
+        let directory = directory("file_dialog");
+        let mut cut = RomBrowser::new(directory.clone(), true);
+        cut.open(&Config::default());
+        assert_eq!(cut.navigate(Navigation::Open), Some(Choice::FileDialog));
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn inputs() {
+        assert_eq!(
+            Navigation::from_input(&InputEvent::KeyDown(Scancode::Return)),
+            Some(Navigation::Open)
+        );
+        assert_eq!(
+            Navigation::from_input(&InputEvent::KeyUp(Scancode::Return)),
+            None
+        );
+        assert_eq!(
+            Navigation::from_input(&InputEvent::KeyDown(Scancode::W)),
+            None
+        );
+    }
+}
@@ -1,76 +1,72 @@
-use sdl2::pixels::Color;
-use std::collections::HashMap;
-
-pub trait Chip8BoolToColor {
-    fn into_color(self) -> Color;
-}
-impl Chip8BoolToColor for bool {
-    fn into_color(self) -> Color {
-        if self {
-            Color::WHITE
-        } else {
-            Color::BLACK
-        }
-    }
-}
-pub trait Chip8ColorToBool {
-    fn into_bool(self) -> bool;
-}
-impl Chip8ColorToBool for Color {
-    fn into_bool(self) -> bool {
-        self == Color::WHITE
-    }
-}
+use crate::sdl2_interaction::framebuffer::Framebuffer;
+use crate::{SCREEN_HEIGHT, SCREEN_WIDTH};
 
 pub struct Screen {
-    pixels: HashMap<usize, HashMap<usize, Color>>,
-    default_color: Color,
+    framebuffer: Framebuffer,
+    selected_planes: u8,
     scale: usize,
-    scroll_down: usize,
-    scroll_side: usize,
 }
 impl Screen {
     pub fn new() -> Self {
+        Self::with_planes(1)
+    }
+
+    pub fn with_planes(planes: usize) -> Self {
         Self {
-            pixels: HashMap::new(),
-            default_color: false.into_color(),
+            framebuffer: Framebuffer::new(planes),
+            selected_planes: 1,
             scale: 2,
-            scroll_down: 0,
-            scroll_side: 0,
         }
     }
 
-    pub fn get_pixels(&self) -> HashMap<usize, HashMap<usize, Color>> {
-        self.pixels.clone()
+    pub fn get_framebuffer(&self) -> &Framebuffer {
+        &self.framebuffer
+    }
+
+    /// `get` and `set` only affect the planes of the mask, bit `n` being plane `n`
+    pub fn select_planes(&mut self, mask: u8) {
+        self.selected_planes = mask;
+    }
+
+    pub fn get_selected_planes(&self) -> u8 {
+        self.selected_planes
+    }
+
+    fn selected(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.framebuffer.get_plane_count()).filter(|p| self.selected_planes & (1 << p) != 0)
     }
 
     pub fn clear(&mut self) {
-        self.pixels = HashMap::new();
+        self.framebuffer.clear();
     }
 
-    pub fn get(&self, x: usize, y: usize) -> Color {
-        if let Some(row) = self.pixels.get(&(y * self.scale)) {
-            if let Some(pix) = row.get(&(x * self.scale)) {
-                return *pix;
-            }
-        }
-        self.default_color
+    pub fn get(&self, x: usize, y: usize) -> bool {
+        self.selected()
+            .any(|p| self.framebuffer.get(p, x * self.scale, y * self.scale))
     }
 
-    pub fn set_scroll_side(&mut self, s: usize) {
-        self.scroll_side = s;
+    /// scrolls the selected planes down by `amount` physical pixels
+    pub fn scroll_down(&mut self, amount: isize) {
+        for plane in self.selected().collect::<Vec<usize>>() {
+            self.framebuffer.scroll_down(plane, amount.max(0) as usize);
+        }
     }
 
-    pub fn get_scroll_side(&self) -> usize {
-        self.scroll_side
+    /// scrolls the selected planes to the right (or to the left if negative)
+    pub fn scroll_side(&mut self, amount: isize) {
+        for plane in self.selected().collect::<Vec<usize>>() {
+            self.framebuffer.scroll_side(plane, amount);
+        }
     }
 
-    pub fn set_scroll_down(&mut self, s: usize) {
-        self.scroll_down = s;
+    /// the visible width in (scaled) pixels
+    pub fn get_width(&self) -> usize {
+        SCREEN_WIDTH as usize / self.scale
     }
 
-    pub fn get_scroll_down(&self) -> usize {
-        self.scroll_down
+    /// the visible height in (scaled) pixels
+    pub fn get_height(&self) -> usize {
+        SCREEN_HEIGHT as usize / self.scale
     }
 
     pub fn get_scale(&self) -> usize {
@@ -94,21 +90,53 @@ impl Screen {
         }
     }
 
-    pub fn set(&mut self, x: usize, y: usize, c: Color) -> bool {
-        if self.pixels.get(&y).is_none() {
-            self.pixels.insert(y, HashMap::new());
-        }
-        if self.pixels.get(&y).unwrap().get(&x).is_none() {
-            self.pixels
-                .get_mut(&y)
-                .unwrap()
-                .insert(x, self.default_color);
+    /// returns true if the pixel changed in any of the selected planes
+    pub fn set(&mut self, x: usize, y: usize, v: bool) -> bool {
+        let mut changed = false;
+        for plane in 0..self.framebuffer.get_plane_count() {
+            if self.selected_planes & (1 << plane) != 0 {
+                changed |= self.framebuffer.set(plane, x, y, v);
+            }
         }
-        if self.pixels.get(&y).unwrap().get(&x).unwrap() == &c {
-            false
-        } else {
-            *self.pixels.get_mut(&y).unwrap().get_mut(&x).unwrap() = c;
-            true
+        changed
+    }
+}
+impl Default for Screen {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sdl2_interaction::screen::Screen;
+
+    #[test]
+    fn scaled() {
+        let mut cut = Screen::new();
+        assert_eq!((cut.get_width(), cut.get_height()), (64, 32));
+        for (x, y) in cut.get_pix(1, 2) {
+            cut.set(x, y, true);
         }
+        assert!(cut.get(1, 2));
+        assert!(cut.get_framebuffer().get(0, 3, 5));
+        cut.set_scale(1);
+        assert_eq!((cut.get_width(), cut.get_height()), (128, 64));
+        assert!(cut.get(2, 4) && cut.get(3, 5) && !cut.get(1, 2));
+    }
+
+    #[test]
+    fn selected_planes() {
+        let mut cut = Screen::with_planes(2);
+        cut.set_scale(1);
+        cut.select_planes(0b10);
+        assert!(cut.set(0, 0, true));
+        assert!(cut.get(0, 0));
+        cut.select_planes(0b01);
+        assert!(!cut.get(0, 0));
+        cut.select_planes(0b11);
+        assert!(cut.set(0, 0, true));
+        assert!(!cut.set(0, 0, true));
+        assert_eq!(cut.get_framebuffer().get_value(0, 0), 0b11);
     }
 }
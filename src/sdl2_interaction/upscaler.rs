@@ -0,0 +1,374 @@
+use serde::{Deserialize, Serialize};
+
+use crate::sdl2_interaction::image::Image;
+
+/// smooths the edges of the pixel art while scaling it up
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Upscaler {
+    #[default]
+    None,
+    Scale2x,
+    Scale3x,
+    Epx,
+    Xbr,
+}
+impl Upscaler {
+    pub fn get_all() -> &'static [Upscaler] {
+        &[
+            Upscaler::None,
+            Upscaler::Scale2x,
+            Upscaler::Scale3x,
+            Upscaler::Epx,
+            Upscaler::Xbr,
+        ]
+    }
+
+    /// the upscaler after this one, `None` after the last one
+    pub fn next(self) -> Self {
+        let all = Self::get_all();
+        let i = all.iter().position(|u| *u == self).unwrap();
+        all[(i + 1) % all.len()]
+    }
+
+    /// upscales the picture of the framebuffer into the buffers (the picture itself is returned
+    /// without an upscaler), pictures of a scaled screen (e.g. every pixel being a 2x2 square in
+    /// low resolution) are upscaled from the screen's own resolution
+    pub fn apply<'a>(
+        &self,
+        image: &'a Image,
+        screen_scale: usize,
+        buffers: &'a mut UpscalerBuffers,
+    ) -> &'a Image {
+        let upscale = match self {
+            Upscaler::None => return image,
+            Upscaler::Scale2x => scale2x,
+            Upscaler::Scale3x => scale3x,
+            Upscaler::Epx => epx,
+            Upscaler::Xbr => xbr,
+        };
+        if screen_scale > 1 {
+            shrink(image, screen_scale, &mut buffers.shrunk);
+            upscale(&buffers.shrunk, &mut buffers.upscaled);
+        } else {
+            upscale(image, &mut buffers.upscaled);
+        }
+        &buffers.upscaled
+    }
+}
+
+/// the pictures of `Upscaler::apply`, kept between frames so they are only allocated once
+#[derive(Debug, Default)]
+pub struct UpscalerBuffers {
+    shrunk: Image,
+    upscaled: Image,
+}
+
+/// writes the picture with a pixel (the top left one) per `factor`x`factor` square
+pub fn shrink(image: &Image, factor: usize, shrunk: &mut Image) {
+    shrunk.resize(image.get_width() / factor, image.get_height() / factor);
+    for y in 0..shrunk.get_height() {
+        for x in 0..shrunk.get_width() {
+            shrunk.set(x, y, image.get(x * factor, y * factor));
+        }
+    }
+}
+
+/// the pixel at the offset of (`x`, `y`), the edges are repeated
+fn neighbor(image: &Image, x: usize, y: usize, dx: isize, dy: isize) -> [u8; 3] {
+    let clamp = |v: usize, d: isize, max: usize| (v as isize + d).clamp(0, max as isize - 1);
+    image.get(
+        clamp(x, dx, image.get_width()) as usize,
+        clamp(y, dy, image.get_height()) as usize,
+    )
+}
+
+/// calls `expand` with every pixel's 3x3 neighborhood (row by row) and writes the returned
+/// `factor`x`factor` pixels (row by row)
+fn expand(
+    image: &Image,
+    factor: usize,
+    expanded: &mut Image,
+    expand: impl Fn(&[[u8; 3]; 9]) -> Vec<[u8; 3]>,
+) {
+    expanded.resize(image.get_width() * factor, image.get_height() * factor);
+    for y in 0..image.get_height() {
+        for x in 0..image.get_width() {
+            let mut around = [[0; 3]; 9];
+            for (i, pixel) in around.iter_mut().enumerate() {
+                *pixel = neighbor(image, x, y, i as isize % 3 - 1, i as isize / 3 - 1);
+            }
+            for (i, rgb) in expand(&around).into_iter().enumerate() {
+                expanded.set(x * factor + i % factor, y * factor + i / factor, rgb);
+            }
+        }
+    }
+}
+
+/// Scale2x (AdvMAME2x)
+pub fn scale2x(image: &Image, expanded: &mut Image) {
+    expand(image, 2, expanded, |&[_, b, _, d, e, f, _, h, _]| {
+        vec![
+            if d == b && b != f && d != h { d } else { e },
+            if b == f && b != d && f != h { f } else { e },
+            if d == h && d != b && h != f { d } else { e },
+            if h == f && d != h && b != f { f } else { e },
+        ]
+    })
+}
+
+/// Scale3x (AdvMAME3x)
+pub fn scale3x(image: &Image, expanded: &mut Image) {
+    expand(image, 3, expanded, |&[a, b, c, d, e, f, g, h, i]| {
+        let top_left = d == b && b != f && d != h;
+        let top_right = b == f && b != d && f != h;
+        let bottom_left = d == h && d != b && h != f;
+        let bottom_right = h == f && d != h && b != f;
+        vec![
+            if top_left { d } else { e },
+            if (top_left && e != c) || (top_right && e != a) {
+                b
+            } else {
+                e
+            },
+            if top_right { f } else { e },
+            if (top_left && e != g) || (bottom_left && e != a) {
+                d
+            } else {
+                e
+            },
+            e,
+            if (top_right && e != i) || (bottom_right && e != c) {
+                f
+            } else {
+                e
+            },
+            if bottom_left { d } else { e },
+            if (bottom_left && e != i) || (bottom_right && e != g) {
+                h
+            } else {
+                e
+            },
+            if bottom_right { f } else { e },
+        ]
+    })
+}
+
+/// EPX as formulated by Eric Johnston, the result is the same as the one of Scale2x
+pub fn epx(image: &Image, expanded: &mut Image) {
+    expand(
+        image,
+        2,
+        expanded,
+        |&[_, top, _, left, p, right, _, bottom, _]| {
+            let neighbors = [top, right, left, bottom];
+            if neighbors
+                .iter()
+                .any(|n| neighbors.iter().filter(|m| *m == n).count() >= 3)
+            {
+                return vec![p; 4];
+            }
+            vec![
+                if left == top { top } else { p },
+                if top == right { right } else { p },
+                if bottom == left { left } else { p },
+                if right == bottom { bottom } else { p },
+            ]
+        },
+    )
+}
+
+/// the weighted YUV distance of xBR
+fn distance(a: [u8; 3], b: [u8; 3]) -> f32 {
+    let [r, g, b] = [0, 1, 2].map(|c| a[c] as f32 - b[c] as f32);
+    let y = 0.299 * r + 0.587 * g + 0.114 * b;
+    let u = -0.169 * r - 0.331 * g + 0.5 * b;
+    let v = 0.5 * r - 0.419 * g - 0.081 * b;
+    48.0 * y.abs() + 7.0 * u.abs() + 6.0 * v.abs()
+}
+
+fn blend(a: [u8; 3], b: [u8; 3]) -> [u8; 3] {
+    [0, 1, 2].map(|c| ((a[c] as u16 + b[c] as u16) / 2) as u8)
+}
+
+/// 2xBR: blends the corners of a pixel with a neighbor if they lie on an edge that is more
+/// continuous than the crossing one
+pub fn xbr(image: &Image, expanded: &mut Image) {
+    expanded.resize(image.get_width() * 2, image.get_height() * 2);
+    for y in 0..image.get_height() {
+        for x in 0..image.get_width() {
+            // the corner in the direction of (sx, sy), the rule is mirrored for the others
+            for (sx, sy) in [(-1, -1), (1, -1), (-1, 1), (1, 1)] {
+                let p = |dx: isize, dy: isize| neighbor(image, x, y, dx * sx, dy * sy);
+                let (e, f, h, i) = (p(0, 0), p(1, 0), p(0, 1), p(1, 1));
+                // the edge between f and h
+                let along = distance(e, p(1, -1))
+                    + distance(e, p(-1, 1))
+                    + distance(i, p(2, 0))
+                    + distance(i, p(0, 2))
+                    + 4.0 * distance(h, f);
+                // the edge between e and i
+                let across = distance(h, p(-1, 2))
+                    + distance(h, p(1, 2))
+                    + distance(f, p(2, 1))
+                    + distance(f, p(2, -1))
+                    + 4.0 * distance(e, i);
+                let corner = if along < across {
+                    blend(
+                        e,
+                        if distance(e, f) <= distance(e, h) {
+                            f
+                        } else {
+                            h
+                        },
+                    )
+                } else {
+                    e
+                };
+                expanded.set(
+                    x * 2 + (sx + 1) as usize / 2,
+                    y * 2 + (sy + 1) as usize / 2,
+                    corner,
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sdl2_interaction::image::Image;
+    use crate::sdl2_interaction::upscaler::{
+        epx, scale2x, scale3x, shrink, xbr, Upscaler, UpscalerBuffers,
+    };
+
+    /// `.` is black, `#` white and `+` gray
+    fn parse(art: &[&str]) -> Image {
+        let mut image = Image::new(art[0].len(), art.len());
+        for (y, row) in art.iter().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                let value = match c {
+                    '#' => 0xFF,
+                    '+' => 0x7F,
+                    _ => 0,
+                };
+                image.set(x, y, [value; 3]);
+            }
+        }
+        image
+    }
+
+    fn render(image: &Image) -> Vec<String> {
+        (0..image.get_height())
+            .map(|y| {
+                (0..image.get_width())
+                    .map(|x| match image.get(x, y) {
+                        [0xFF, 0xFF, 0xFF] => '#',
+                        [0x7F, 0x7F, 0x7F] => '+',
+                        [0, 0, 0] => '.',
+                        _ => '?',
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn upscale(upscaler: fn(&Image, &mut Image), image: &Image) -> Image {
+        let mut upscaled = Image::default();
+        upscaler(image, &mut upscaled);
+        upscaled
+    }
+
+    static TRIANGLE: [&str; 6] = ["......", ".#....", ".##...", ".###..", "......", "......"];
+
+    #[test]
+    fn scale2x_golden() {
+        assert_eq!(
+            render(&upscale(scale2x, &parse(&TRIANGLE))),
+            [
+                "............",
+                "............",
+                "..##........",
+                "..###.......",
+                "..###.......",
+                "..#####.....",
+                "..######....",
+                "...#####....",
+                "............",
+                "............",
+                "............",
+                "............"
+            ]
+        );
+    }
+
+    #[test]
+    fn scale3x_golden() {
+        assert_eq!(
+            render(&upscale(scale3x, &parse(&TRIANGLE)))[3..12],
+            [
+                "...###............",
+                "...###............",
+                "...####...........",
+                "...#####..........",
+                "...######.........",
+                "...#######........",
+                "...#########......",
+                "....########......",
+                ".....#######......"
+            ]
+        );
+    }
+
+    #[test]
+    fn epx_golden() {
+        for art in [
+            &TRIANGLE[..],
+            &[".#.", "##.", "..."],
+            &["#.#", ".#.", "#.."],
+        ] {
+            assert_eq!(upscale(epx, &parse(art)), upscale(scale2x, &parse(art)));
+        }
+    }
+
+    #[test]
+    fn xbr_golden() {
+        assert_eq!(
+            render(&upscale(xbr, &parse(&TRIANGLE)))[2..8],
+            [
+                "..++........",
+                "..##+.......",
+                "..###+......",
+                "..####+.....",
+                "..#####+....",
+                "..+####+...."
+            ]
+        );
+        // straight edges stay sharp, the corners of a square would be rounded
+        let stripe = parse(&["....", "####", "####", "...."]);
+        assert_eq!(upscale(xbr, &stripe), stripe.scale(2));
+    }
+
+    #[test]
+    fn low_resolution() {
+        let image = parse(&TRIANGLE).scale(2);
+        let mut shrunk = Image::default();
+        shrink(&image, 2, &mut shrunk);
+        assert_eq!(shrunk, parse(&TRIANGLE));
+        let mut buffers = UpscalerBuffers::default();
+        assert_eq!(
+            Upscaler::Scale2x.apply(&image, 2, &mut buffers),
+            &upscale(scale2x, &parse(&TRIANGLE))
+        );
+        // a high resolution picture that happens to consist of 2x2 squares
+        assert_eq!(
+            Upscaler::Scale2x.apply(&image, 1, &mut buffers),
+            &upscale(scale2x, &image)
+        );
+        assert!(std::ptr::eq(
+            Upscaler::None.apply(&image, 2, &mut buffers),
+            &image
+        ));
+        assert_eq!(Upscaler::Xbr.next(), Upscaler::None);
+    }
+}
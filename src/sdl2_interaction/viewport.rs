@@ -0,0 +1,104 @@
+use sdl2::rect::Rect;
+
+use crate::sdl2_interaction::framebuffer::{HEIGHT, WIDTH};
+
+/// where the framebuffer is drawn in the window: as large as possible while keeping its aspect,
+/// centered between bars
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Viewport {
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+}
+impl Viewport {
+    /// the viewport of a window of `width`x`height` pixels, only whole multiples of the
+    /// framebuffer if `integer` (but at least its own size)
+    pub fn fit(width: u32, height: u32, integer: bool) -> Self {
+        let scale = (width as f32 / WIDTH as f32).min(height as f32 / HEIGHT as f32);
+        let scale = if integer {
+            scale.floor().max(1.0)
+        } else {
+            scale
+        };
+        let (viewport_width, viewport_height) = (
+            (WIDTH as f32 * scale).round() as u32,
+            (HEIGHT as f32 * scale).round() as u32,
+        );
+        Self {
+            x: (width as i32 - viewport_width as i32) / 2,
+            y: (height as i32 - viewport_height as i32) / 2,
+            width: viewport_width,
+            height: viewport_height,
+        }
+    }
+
+    /// the destination of the framebuffer in the window
+    pub fn get_rect(&self) -> Rect {
+        Rect::new(self.x, self.y, self.width, self.height)
+    }
+
+    /// the top left corner in window pixels
+    pub fn get_origin(&self) -> (i32, i32) {
+        (self.x, self.y)
+    }
+
+    /// the whole window pixels per framebuffer pixel (at least 1)
+    pub fn get_scale(&self) -> u32 {
+        (self.width / WIDTH as u32).max(1)
+    }
+
+    /// the window pixels per framebuffer pixel, fractional without integer scaling
+    pub fn get_exact_scale(&self) -> f32 {
+        self.width as f32 / WIDTH as f32
+    }
+
+    /// the framebuffer pixel at a position of the window, `None` on the bars
+    pub fn to_framebuffer(&self, x: i32, y: i32) -> Option<(usize, usize)> {
+        let (x, y) = (x - self.x, y - self.y);
+        if x < 0 || y < 0 || x >= self.width as i32 || y >= self.height as i32 {
+            return None;
+        }
+        Some((
+            x as usize * WIDTH / self.width as usize,
+            y as usize * HEIGHT / self.height as usize,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sdl2::rect::Rect;
+
+    use crate::sdl2_interaction::viewport::Viewport;
+
+    #[test]
+    fn fit() {
+        // too high, bars above and below
+        let cut = Viewport::fit(1280, 1000, false);
+        assert_eq!(cut.get_rect(), Rect::new(0, 180, 1280, 640));
+        assert_eq!(cut.get_scale(), 10);
+        // too wide and a scale of 7.5
+        let cut = Viewport::fit(1920, 480, false);
+        assert_eq!(cut.get_rect(), Rect::new(480, 0, 960, 480));
+        assert_eq!(cut.get_scale(), 7);
+        assert_eq!(cut.get_exact_scale(), 7.5);
+        let cut = Viewport::fit(1920, 480, true);
+        assert_eq!(cut.get_rect(), Rect::new(512, 16, 896, 448));
+        // smaller than the framebuffer
+        assert_eq!(
+            Viewport::fit(100, 50, true).get_rect(),
+            Rect::new(-14, -7, 128, 64)
+        );
+    }
+
+    #[test]
+    fn mouse() {
+        let cut = Viewport::fit(1920, 480, false);
+        assert_eq!(cut.to_framebuffer(479, 100), None);
+        assert_eq!(cut.to_framebuffer(480, 0), Some((0, 0)));
+        assert_eq!(cut.to_framebuffer(480 + 15, 7), Some((2, 0)));
+        assert_eq!(cut.to_framebuffer(480 + 959, 479), Some((127, 63)));
+        assert_eq!(cut.to_framebuffer(480 + 960, 479), None);
+    }
+}
@@ -0,0 +1,169 @@
+use std::io::{Read, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serialport::{SerialPort, TTYPort};
+
+use chip8_emulator::sdl2_interaction::keypad::{
+    BUZZER, HELLO, LEDS, LEGACY_HANDSHAKE, POLL, STATUS_AMBIGUOUS,
+};
+
+/// how long `MockKeypad::wait_for` waits
+static WAIT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// what the host did to the mock keypad and what it answers
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MockState {
+    /// in the order of the keypad's rows
+    pub pressed: u16,
+    pub ambiguous: bool,
+    pub buzzer: bool,
+    /// in the order of the keypad's rows
+    pub leds: u16,
+    pub polls: usize,
+}
+
+/// a keypad speaking the protocol of `keypad` on the master side of a pseudo terminal, the host
+/// opens the slave side by its name
+pub struct MockKeypad {
+    name: String,
+    state: Arc<Mutex<MockState>>,
+    closing: Arc<AtomicBool>,
+}
+impl MockKeypad {
+    /// a device answering `HELLO` with `version` or a device only knowing the legacy handshake
+    /// if `version` is 0
+    pub fn spawn(version: u8, id: u16, capabilities: u8) -> Self {
+        let (mut master, mut slave) = TTYPort::pair().expect("creating a pseudo terminal");
+        slave
+            .set_exclusive(false)
+            .expect("sharing the pseudo terminal");
+        let name = slave.name().expect("naming the pseudo terminal");
+        // the host opens the slave side by its name
+        drop(slave);
+        let state = Arc::new(Mutex::new(MockState::default()));
+        let closing = Arc::new(AtomicBool::new(false));
+
+        let thread_state = Arc::clone(&state);
+        let thread_closing = Arc::clone(&closing);
+        thread::spawn(move || {
+            let mut command = [0];
+            loop {
+                if master.read_exact(&mut command).is_err() {
+                    if thread_closing.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    // nothing to read or no host has opened the terminal
+                    thread::sleep(Duration::from_millis(1));
+                    continue;
+                }
+                let command = command[0];
+                let answer = if command == POLL {
+                    if thread_closing.load(Ordering::SeqCst) {
+                        // the answers of all previous polls have been read
+                        break;
+                    }
+                    let mut state = thread_state.lock().unwrap();
+                    state.polls += 1;
+                    let pressed = state.pressed.to_be_bytes();
+                    match (version, state.ambiguous) {
+                        (0, true) => vec![0xFF, 0xFF],
+                        (0, false) => pressed.to_vec(),
+                        (_, ambiguous) => {
+                            let status = if ambiguous { STATUS_AMBIGUOUS } else { 0 };
+                            vec![status, pressed[0], pressed[1]]
+                        }
+                    }
+                } else if command == LEGACY_HANDSHAKE {
+                    vec![LEGACY_HANDSHAKE]
+                } else if version == 0 {
+                    // ignored, like the arguments of the commands below
+                    continue;
+                } else if command == HELLO {
+                    let mut host_version = [0];
+                    if master.read_exact(&mut host_version).is_err() {
+                        continue;
+                    }
+                    // the buzzer and the LEDs are turned off
+                    let mut state = thread_state.lock().unwrap();
+                    *state = MockState {
+                        pressed: state.pressed,
+                        ..Default::default()
+                    };
+                    let [id_high, id_low] = id.to_be_bytes();
+                    vec![
+                        HELLO,
+                        version.min(host_version[0]),
+                        id_high,
+                        id_low,
+                        capabilities,
+                    ]
+                } else if command == BUZZER {
+                    let mut on = [0];
+                    if master.read_exact(&mut on).is_ok() {
+                        thread_state.lock().unwrap().buzzer = on[0] != 0;
+                    }
+                    continue;
+                } else if command == LEDS {
+                    let mut leds = [0, 0];
+                    if master.read_exact(&mut leds).is_ok() {
+                        thread_state.lock().unwrap().leds = u16::from_be_bytes(leds);
+                    }
+                    continue;
+                } else {
+                    continue;
+                };
+                if master.write_all(&answer).is_err() {
+                    break;
+                }
+            }
+        });
+        Self {
+            name,
+            state,
+            closing,
+        }
+    }
+
+    /// the name of the port the host has to open
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn get_state(&self) -> MockState {
+        self.state.lock().unwrap().clone()
+    }
+
+    /// the keys in the order of the keypad's rows
+    pub fn set_pressed(&self, pressed: u16) {
+        self.state.lock().unwrap().pressed = pressed;
+    }
+
+    pub fn set_ambiguous(&self, ambiguous: bool) {
+        self.state.lock().unwrap().ambiguous = ambiguous;
+    }
+
+    /// waits until the state fulfills `condition`, false if it doesn't within two seconds
+    pub fn wait_for(&self, condition: impl Fn(&MockState) -> bool) -> bool {
+        let start = Instant::now();
+        while start.elapsed() < WAIT_TIMEOUT {
+            if condition(&self.state.lock().unwrap()) {
+                return true;
+            }
+            thread::sleep(Duration::from_millis(1));
+        }
+        false
+    }
+
+    /// closes the pseudo terminal instead of answering the next poll
+    pub fn disconnect(&self) {
+        self.closing.store(true, Ordering::SeqCst);
+    }
+}
+impl Drop for MockKeypad {
+    fn drop(&mut self) {
+        self.disconnect();
+    }
+}
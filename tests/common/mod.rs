@@ -0,0 +1,3 @@
+//! helpers shared by the integration tests
+
+pub mod mock_keypad;
@@ -0,0 +1,95 @@
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+static EMULATOR: &str = env!("CARGO_BIN_EXE_chip8-emulator");
+
+fn roms() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("roms")
+}
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("chip8-{}-{}", name, std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn run(args: &[&str]) -> Output {
+    Command::new(EMULATOR).args(args).output().unwrap()
+}
+
+#[test]
+fn test_roms_pass() {
+    let output = run(&["test", roms().to_str().unwrap()]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("PASS font.ch8"), "{}", stdout);
+    assert!(stdout.contains("PASS carry.ch8"), "{}", stdout);
+    assert_eq!(output.status.code(), Some(0), "{}", stdout);
+}
+
+#[test]
+fn mismatch_reports_diff() {
+    let dir = temp_dir("mismatch");
+    fs::copy(roms().join("font.ch8"), dir.join("font.ch8")).unwrap();
+    let expected = fs::read_to_string(roms().join("font.txt"))
+        .unwrap()
+        .replacen('#', ".", 1);
+    fs::write(dir.join("font.txt"), expected).unwrap();
+    fs::write(dir.join("missing.ch8"), [0x12, 0x00]).unwrap();
+
+    let output = run(&["test", dir.to_str().unwrap()]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("FAIL font.ch8"), "{}", stdout);
+    assert!(stdout.contains("1 pixels differ:\n+###."), "{}", stdout);
+    assert!(
+        stdout.contains("FAIL missing.ch8 (no expected screen)"),
+        "{}",
+        stdout
+    );
+    assert!(stdout.contains("0/2 passed"), "{}", stdout);
+    assert_eq!(output.status.code(), Some(1));
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn missing_directory() {
+    let output = run(&["test", "does/not/exist"]);
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn headless_expect() {
+    let font = roms().join("font.ch8");
+    let expected = roms().join("font.txt");
+    let output = run(&[
+        "--headless",
+        font.to_str().unwrap(),
+        "--frames",
+        "10",
+        "--expect",
+        expected.to_str().unwrap(),
+    ]);
+    assert_eq!(output.status.code(), Some(0));
+
+    let carry = roms().join("carry.ch8");
+    let output = run(&[
+        "--headless",
+        carry.to_str().unwrap(),
+        "--frames",
+        "10",
+        "--expect",
+        expected.to_str().unwrap(),
+    ]);
+    assert_eq!(output.status.code(), Some(1));
+}
+
+#[test]
+fn platform_option() {
+    let output = run(&["test", roms().to_str().unwrap(), "--platform", "chip8"]);
+    assert_eq!(output.status.code(), Some(0));
+
+    let output = run(&["test", roms().to_str().unwrap(), "--platform", "gameboy"]);
+    assert_eq!(output.status.code(), Some(2));
+}
@@ -0,0 +1,95 @@
+use chip8_emulator::events::input::InputEvent;
+use chip8_emulator::sdl2_interaction::controller::{ControllerInput, ControllerManager};
+use sdl2::controller::{Axis, Button};
+use sdl2::sys::{
+    SDL_JoystickAttachVirtual, SDL_JoystickDetachVirtual, SDL_JoystickOpen,
+    SDL_JoystickSetVirtualAxis, SDL_JoystickSetVirtualButton, SDL_JoystickType,
+};
+use sdl2::EventPump;
+
+static MAPPING: &str = "a:b0,b:b1,x:b2,y:b3,back:b4,guide:b5,start:b6,leftstick:b7,\
+rightstick:b8,leftshoulder:b9,rightshoulder:b10,dpup:b11,dpdown:b12,dpleft:b13,dpright:b14,\
+leftx:a0,lefty:a1,rightx:a2,righty:a3,lefttrigger:+a4,righttrigger:+a5";
+
+fn poll(event_pump: &mut EventPump, cut: &mut ControllerManager) -> Vec<InputEvent> {
+    event_pump
+        .poll_iter()
+        .flat_map(|e| cut.update(&e))
+        .collect()
+}
+
+/// uses a virtual joystick, so no physical controller is required (needs SDL 2.0.14 or newer)
+#[test]
+fn virtual_controller() {
+    let sdl = sdl2::init().unwrap();
+    let joysticks = sdl.joystick().unwrap();
+    let controllers = sdl.game_controller().unwrap();
+    let mut event_pump = sdl.event_pump().unwrap();
+    let mut cut = ControllerManager::new(controllers.clone());
+
+    let index = unsafe {
+        SDL_JoystickAttachVirtual(SDL_JoystickType::SDL_JOYSTICK_TYPE_GAMECONTROLLER, 6, 15, 0)
+    };
+    if index < 0 {
+        eprintln!(
+            "Skipping, virtual joysticks aren't supported: {}.",
+            sdl2::get_error()
+        );
+        return;
+    }
+    let guid = joysticks.device_guid(index as u32).unwrap();
+    controllers
+        .add_mapping(&format!("{},Virtual,{}", guid, MAPPING))
+        .unwrap();
+    assert_eq!(poll(&mut event_pump, &mut cut), []);
+    assert_eq!(cut.get_count(), 1);
+
+    let joystick = unsafe { SDL_JoystickOpen(index) };
+    unsafe {
+        SDL_JoystickSetVirtualButton(joystick, 0, 1);
+        SDL_JoystickSetVirtualAxis(joystick, 0, i16::MIN);
+    }
+    let events = poll(&mut event_pump, &mut cut);
+    assert!(events.contains(&InputEvent::ControllerDown(
+        0,
+        ControllerInput::Button(Button::A)
+    )));
+    assert!(events.contains(&InputEvent::ControllerDown(
+        0,
+        ControllerInput::Axis(Axis::LeftX, false)
+    )));
+
+    unsafe {
+        SDL_JoystickSetVirtualAxis(joystick, 0, i16::MAX);
+    }
+    assert_eq!(
+        poll(&mut event_pump, &mut cut),
+        [
+            InputEvent::ControllerDown(0, ControllerInput::Axis(Axis::LeftX, true)),
+            InputEvent::ControllerUp(0, ControllerInput::Axis(Axis::LeftX, false)),
+        ]
+    );
+
+    unsafe {
+        SDL_JoystickSetVirtualAxis(joystick, 0, 0);
+    }
+    assert_eq!(
+        poll(&mut event_pump, &mut cut),
+        [InputEvent::ControllerUp(
+            0,
+            ControllerInput::Axis(Axis::LeftX, true)
+        )]
+    );
+
+    unsafe {
+        SDL_JoystickDetachVirtual(index);
+    }
+    assert_eq!(
+        poll(&mut event_pump, &mut cut),
+        [InputEvent::ControllerUp(
+            0,
+            ControllerInput::Button(Button::A)
+        )]
+    );
+    assert_eq!(cut.get_count(), 0);
+}
@@ -0,0 +1,132 @@
+#![cfg(unix)]
+
+mod common;
+
+use std::sync::mpsc;
+use std::time::Duration;
+
+use chip8_emulator::events::input::InputEvent;
+use chip8_emulator::sdl2_interaction::audio_manager::AudioEvent;
+use chip8_emulator::sdl2_interaction::event_manager::IncomingEvent;
+use chip8_emulator::sdl2_interaction::keypad::{
+    spawn, DeviceInfo, Keypad, KeypadEvent, KeypadSettings, CAPABILITY_BUZZER, CAPABILITY_LEDS,
+};
+use common::mock_keypad::MockKeypad;
+
+#[test]
+fn versioned_protocol() {
+    let mock = MockKeypad::spawn(1, 0x0042, CAPABILITY_BUZZER | CAPABILITY_LEDS);
+    let mut cut = Keypad::open(mock.get_name(), 9601).unwrap();
+    assert_eq!(
+        cut.get_device(),
+        &DeviceInfo {
+            version: 1,
+            id: 0x0042,
+            capabilities: CAPABILITY_BUZZER | CAPABILITY_LEDS
+        }
+    );
+
+    // the first key of the second row
+    mock.set_pressed(1 << 4);
+    assert_eq!(cut.poll().unwrap(), [InputEvent::KeypadDown(0, 0x4)]);
+    mock.set_pressed(0b1111);
+    mock.set_ambiguous(true);
+    assert_eq!(cut.poll().unwrap(), []);
+    mock.set_ambiguous(false);
+    assert_eq!(
+        cut.poll().unwrap(),
+        [
+            InputEvent::KeypadDown(0, 0x1),
+            InputEvent::KeypadDown(0, 0x2),
+            InputEvent::KeypadDown(0, 0x3),
+            InputEvent::KeypadDown(0, 0xC),
+            InputEvent::KeypadUp(0, 0x4)
+        ]
+    );
+
+    cut.set_buzzer(true).unwrap();
+    // `C` is the last key of the first row, `0` the second key of the last row
+    cut.set_leds(1 << 0xC | 1 << 0x0).unwrap();
+    assert!(mock.wait_for(|s| s.buzzer && s.leds == (1 << 3 | 1 << 13)));
+    cut.set_buzzer(false).unwrap();
+    assert!(mock.wait_for(|s| !s.buzzer));
+}
+
+#[test]
+fn legacy_protocol() {
+    let mock = MockKeypad::spawn(0, 0, 0);
+    let mut cut = Keypad::open(mock.get_name(), 9601).unwrap();
+    assert_eq!(cut.get_device().version, 0);
+    assert!(!cut.get_device().has(CAPABILITY_BUZZER));
+
+    mock.set_pressed(1 << 15);
+    assert_eq!(cut.poll().unwrap(), [InputEvent::KeypadDown(0, 0xF)]);
+    mock.set_ambiguous(true);
+    assert_eq!(cut.poll().unwrap(), []);
+    // ignored without a buzzer
+    cut.set_buzzer(true).unwrap();
+    assert_eq!(cut.poll().unwrap(), []);
+    assert!(!mock.get_state().buzzer);
+}
+
+#[test]
+fn several_keypads() {
+    let first = MockKeypad::spawn(1, 7, CAPABILITY_BUZZER | CAPABILITY_LEDS);
+    let second = MockKeypad::spawn(0, 0, 0);
+    let (sender, receiver) = mpsc::channel();
+    let (keypad_sender, keypad_receiver) = mpsc::channel();
+    spawn(
+        [&first, &second]
+            .iter()
+            .map(|m| KeypadSettings {
+                port: Some(m.get_name().to_owned()),
+                ..Default::default()
+            })
+            .collect(),
+        sender,
+        keypad_receiver,
+    );
+    let next = || receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+    let next_input = || match next() {
+        IncomingEvent::Input(input) => input,
+        e => panic!("unexpected event {:?}", e),
+    };
+    let mut slots = Vec::new();
+    for _ in 0..2 {
+        match next() {
+            IncomingEvent::Keypad(KeypadEvent::Connected(slot, port, device)) => {
+                assert_eq!(device.id, if port == first.get_name() { 7 } else { 0 });
+                slots.push((port, slot));
+            }
+            e => panic!("unexpected event {:?}", e),
+        }
+    }
+    let slot = |mock: &MockKeypad| slots.iter().find(|(p, _)| p == mock.get_name()).unwrap().1;
+    // by the order of the settings, not of the connections
+    assert_eq!(slot(&first), 0);
+    assert_eq!(slot(&second), 1);
+
+    keypad_sender
+        .send(IncomingEvent::Audio(AudioEvent::Buzz(true)))
+        .unwrap();
+    keypad_sender
+        .send(IncomingEvent::Keypad(KeypadEvent::SetLeds(1 << 0x5)))
+        .unwrap();
+    assert!(first.wait_for(|s| s.buzzer && s.leds == 1 << 5));
+
+    first.set_pressed(1 << 5);
+    assert_eq!(next_input(), InputEvent::KeypadDown(slot(&first), 0x5));
+    second.set_pressed(1 << 3);
+    assert_eq!(next_input(), InputEvent::KeypadDown(slot(&second), 0xC));
+    first.disconnect();
+    assert_eq!(next_input(), InputEvent::KeypadUp(slot(&first), 0x5));
+    match next() {
+        IncomingEvent::Keypad(KeypadEvent::Disconnected(s, port)) => {
+            assert_eq!((s, port.as_str()), (slot(&first), first.get_name()))
+        }
+        e => panic!("unexpected event {:?}", e),
+    }
+    // the other keypad is still polled
+    second.set_pressed(0);
+    assert_eq!(next_input(), InputEvent::KeypadUp(slot(&second), 0xC));
+}
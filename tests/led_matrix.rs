@@ -0,0 +1,76 @@
+#![cfg(unix)]
+
+use std::io::Read;
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+use chip8_emulator::sdl2_interaction::event_manager::IncomingEvent;
+use chip8_emulator::sdl2_interaction::framebuffer::Framebuffer;
+use chip8_emulator::sdl2_interaction::led_matrix::{encode_frame, spawn, LedMatrixSettings};
+use chip8_emulator::sdl2_interaction::output::ScreenEvent;
+use serialport::{SerialPort, TTYPort};
+
+/// the bytes received within `timeout`, at most `len`
+fn receive(master: &mut TTYPort, len: usize, timeout: Duration) -> Vec<u8> {
+    let start = Instant::now();
+    let mut received = Vec::new();
+    let mut buffer = [0; 64];
+    while received.len() < len && start.elapsed() < timeout {
+        if let Ok(n) = master.read(&mut buffer[..(len - received.len()).min(64)]) {
+            received.extend_from_slice(&buffer[..n]);
+        }
+    }
+    received
+}
+
+#[test]
+fn frames() {
+    let (mut master, mut slave) = TTYPort::pair().unwrap();
+    slave.set_exclusive(false).unwrap();
+    let name = slave.name().unwrap();
+    drop(slave);
+    let (sender, receiver) = mpsc::channel();
+    spawn(
+        LedMatrixSettings {
+            port: Some(name),
+            ..Default::default()
+        },
+        receiver,
+    );
+
+    let mut framebuffer = Framebuffer::new(1);
+    framebuffer.set(0, 0, 0, true);
+    let frame = encode_frame(&framebuffer, 64, 32);
+    sender
+        .send(IncomingEvent::Screen(ScreenEvent::Frame(
+            framebuffer.clone(),
+            1,
+        )))
+        .unwrap();
+    assert_eq!(
+        receive(&mut master, frame.len(), Duration::from_secs(2)),
+        frame
+    );
+
+    // an unchanged frame isn't sent again right away
+    sender
+        .send(IncomingEvent::Screen(ScreenEvent::Frame(
+            framebuffer.clone(),
+            1,
+        )))
+        .unwrap();
+    assert_eq!(receive(&mut master, 1, Duration::from_millis(200)), []);
+
+    framebuffer.set(0, 127, 63, true);
+    let frame = encode_frame(&framebuffer, 64, 32);
+    sender
+        .send(IncomingEvent::Screen(ScreenEvent::ToggleHelp))
+        .unwrap();
+    sender
+        .send(IncomingEvent::Screen(ScreenEvent::Frame(framebuffer, 1)))
+        .unwrap();
+    assert_eq!(
+        receive(&mut master, frame.len(), Duration::from_secs(2)),
+        frame
+    );
+}